@@ -0,0 +1,297 @@
+use agendum_core::caldav::{
+    parse_objects, CalDavClient, CalendarCollection, CollectionState, DavTransport, TimeRange,
+};
+use agendum_core::dedup::SourcedEvent;
+use agendum_core::normalizer::normalize;
+use agendum_core::parser::ParseOutput;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Where `agendum caldav` reads calendars from.
+pub struct Source<'a, T> {
+    pub url: &'a str,
+    pub user: Option<&'a str>,
+    pub password: Option<String>,
+    pub transport: T,
+}
+
+/// Calendar id for `calendar:` queries: the display name, or the last path
+/// segment of the collection.
+fn calendar_id(calendar: &CalendarCollection) -> String {
+    calendar.display_name.clone().unwrap_or_else(|| {
+        calendar
+            .href
+            .trim_end_matches('/')
+            .rsplit('/')
+            .next()
+            .unwrap_or_default()
+            .to_string()
+    })
+}
+
+fn tag(calendar: &CalendarCollection, parsed: ParseOutput) -> Vec<SourcedEvent> {
+    for message in &parsed.diagnostics.parser_error_messages {
+        eprintln!("agendum: {}: {message}", calendar.href);
+    }
+    let id = calendar_id(calendar);
+    normalize(parsed.events)
+        .into_iter()
+        .map(|event| SourcedEvent {
+            calendar_id: id.clone(),
+            event,
+        })
+        .collect()
+}
+
+/// Keys `states` by collection URL, whatever form of href the server used
+/// when they were saved, and drops calendars deleted on the server.
+fn prune(
+    states: &mut BTreeMap<String, CollectionState>,
+    calendars: &[CalendarCollection],
+    url: impl Fn(&str) -> String,
+) {
+    let listed: Vec<String> = calendars
+        .iter()
+        .map(|calendar| url(&calendar.href))
+        .collect();
+    *states = std::mem::take(states)
+        .into_iter()
+        .map(|(href, state)| (url(&href), state))
+        .filter(|(key, _)| listed.contains(key))
+        .collect();
+}
+
+/// Events of every calendar found at the source, optionally overlapping
+/// `range`. With a `state_path`, collections are synced incrementally and
+/// their objects kept in that file between runs.
+pub fn import<T: DavTransport>(
+    source: Source<T>,
+    range: Option<TimeRange>,
+    state_path: Option<&Path>,
+) -> Result<Vec<SourcedEvent>, String> {
+    let mut client = CalDavClient::new(source.url, source.transport);
+    if let Some(user) = source.user {
+        client = client.with_basic_auth(user, source.password.as_deref().unwrap_or_default());
+    }
+    let calendars = client.discover()?;
+
+    let Some(path) = state_path else {
+        let mut events = Vec::new();
+        for calendar in &calendars {
+            let objects = client.query(&calendar.href, range)?;
+            events.extend(tag(calendar, parse_objects(&objects)));
+        }
+        return Ok(events);
+    };
+
+    let mut states: BTreeMap<String, CollectionState> = if path.exists() {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("Cannot read CalDAV state {}: {e}", path.display()))?;
+        serde_json::from_str(&text)
+            .map_err(|e| format!("Invalid CalDAV state {}: {e}", path.display()))?
+    } else {
+        BTreeMap::new()
+    };
+    prune(&mut states, &calendars, |href| client.url(href));
+
+    let mut events = Vec::new();
+    for calendar in &calendars {
+        let state = states
+            .entry(client.url(&calendar.href))
+            .or_insert_with(|| CollectionState::new(&calendar.href));
+        let changes = client.sync(state)?;
+        for url in &changes.missing {
+            eprintln!("agendum: {url}: not returned by the server, retried next time");
+        }
+        eprintln!(
+            "agendum: {}: {} updated, {} removed{}",
+            calendar_id(calendar),
+            changes.updated.len(),
+            changes.removed.len(),
+            if changes.full_resync {
+                " (full resync)"
+            } else {
+                ""
+            }
+        );
+        events.extend(tag(calendar, state.parse()));
+    }
+    let text = serde_json::to_string(&states)
+        .map_err(|e| format!("Cannot serialize CalDAV state: {e}"))?;
+    std::fs::write(path, text)
+        .map_err(|e| format!("Cannot write CalDAV state {}: {e}", path.display()))?;
+    Ok(events)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sync::HttpTransport;
+    use agendum_core::caldav::{DavRequest, DavResponse};
+    use std::time::Duration;
+
+    struct Fake<F>(F);
+
+    impl<F: FnMut(&DavRequest) -> String> DavTransport for Fake<F> {
+        fn send(&mut self, request: &DavRequest) -> Result<DavResponse, String> {
+            Ok(DavResponse {
+                status: 207,
+                body: format!(
+                    r#"<d:multistatus xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">{}</d:multistatus>"#,
+                    (self.0)(request)
+                ),
+            })
+        }
+    }
+
+    fn collection(href: &str, display_name: Option<&str>) -> CalendarCollection {
+        CalendarCollection {
+            href: href.to_string(),
+            display_name: display_name.map(str::to_string),
+            color: None,
+            components: Vec::new(),
+            sync_token: None,
+        }
+    }
+
+    #[test]
+    fn names_calendars_after_their_display_name_or_last_segment() {
+        assert_eq!(
+            calendar_id(&collection("/jean/jurys/", Some("Jurys"))),
+            "Jurys"
+        );
+        assert_eq!(calendar_id(&collection("/jean/jurys/", None)), "jurys");
+        assert_eq!(
+            calendar_id(&collection("https://dav.example/jean/cours", None)),
+            "cours"
+        );
+    }
+
+    #[test]
+    fn keeps_cached_objects_when_the_server_changes_href_form() {
+        let path = std::env::temp_dir().join(format!("agendum-caldav-{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let downloads = &std::cell::Cell::new(0);
+        let run = |calendar_href: &'static str| {
+            let transport = Fake(move |request: &DavRequest| {
+                let ok = "<d:status>HTTP/1.1 200 OK</d:status>";
+                if request.method == "PROPFIND" {
+                    format!(
+                        "<d:response><d:href>{calendar_href}</d:href><d:propstat><d:prop>\
+<d:resourcetype><d:collection/><c:calendar/></d:resourcetype></d:prop>{ok}</d:propstat></d:response>"
+                    )
+                } else if request.body.contains("<d:sync-token></d:sync-token>") {
+                    format!(
+                        "<d:response><d:href>/jean/jurys/a.ics</d:href><d:propstat><d:prop>\
+<d:getetag>\"1\"</d:getetag></d:prop>{ok}</d:propstat></d:response><d:sync-token>t1</d:sync-token>"
+                    )
+                } else if request.body.contains("calendar-multiget") {
+                    downloads.set(downloads.get() + 1);
+                    format!(
+                        "<d:response><d:href>/jean/jurys/a.ics</d:href><d:propstat><d:prop>\
+<d:getetag>\"1\"</d:getetag><c:calendar-data>BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nUID:a\r\n\
+SUMMARY:Jury M1\r\nDTSTART:20250106T080000\r\nDTEND:20250106T100000\r\nEND:VEVENT\r\n\
+END:VCALENDAR\r\n</c:calendar-data></d:prop>{ok}</d:propstat></d:response>"
+                    )
+                } else {
+                    // Nothing changed since t1.
+                    "<d:sync-token>t1</d:sync-token>".to_string()
+                }
+            });
+            let source = Source {
+                url: "https://dav.example/jean/jurys/",
+                user: None,
+                password: None,
+                transport,
+            };
+            import(source, None, Some(&path)).unwrap()
+        };
+
+        assert_eq!(run("/jean/jurys/").len(), 1);
+        // Same collection, now listed with an absolute href.
+        let events = run("https://dav.example/jean/jurys/");
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].calendar_id, "jurys");
+        assert_eq!(downloads.get(), 1);
+        let states: BTreeMap<String, CollectionState> =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(
+            states.keys().collect::<Vec<_>>(),
+            ["https://dav.example/jean/jurys/"]
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    fn send(transport: &mut HttpTransport, method: &str, url: &str, body: &str) -> u16 {
+        let request = DavRequest {
+            method: method.to_string(),
+            url: url.to_string(),
+            headers: vec![("Content-Type".to_string(), "text/calendar".to_string())],
+            body: body.to_string(),
+        };
+        transport.send(&request).unwrap().status
+    }
+
+    fn ics(uid: &str, summary: &str, rule: &str) -> String {
+        format!(
+            "BEGIN:VCALENDAR\r\nVERSION:2.0\r\nPRODID:-//Agendum//Test//EN\r\n\
+BEGIN:VEVENT\r\nUID:{uid}\r\nDTSTAMP:20250101T000000Z\r\nSUMMARY:{summary}\r\n\
+DTSTART:20250106T080000Z\r\nDTEND:20250106T100000Z\r\n{rule}END:VEVENT\r\nEND:VCALENDAR\r\n"
+        )
+    }
+
+    /// Runs against a disposable server without authentication, e.g. Radicale
+    /// with its default configuration:
+    /// `AGENDUM_TEST_CALDAV_URL=http://localhost:5232/test/ cargo test -p agendum-cli -- --ignored`
+    #[test]
+    #[ignore = "needs a CalDAV server in AGENDUM_TEST_CALDAV_URL"]
+    fn queries_and_syncs_a_real_server() {
+        let base = std::env::var("AGENDUM_TEST_CALDAV_URL").expect("AGENDUM_TEST_CALDAV_URL");
+        let calendar = format!(
+            "{}/agendum-{}/",
+            base.trim_end_matches('/'),
+            std::process::id()
+        );
+        let object = |name: &str| format!("{calendar}{name}.ics");
+        let mut transport = HttpTransport::new(Duration::from_secs(10));
+        assert_eq!(send(&mut transport, "MKCALENDAR", &calendar, ""), 201);
+        let single = ics("a", "Jury M1", "");
+        let weekly = ics("b", "Conseil", "RRULE:FREQ=WEEKLY;COUNT=3\r\n");
+        assert_eq!(send(&mut transport, "PUT", &object("a"), &single), 201);
+        assert_eq!(send(&mut transport, "PUT", &object("b"), &weekly), 201);
+
+        let mut client = CalDavClient::new(&calendar, &mut transport);
+        let range = TimeRange {
+            start_ms: 1_736_121_600_000,
+            end_ms: 1_737_936_000_000,
+        };
+        let parsed = parse_objects(&client.query(&calendar, Some(range)).unwrap());
+        assert_eq!(parsed.events.len(), 4);
+        assert_eq!(parsed.diagnostics.recurring_events_not_expanded, 0);
+
+        let mut state = CollectionState::new(&calendar);
+        let first = client.sync(&mut state).unwrap();
+        assert_eq!(first.updated.len(), 2);
+        assert!(first.missing.is_empty());
+        assert!(state.sync_token.is_some());
+        drop(client);
+
+        let renamed = ics("a", "Jury M1 (report\u{e9})", "");
+        assert!(matches!(
+            send(&mut transport, "PUT", &object("a"), &renamed),
+            201 | 204
+        ));
+        assert!(matches!(
+            send(&mut transport, "DELETE", &object("b"), ""),
+            200 | 204
+        ));
+        let mut client = CalDavClient::new(&calendar, &mut transport);
+        let second = client.sync(&mut state).unwrap();
+        assert!(!second.full_resync);
+        assert_eq!(second.updated, [object("a")]);
+        assert_eq!(second.removed, [object("b")]);
+        drop(client);
+
+        send(&mut transport, "DELETE", &calendar, "");
+    }
+}
@@ -0,0 +1,87 @@
+use agendum_core::conflicts::ConflictConfig;
+use agendum_core::dedup::DedupConfig;
+use agendum_core::fiche::FicheOptions;
+use agendum_core::ics_writer::IcsExportOptions;
+use agendum_core::identity::IdentityConfig;
+use agendum_core::service::ServiceReportOptions;
+use agendum_core::service_export::SpreadsheetOptions;
+use agendum_core::sync::SyncPolicy;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Settings shared by every subcommand, read from the `--config` JSON file.
+///
+/// Every section is optional and falls back to the same defaults as the web
+/// application.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct Config {
+    /// Query applied to every command before anything else, e.g.
+    /// `"promo:M1* -type:REUNION"`. `--query` replaces it.
+    pub query: Option<String>,
+    /// Copies of one session across calendars are merged before reports and
+    /// exports. `null` keeps every copy.
+    pub dedup: Option<DedupConfig>,
+    pub service: ServiceReportOptions,
+    /// HETD coefficients, number format and fields for `--template`.
+    pub fiche: FicheOptions,
+    pub spreadsheet: SpreadsheetOptions,
+    pub conflicts: ConflictConfig,
+    pub identity: IdentityConfig,
+    pub ics: IcsExportOptions,
+    /// Refresh delays and limits for `agendum sync`.
+    pub sync: SyncPolicy,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            query: None,
+            dedup: Some(DedupConfig::default()),
+            service: ServiceReportOptions::default(),
+            fiche: FicheOptions::default(),
+            spreadsheet: SpreadsheetOptions::default(),
+            conflicts: ConflictConfig::default(),
+            identity: IdentityConfig::default(),
+            ics: IcsExportOptions::default(),
+            sync: SyncPolicy::default(),
+        }
+    }
+}
+
+impl Config {
+    pub fn load(path: Option<&Path>) -> Result<Config, String> {
+        let Some(path) = path else {
+            return Ok(Config::default());
+        };
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("Cannot read config {}: {e}", path.display()))?;
+        serde_json::from_str(&text).map_err(|e| format!("Invalid config {}: {e}", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn partial_config_keeps_defaults() {
+        let config: Config = serde_json::from_str(
+            r#"{
+                "query": "promo:M1*",
+                "service": { "teacher": "DUPONT Jean" },
+                "spreadsheet": { "delimiter": ";", "decimal_comma": true }
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(config.query.as_deref(), Some("promo:M1*"));
+        assert_eq!(config.service.teacher.as_deref(), Some("DUPONT Jean"));
+        assert_eq!(config.spreadsheet.delimiter, ';');
+        assert_eq!(config.spreadsheet.decimals, 2);
+        assert_eq!(config.dedup, Some(DedupConfig::default()));
+
+        let config: Config = serde_json::from_str(r#"{ "dedup": null }"#).unwrap();
+        assert_eq!(config.dedup, None);
+    }
+}
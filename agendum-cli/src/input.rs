@@ -0,0 +1,210 @@
+use agendum_core::dedup::{deduplicate, DedupConfig, SourcedEvent};
+use agendum_core::jcal::parse_jcal_with_diagnostics;
+use agendum_core::normalizer::{normalize, NormalizedEvent};
+use agendum_core::parser::{
+    parse_ics_content_with_diagnostics, ParseDiagnostics, ParseOutput, RawEvent,
+};
+use agendum_core::query::Query;
+use agendum_core::xcal::parse_xcal_with_diagnostics;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+/// One calendar file read from the command line.
+pub struct Calendar {
+    pub path: PathBuf,
+    /// File name without extension, or the full path when two inputs share a name.
+    pub id: String,
+    pub raw: Vec<RawEvent>,
+    pub diagnostics: ParseDiagnostics,
+}
+
+fn extension(path: &Path) -> String {
+    path.extension()
+        .map(|ext| ext.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default()
+}
+
+fn is_calendar(path: &Path) -> bool {
+    matches!(extension(path).as_str(), "ics" | "jcal" | "xcal" | "xcs")
+}
+
+/// Picks the parser from the extension: jCal for `.jcal`/`.json`, xCal for
+/// `.xcal`/`.xcs`/`.xml`, ICS otherwise.
+fn parse(path: &Path, content: &str) -> ParseOutput {
+    match extension(path).as_str() {
+        "jcal" | "json" => parse_jcal_with_diagnostics(content),
+        "xcal" | "xcs" | "xml" => parse_xcal_with_diagnostics(content),
+        _ => parse_ics_content_with_diagnostics(content),
+    }
+}
+
+fn collect(path: &Path, files: &mut Vec<PathBuf>) -> Result<(), String> {
+    if !path.is_dir() {
+        files.push(path.to_path_buf());
+        return Ok(());
+    }
+    let mut entries: Vec<PathBuf> = std::fs::read_dir(path)
+        .map_err(|e| format!("Cannot read directory {}: {e}", path.display()))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .collect();
+    entries.sort();
+    for entry in entries {
+        if entry.is_dir() {
+            collect(&entry, files)?;
+        } else if is_calendar(&entry) {
+            files.push(entry);
+        }
+    }
+    Ok(())
+}
+
+/// Calendar files named on the command line; directories are searched
+/// recursively for `*.ics`, `*.jcal`, `*.xcal` and `*.xcs` files in name order.
+pub fn calendar_files(inputs: &[PathBuf]) -> Result<Vec<PathBuf>, String> {
+    let mut files = Vec::new();
+    for input in inputs {
+        collect(input, &mut files)?;
+    }
+    Ok(files)
+}
+
+pub fn read_calendars(inputs: &[PathBuf]) -> Result<Vec<Calendar>, String> {
+    let files = calendar_files(inputs)?;
+    let stem = |path: &Path| {
+        path.file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    };
+    let mut seen = BTreeSet::new();
+    let clashing: BTreeSet<String> = files
+        .iter()
+        .map(|path| stem(path))
+        .filter(|name| !seen.insert(name.clone()))
+        .collect();
+
+    files
+        .into_iter()
+        .map(|path| {
+            let content = std::fs::read_to_string(&path)
+                .map_err(|e| format!("Cannot read {}: {e}", path.display()))?;
+            let parsed = parse(&path, &content);
+            let name = stem(&path);
+            let id = if clashing.contains(&name) {
+                path.display().to_string()
+            } else {
+                name
+            };
+            Ok(Calendar {
+                path,
+                id,
+                raw: parsed.events,
+                diagnostics: parsed.diagnostics,
+            })
+        })
+        .collect()
+}
+
+/// Normalized events of every calendar, in input order.
+pub fn sourced_events(calendars: &[Calendar]) -> Vec<SourcedEvent> {
+    calendars
+        .iter()
+        .flat_map(|calendar| {
+            normalize(calendar.raw.clone())
+                .into_iter()
+                .map(|event| SourcedEvent {
+                    calendar_id: calendar.id.clone(),
+                    event,
+                })
+        })
+        .collect()
+}
+
+/// Events the commands work on: filtered by the query, then deduplicated.
+pub fn select(
+    events: Vec<SourcedEvent>,
+    query: Option<&str>,
+    dedup: Option<&DedupConfig>,
+) -> Result<Vec<SourcedEvent>, String> {
+    let query = Query::parse(query.unwrap_or_default()).map_err(|e| e.to_string())?;
+    let events: Vec<SourcedEvent> = events
+        .into_iter()
+        .filter(|event| query.matches_sourced(event))
+        .collect();
+    let Some(config) = dedup else {
+        return Ok(events);
+    };
+    let result = deduplicate(&events, config);
+    Ok(result
+        .kept_indices()
+        .map(|index| events[index].clone())
+        .collect())
+}
+
+pub fn events_only(events: Vec<SourcedEvent>) -> Vec<NormalizedEvent> {
+    events.into_iter().map(|sourced| sourced.event).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CALENDAR: &str = "BEGIN:VCALENDAR\r\nVERSION:2.0\r\n\
+BEGIN:VEVENT\r\nUID:cm-1\r\nSUMMARY:CM Algo\r\nDESCRIPTION:M1 INFO\\nDUPONT Jean\r\n\
+DTSTART:20250106T080000\r\nDTEND:20250106T100000\r\nEND:VEVENT\r\n\
+BEGIN:VEVENT\r\nUID:tp-1\r\nSUMMARY:TP Réseaux\r\nDESCRIPTION:L3 INFO\\nMARTIN Paul\r\n\
+DTSTART:20250107T080000\r\nDTEND:20250107T100000\r\nEND:VEVENT\r\n\
+END:VCALENDAR\r\n";
+
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("agendum-cli-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("m1")).unwrap();
+        std::fs::create_dir_all(dir.join("m2")).unwrap();
+        dir
+    }
+
+    #[test]
+    fn reads_directories_and_disambiguates_names() {
+        let dir = scratch("read");
+        std::fs::write(dir.join("m1/info.ics"), CALENDAR).unwrap();
+        std::fs::write(dir.join("m2/info.ics"), CALENDAR).unwrap();
+        std::fs::write(dir.join("m2/notes.txt"), "ignored").unwrap();
+
+        let calendars = read_calendars(std::slice::from_ref(&dir)).unwrap();
+
+        assert_eq!(calendars.len(), 2);
+        assert!(calendars[0].id.ends_with("info.ics"));
+        assert_ne!(calendars[0].id, calendars[1].id);
+        assert_eq!(calendars[0].raw.len(), 2);
+        assert_eq!(calendars[0].diagnostics.calendars_parsed, 1);
+
+        let single = read_calendars(&[dir.join("m1/info.ics")]).unwrap();
+        assert_eq!(single[0].id, "info");
+
+        let events = normalize(calendars[0].raw.clone());
+        let options = agendum_core::ics_writer::IcsExportOptions::default();
+        let jcal = agendum_core::jcal::write_jcal(&events, &options).unwrap();
+        std::fs::write(dir.join("m1/info.jcal"), jcal).unwrap();
+        let from_jcal = read_calendars(&[dir.join("m1/info.jcal")]).unwrap();
+        assert_eq!(from_jcal[0].raw.len(), 2);
+        assert_eq!(from_jcal[0].diagnostics.parser_errors, 0);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn selects_by_query_and_merges_copies() {
+        let dir = scratch("select");
+        std::fs::write(dir.join("m1/a.ics"), CALENDAR).unwrap();
+        std::fs::write(dir.join("m2/b.ics"), CALENDAR).unwrap();
+        let calendars = read_calendars(std::slice::from_ref(&dir)).unwrap();
+        let events = sourced_events(&calendars);
+        assert_eq!(events.len(), 4);
+
+        let merged = select(events.clone(), None, Some(&DedupConfig::default())).unwrap();
+        assert_eq!(merged.len(), 2);
+        let kept = select(events.clone(), Some("type:TP"), None).unwrap();
+        assert_eq!(kept.len(), 2);
+        assert!(select(events, Some("after:someday"), None).is_err());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,568 @@
+//! `agendum`: the agendum-core pipeline from the command line, printing JSON
+//! or the requested export format to stdout.
+
+mod caldav;
+mod config;
+mod input;
+mod sync;
+
+use agendum_core::caldav::TimeRange;
+use agendum_core::conflicts::detect_conflicts;
+use agendum_core::dedup::SourcedEvent;
+use agendum_core::diff::diff_snapshots_with;
+use agendum_core::fiche::render_fiche;
+use agendum_core::hetd::compute_hetd;
+use agendum_core::ics_writer::write_ics;
+use agendum_core::jcal::write_jcal;
+use agendum_core::normalizer::{normalize, NormalizedEvent};
+use agendum_core::service::build_service_report;
+use agendum_core::service_export::{csv_field, service_report_csv, service_report_ods};
+use agendum_core::sync::{sync_source, Trigger};
+use agendum_core::xcal::write_xcal;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use config::Config;
+use input::{events_only, read_calendars, select, sourced_events};
+use serde::Serialize;
+use serde_json::json;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use sync::{HttpTransport, SyncFile};
+
+#[derive(Parser)]
+#[command(
+    name = "agendum",
+    version,
+    about = "Timetable tools for ADE/ICS calendars"
+)]
+struct Cli {
+    /// JSON configuration file (query, dedup, service, HETD, export options).
+    #[arg(short, long, global = true)]
+    config: Option<PathBuf>,
+    /// Write the result to this file instead of stdout.
+    #[arg(short, long, global = true)]
+    output: Option<PathBuf>,
+    /// Print JSON on a single line.
+    #[arg(long, global = true)]
+    compact: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Args)]
+struct Inputs {
+    /// ICS files, or directories searched for *.ics files.
+    #[arg(required = true)]
+    inputs: Vec<PathBuf>,
+}
+
+#[derive(Args)]
+struct Selection {
+    #[command(flatten)]
+    inputs: Inputs,
+    /// Only keep events matching this query (replaces the configured one).
+    #[arg(short, long)]
+    query: Option<String>,
+    /// Keep every copy of sessions present in several calendars.
+    #[arg(long)]
+    no_dedup: bool,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Raw events and parser diagnostics of each file.
+    Parse(Inputs),
+    /// Normalized events tagged with their calendar.
+    Normalize(Selection),
+    /// Parser diagnostics and data quality counts per file.
+    Diagnose(Inputs),
+    /// Service reports.
+    #[command(subcommand)]
+    Report(Report),
+    /// Teacher, room and group double bookings.
+    Conflicts(Selection),
+    /// Changes between two snapshots of the same calendars.
+    Diff {
+        /// Previous snapshot: an ICS file or a directory.
+        old: PathBuf,
+        /// Current snapshot: an ICS file or a directory.
+        new: PathBuf,
+    },
+    /// Normalized events as ICS, jCal, xCal, CSV or JSON.
+    Export {
+        #[arg(value_enum)]
+        format: ExportFormat,
+        #[command(flatten)]
+        selection: Selection,
+    },
+    /// Refresh a remote calendar, printing what changed since the last run.
+    Sync {
+        /// ICS URL of the calendar.
+        url: String,
+        /// JSON file keeping the source state and last events between runs.
+        #[arg(short, long)]
+        state: PathBuf,
+        /// Refresh now, within the manual refresh limits, instead of waiting
+        /// for the automatic refresh delay.
+        #[arg(long)]
+        manual: bool,
+        /// Request timeout in seconds.
+        #[arg(long, default_value_t = 30)]
+        timeout: u64,
+    },
+    /// Normalized events of the calendars found on a CalDAV server.
+    Caldav {
+        /// Server root, principal or calendar collection URL.
+        url: String,
+        #[arg(short, long)]
+        user: Option<String>,
+        /// Environment variable holding the password.
+        #[arg(long, default_value = "AGENDUM_CALDAV_PASSWORD")]
+        password_env: String,
+        /// JSON file keeping sync tokens and events between runs, so that
+        /// only changes are downloaded.
+        #[arg(short, long)]
+        state: Option<PathBuf>,
+        /// Only events ending after this day (YYYY-MM-DD, UTC).
+        #[arg(long, conflicts_with = "state")]
+        from: Option<String>,
+        /// Only events starting before this day (YYYY-MM-DD, UTC).
+        #[arg(long, conflicts_with = "state")]
+        to: Option<String>,
+        /// Only keep events matching this query (replaces the configured one).
+        #[arg(short, long)]
+        query: Option<String>,
+        /// Request timeout in seconds.
+        #[arg(long, default_value_t = 30)]
+        timeout: u64,
+    },
+}
+
+#[derive(Subcommand)]
+enum Report {
+    /// Hours per teacher, subject and session type.
+    Service {
+        #[command(flatten)]
+        selection: Selection,
+        /// Restrict the report to one teacher.
+        #[arg(short, long)]
+        teacher: Option<String>,
+        #[arg(short, long, value_enum, default_value_t = ReportFormat::Json)]
+        format: ReportFormat,
+        /// Render a fiche de service with this template instead (HTML or
+        /// Markdown, as set in the `fiche` config section).
+        #[arg(long, conflicts_with = "format")]
+        template: Option<PathBuf>,
+    },
+    /// Weighted HETD service and complementary hours per teacher.
+    Hetd(Selection),
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ReportFormat {
+    Json,
+    Csv,
+    Ods,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ExportFormat {
+    Ics,
+    Jcal,
+    Xcal,
+    Csv,
+    Json,
+}
+
+struct Output {
+    path: Option<PathBuf>,
+    compact: bool,
+}
+
+impl Output {
+    fn bytes(&self, bytes: &[u8]) -> Result<(), String> {
+        match &self.path {
+            Some(path) => std::fs::write(path, bytes)
+                .map_err(|e| format!("Cannot write {}: {e}", path.display())),
+            None => std::io::stdout()
+                .write_all(bytes)
+                .map_err(|e| format!("Cannot write to stdout: {e}")),
+        }
+    }
+
+    fn json<T: Serialize>(&self, value: &T) -> Result<(), String> {
+        let mut text = if self.compact {
+            serde_json::to_string(value)
+        } else {
+            serde_json::to_string_pretty(value)
+        }
+        .map_err(|e| format!("Cannot serialize result: {e}"))?;
+        text.push('\n');
+        self.bytes(text.as_bytes())
+    }
+}
+
+impl Selection {
+    fn events(&self, config: &Config) -> Result<Vec<SourcedEvent>, String> {
+        let calendars = read_calendars(&self.inputs.inputs)?;
+        let query = self.query.as_deref().or(config.query.as_deref());
+        let dedup = if self.no_dedup {
+            None
+        } else {
+            config.dedup.as_ref()
+        };
+        select(sourced_events(&calendars), query, dedup)
+    }
+}
+
+/// One line per event, teachers and promos joined with `;`.
+fn events_csv(events: &[SourcedEvent], delimiter: char) -> String {
+    let header = [
+        "calendar", "uid", "start", "end", "minutes", "subject", "type", "teachers", "promos",
+        "location",
+    ];
+    let mut out = header.join(&delimiter.to_string());
+    out.push('\n');
+    for sourced in events {
+        let event = &sourced.event;
+        let fields = [
+            sourced.calendar_id.clone(),
+            event.raw.uid.clone(),
+            event
+                .start_rfc3339
+                .clone()
+                .unwrap_or_else(|| event.start_iso.clone()),
+            event
+                .end_rfc3339
+                .clone()
+                .unwrap_or_else(|| event.end_iso.clone()),
+            event.minutes().to_string(),
+            event.subject.clone(),
+            event.type_.clone(),
+            event.known_teachers().join(";"),
+            event.promos.join(";"),
+            event.raw.location.clone(),
+        ];
+        let line: Vec<String> = fields
+            .iter()
+            .map(|field| csv_field(field, delimiter))
+            .collect();
+        out.push_str(&line.join(&delimiter.to_string()));
+        out.push('\n');
+    }
+    out
+}
+
+fn snapshot(path: &Path) -> Result<Vec<NormalizedEvent>, String> {
+    let calendars = read_calendars(&[path.to_path_buf()])?;
+    Ok(calendars
+        .into_iter()
+        .flat_map(|calendar| normalize(calendar.raw))
+        .collect())
+}
+
+/// Midnight UTC of a `YYYY-MM-DD` day, in epoch milliseconds.
+fn day_ms(day: &str) -> Result<i64, String> {
+    chrono::NaiveDate::parse_from_str(day, "%Y-%m-%d")
+        .map(|date| {
+            date.and_time(chrono::NaiveTime::MIN)
+                .and_utc()
+                .timestamp_millis()
+        })
+        .map_err(|e| format!("Invalid day `{day}`: {e}"))
+}
+
+fn run(cli: Cli) -> Result<(), String> {
+    let config = Config::load(cli.config.as_deref())?;
+    let out = Output {
+        path: cli.output,
+        compact: cli.compact,
+    };
+
+    match cli.command {
+        Command::Parse(inputs) => {
+            let calendars = read_calendars(&inputs.inputs)?;
+            let files: Vec<_> = calendars
+                .iter()
+                .map(|calendar| {
+                    json!({
+                        "path": calendar.path,
+                        "calendar_id": calendar.id,
+                        "events": calendar.raw,
+                        "diagnostics": calendar.diagnostics,
+                    })
+                })
+                .collect();
+            out.json(&files)
+        }
+        Command::Normalize(selection) => out.json(&selection.events(&config)?),
+        Command::Diagnose(inputs) => {
+            let calendars = read_calendars(&inputs.inputs)?;
+            let files: Vec<_> = calendars
+                .iter()
+                .map(|calendar| {
+                    let events = normalize(calendar.raw.clone());
+                    let count = |test: fn(&NormalizedEvent) -> bool| {
+                        events.iter().filter(|event| test(event)).count()
+                    };
+                    json!({
+                        "path": calendar.path,
+                        "calendar_id": calendar.id,
+                        "events": events.len(),
+                        "diagnostics": calendar.diagnostics,
+                        "without_teacher": count(|event| event.known_teachers().is_empty()),
+                        "without_promo": count(|event| event.promos.is_empty()),
+                        "without_type": count(|event| event.type_.trim().is_empty()),
+                        "without_location": count(|event| event.raw.location.trim().is_empty()),
+                        "missing_times": count(|event| {
+                            event.start_utc_ms.is_none() || event.end_utc_ms.is_none()
+                        }),
+                        "invalid_times": count(|event| {
+                            matches!(
+                                (event.start_utc_ms, event.end_utc_ms),
+                                (Some(start), Some(end)) if end <= start
+                            )
+                        }),
+                    })
+                })
+                .collect();
+            out.json(&files)
+        }
+        Command::Report(Report::Service {
+            selection,
+            teacher,
+            format,
+            template,
+        }) => {
+            let events = events_only(selection.events(&config)?);
+            if let Some(path) = template {
+                let source = std::fs::read_to_string(&path)
+                    .map_err(|e| format!("Cannot read template {}: {e}", path.display()))?;
+                let mut options = config.fiche.clone();
+                options.teacher = teacher.or(options.teacher);
+                let document = render_fiche(&events, &source, &options)
+                    .map_err(|e| format!("{}: {e}", path.display()))?;
+                return out.bytes(document.as_bytes());
+            }
+            let mut options = config.service.clone();
+            options.teacher = teacher.or(options.teacher);
+            let report = build_service_report(&events, &options)?;
+            match format {
+                ReportFormat::Json => out.json(&report),
+                ReportFormat::Csv => {
+                    out.bytes(service_report_csv(&report, &config.spreadsheet).as_bytes())
+                }
+                ReportFormat::Ods => out.bytes(&service_report_ods(&report, &config.spreadsheet)),
+            }
+        }
+        Command::Report(Report::Hetd(selection)) => {
+            let events = events_only(selection.events(&config)?);
+            out.json(&compute_hetd(&events, &config.fiche.hetd))
+        }
+        Command::Conflicts(selection) => {
+            // Copies are merged by the conflict detector itself.
+            let mut selection = selection;
+            selection.no_dedup = true;
+            let events = selection.events(&config)?;
+            out.json(&json!({
+                "report": detect_conflicts(&events, &config.conflicts),
+                "events": events,
+            }))
+        }
+        Command::Diff { old, new } => {
+            let old = snapshot(&old)?;
+            let new = snapshot(&new)?;
+            let diff = diff_snapshots_with(&old, &new, &config.identity);
+            let changed: Vec<_> = diff
+                .changed
+                .iter()
+                .map(|change| {
+                    json!({
+                        "old": old[change.old_index],
+                        "new": new[change.new_index],
+                        "matched_by": change.matched_by,
+                        "confidence": change.confidence,
+                        "details": change.details,
+                    })
+                })
+                .collect();
+            out.json(&json!({
+                "summary": diff.summary,
+                "added": diff.added.iter().map(|&i| &new[i]).collect::<Vec<_>>(),
+                "removed": diff.removed.iter().map(|&i| &old[i]).collect::<Vec<_>>(),
+                "changed": changed,
+            }))
+        }
+        Command::Export { format, selection } => {
+            let events = selection.events(&config)?;
+            match format {
+                ExportFormat::Json => out.json(&events),
+                ExportFormat::Csv => {
+                    let csv = events_csv(&events, config.spreadsheet.delimiter);
+                    out.bytes(csv.as_bytes())
+                }
+                ExportFormat::Ics => {
+                    let ics =
+                        write_ics(&events_only(events), &config.ics).map_err(|e| e.to_string())?;
+                    out.bytes(ics.as_bytes())
+                }
+                ExportFormat::Jcal => {
+                    let jcal =
+                        write_jcal(&events_only(events), &config.ics).map_err(|e| e.to_string())?;
+                    out.bytes(jcal.as_bytes())
+                }
+                ExportFormat::Xcal => {
+                    let xcal =
+                        write_xcal(&events_only(events), &config.ics).map_err(|e| e.to_string())?;
+                    out.bytes(xcal.as_bytes())
+                }
+            }
+        }
+        Command::Sync {
+            url,
+            state,
+            manual,
+            timeout,
+        } => {
+            let mut file = SyncFile::load(&state, &url)?;
+            let trigger = if manual {
+                Trigger::Manual
+            } else {
+                Trigger::Auto
+            };
+            let now_ms = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_millis() as i64);
+            let mut transport = HttpTransport::new(Duration::from_secs(timeout));
+            let report = match sync_source(
+                &mut file.state,
+                trigger,
+                &file.events,
+                &mut transport,
+                &config.identity,
+                &config.sync,
+                now_ms,
+            ) {
+                Ok(report) => report,
+                Err(decision) => return out.json(&decision),
+            };
+            let previous = match &report.events {
+                Some(events) => std::mem::replace(&mut file.events, events.clone()),
+                None => file.events.clone(),
+            };
+            file.save(&state)?;
+            let changed = report.diff.as_ref().map(|diff| {
+                json!({
+                    "summary": diff.summary,
+                    "added": diff.added.iter().map(|&i| &file.events[i]).collect::<Vec<_>>(),
+                    "removed": diff.removed.iter().map(|&i| &previous[i]).collect::<Vec<_>>(),
+                })
+            });
+            out.json(&json!({
+                "status": report.status,
+                "error": report.error,
+                "diagnostics": report.diagnostics,
+                "next_refresh_ms": report.next_refresh_ms,
+                "changes": changed,
+            }))
+        }
+        Command::Caldav {
+            url,
+            user,
+            password_env,
+            state,
+            from,
+            to,
+            query,
+            timeout,
+        } => {
+            let range = match (&from, &to) {
+                (None, None) => None,
+                _ => Some(TimeRange {
+                    start_ms: from.as_deref().map_or(Ok(0), day_ms)?,
+                    end_ms: to
+                        .as_deref()
+                        .map_or(Ok(i64::from(i32::MAX) * 1000), day_ms)?,
+                }),
+            };
+            let source = caldav::Source {
+                url: &url,
+                user: user.as_deref(),
+                password: std::env::var(&password_env).ok(),
+                transport: HttpTransport::new(Duration::from_secs(timeout)),
+            };
+            let events = caldav::import(source, range, state.as_deref())?;
+            let query = query.as_deref().or(config.query.as_deref());
+            out.json(&select(events, query, config.dedup.as_ref())?)
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(message) => {
+            eprintln!("agendum: {message}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn command_line_is_consistent() {
+        Cli::command().debug_assert();
+
+        let cli = Cli::try_parse_from([
+            "agendum",
+            "report",
+            "service",
+            "cals/",
+            "--teacher",
+            "DUPONT Jean",
+            "-f",
+            "csv",
+            "--compact",
+        ])
+        .unwrap();
+        assert!(cli.compact);
+        assert!(matches!(
+            cli.command,
+            Command::Report(Report::Service {
+                format: ReportFormat::Csv,
+                teacher: Some(_),
+                ..
+            })
+        ));
+        assert!(Cli::try_parse_from(["agendum", "normalize"]).is_err());
+    }
+
+    #[test]
+    fn csv_export_uses_the_delimiter_and_quotes_fields() {
+        let event = normalize(vec![agendum_core::parser::RawEvent {
+            uid: "1".to_string(),
+            summary: "TD Algo".to_string(),
+            description: String::new(),
+            location: "Salle; B12".to_string(),
+            start: "20250106T080000".to_string(),
+            end: "20250106T100000".to_string(),
+        }])
+        .remove(0);
+        let events = [SourcedEvent {
+            calendar_id: "m1".to_string(),
+            event,
+        }];
+
+        let csv = events_csv(&events, ';');
+        let lines: Vec<&str> = csv.lines().collect();
+        assert!(lines[0].starts_with("calendar;uid;start;"));
+        assert!(lines[1].starts_with("m1;1;"));
+        assert!(lines[1].ends_with(";\"Salle; B12\""));
+        assert!(events_csv(&events, ',').ends_with(",Salle; B12\n"));
+    }
+}
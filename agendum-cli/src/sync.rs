@@ -0,0 +1,211 @@
+use agendum_core::caldav::{DavRequest, DavResponse, DavTransport};
+use agendum_core::normalizer::NormalizedEvent;
+use agendum_core::sync::{FetchOutcome, FetchRequest, SourceState, Transport};
+use serde::{Deserialize, Serialize};
+use std::io::Read;
+use std::path::Path;
+use std::time::Duration;
+
+/// Largest calendar accepted from a server.
+const MAX_BODY_BYTES: u64 = 32 * 1024 * 1024;
+
+/// Blocking HTTP client for [`agendum_core::sync`] and
+/// [`agendum_core::caldav`].
+pub struct HttpTransport {
+    agent: ureq::Agent,
+}
+
+impl HttpTransport {
+    pub fn new(timeout: Duration) -> HttpTransport {
+        HttpTransport {
+            agent: ureq::AgentBuilder::new()
+                .timeout(timeout)
+                .user_agent(concat!("agendum/", env!("CARGO_PKG_VERSION")))
+                .build(),
+        }
+    }
+}
+
+/// Reads the whole body, refusing one over [`MAX_BODY_BYTES`] rather than
+/// returning a truncated calendar.
+fn read_body(response: ureq::Response) -> Result<Vec<u8>, String> {
+    let mut body = Vec::new();
+    response
+        .into_reader()
+        .take(MAX_BODY_BYTES + 1)
+        .read_to_end(&mut body)
+        .map_err(|e| e.to_string())?;
+    if body.len() as u64 > MAX_BODY_BYTES {
+        return Err(format!(
+            "Calendar is larger than {} MiB",
+            MAX_BODY_BYTES / (1024 * 1024)
+        ));
+    }
+    Ok(body)
+}
+
+fn outcome(response: ureq::Response) -> FetchOutcome {
+    match response.status() {
+        304 => return FetchOutcome::NotModified,
+        200..=299 => {}
+        status => {
+            return FetchOutcome::Failed {
+                message: format!("HTTP {status} {}", response.status_text()),
+            }
+        }
+    }
+    let etag = response.header("ETag").map(str::to_string);
+    let last_modified = response.header("Last-Modified").map(str::to_string);
+    let body = match read_body(response) {
+        Ok(body) => body,
+        Err(message) => return FetchOutcome::Failed { message },
+    };
+    FetchOutcome::Body {
+        text: String::from_utf8_lossy(&body).into_owned(),
+        etag,
+        last_modified,
+    }
+}
+
+impl Transport for HttpTransport {
+    fn fetch(&mut self, request: &FetchRequest) -> FetchOutcome {
+        let mut call = self.agent.get(&request.url);
+        if let Some(etag) = &request.if_none_match {
+            call = call.set("If-None-Match", etag);
+        }
+        if let Some(date) = &request.if_modified_since {
+            call = call.set("If-Modified-Since", date);
+        }
+        match call.call() {
+            Ok(response) | Err(ureq::Error::Status(_, response)) => outcome(response),
+            Err(error) => FetchOutcome::Failed {
+                message: error.to_string(),
+            },
+        }
+    }
+}
+
+impl DavTransport for HttpTransport {
+    fn send(&mut self, request: &DavRequest) -> Result<DavResponse, String> {
+        let mut call = self.agent.request(&request.method, &request.url);
+        for (name, value) in &request.headers {
+            call = call.set(name, value);
+        }
+        let response = match call.send_string(&request.body) {
+            Ok(response) | Err(ureq::Error::Status(_, response)) => response,
+            Err(error) => return Err(error.to_string()),
+        };
+        let status = response.status();
+        let body = read_body(response)
+            .and_then(|body| String::from_utf8(body).map_err(|e| e.to_string()))
+            .map_err(|e| format!("{} {}: {e}", request.method, request.url))?;
+        Ok(DavResponse { status, body })
+    }
+}
+
+/// What `agendum sync` keeps between runs: the source state and the events
+/// of the last successful copy, to diff the next one against.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct SyncFile {
+    pub state: SourceState,
+    pub events: Vec<NormalizedEvent>,
+}
+
+impl SyncFile {
+    /// Reads `path`, or starts from scratch when it does not exist yet.
+    pub fn load(path: &Path, url: &str) -> Result<SyncFile, String> {
+        if !path.exists() {
+            return Ok(SyncFile {
+                state: SourceState::new(url),
+                events: Vec::new(),
+            });
+        }
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("Cannot read sync state {}: {e}", path.display()))?;
+        let mut file: SyncFile = serde_json::from_str(&text)
+            .map_err(|e| format!("Invalid sync state {}: {e}", path.display()))?;
+        if file.state.url != url {
+            // Validators and hashes belong to the old URL.
+            file.state = SourceState::new(url);
+        }
+        Ok(file)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let text =
+            serde_json::to_string(self).map_err(|e| format!("Cannot serialize sync state: {e}"))?;
+        std::fs::write(path, text)
+            .map_err(|e| format!("Cannot write sync state {}: {e}", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+
+    /// Answers two requests: the calendar, then 304 if revalidated.
+    fn serve_twice(body: &'static str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            for stream in listener.incoming().take(2) {
+                let mut stream = stream.unwrap();
+                let mut revalidated = false;
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line.trim().is_empty() {
+                        break;
+                    }
+                    revalidated |= line
+                        .to_ascii_lowercase()
+                        .starts_with("if-none-match: \"v1\"");
+                }
+                let reply = if revalidated {
+                    "HTTP/1.1 304 Not Modified\r\nContent-Length: 0\r\n\r\n".to_string()
+                } else {
+                    format!(
+                        "HTTP/1.1 200 OK\r\nETag: \"v1\"\r\nContent-Length: {}\r\n\r\n{body}",
+                        body.len()
+                    )
+                };
+                stream.write_all(reply.as_bytes()).unwrap();
+            }
+        });
+        format!("http://{address}/cal.ics")
+    }
+
+    #[test]
+    fn fetches_and_revalidates_over_http() {
+        let url = serve_twice("BEGIN:VCALENDAR\r\nEND:VCALENDAR\r\n");
+        let mut transport = HttpTransport::new(Duration::from_secs(5));
+        let mut request = FetchRequest {
+            url,
+            if_none_match: None,
+            if_modified_since: None,
+        };
+        let FetchOutcome::Body { text, etag, .. } = transport.fetch(&request) else {
+            panic!("expected a body");
+        };
+        assert!(text.starts_with("BEGIN:VCALENDAR"));
+        assert_eq!(etag.as_deref(), Some("\"v1\""));
+
+        request.if_none_match = etag;
+        assert_eq!(transport.fetch(&request), FetchOutcome::NotModified);
+    }
+
+    #[test]
+    fn rejects_bodies_over_the_limit() {
+        let body = "x".repeat(MAX_BODY_BYTES as usize + 1);
+        let response = ureq::Response::new(200, "OK", &body).unwrap();
+        assert!(matches!(outcome(response), FetchOutcome::Failed { .. }));
+
+        let body = "x".repeat(MAX_BODY_BYTES as usize);
+        let response = ureq::Response::new(200, "OK", &body).unwrap();
+        assert!(read_body(response).is_ok());
+    }
+}
@@ -0,0 +1,217 @@
+use crate::normalizer::NormalizedEvent;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum RoundingMode {
+    #[default]
+    Nearest,
+    Up,
+    Down,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rounding {
+    pub step_minutes: u32,
+    #[serde(default)]
+    pub mode: RoundingMode,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BreakDeduction {
+    /// Sessions strictly longer than this are considered long.
+    pub threshold_minutes: u32,
+    pub deduct_minutes: u32,
+}
+
+/// How raw session minutes turn into billable minutes.
+///
+/// Per session the break is deducted first, then the minimum is enforced, then
+/// the result is rounded. The daily cap applies to the sum of a local day.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct AccountingPolicy {
+    pub rounding: Option<Rounding>,
+    pub minimum_billable_minutes: Option<u32>,
+    pub long_session_break: Option<BreakDeduction>,
+    pub daily_cap_minutes: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct AccountingTotals {
+    pub sessions: u32,
+    pub raw_minutes: i64,
+    pub billable_minutes: i64,
+    /// Minutes removed by the daily cap, already excluded from `billable_minutes`.
+    pub capped_minutes: i64,
+}
+
+fn round_to_step(minutes: i64, rounding: Rounding) -> i64 {
+    let step = i64::from(rounding.step_minutes);
+    if step <= 0 {
+        return minutes;
+    }
+    let floor = minutes.div_euclid(step) * step;
+    let remainder = minutes - floor;
+    match rounding.mode {
+        RoundingMode::Down => floor,
+        RoundingMode::Up if remainder > 0 => floor + step,
+        RoundingMode::Up => floor,
+        RoundingMode::Nearest if remainder * 2 >= step => floor + step,
+        RoundingMode::Nearest => floor,
+    }
+}
+
+impl AccountingPolicy {
+    /// Billable minutes for a single session lasting `raw_minutes`.
+    pub fn session_minutes(&self, raw_minutes: i64) -> i64 {
+        if raw_minutes <= 0 {
+            return 0;
+        }
+
+        let mut minutes = raw_minutes;
+        if let Some(deduction) = self.long_session_break {
+            if minutes > i64::from(deduction.threshold_minutes) {
+                minutes = (minutes - i64::from(deduction.deduct_minutes)).max(0);
+            }
+        }
+        if let Some(minimum) = self.minimum_billable_minutes {
+            minutes = minutes.max(i64::from(minimum));
+        }
+        if let Some(rounding) = self.rounding {
+            minutes = round_to_step(minutes, rounding);
+        }
+        minutes
+    }
+
+    /// Billable minutes for one event, honoring everything but the daily cap.
+    pub fn event_minutes(&self, event: &NormalizedEvent) -> i64 {
+        self.session_minutes(event.minutes())
+    }
+
+    /// Billable minutes for each event, in input order.
+    ///
+    /// The daily cap is consumed chronologically: once a local day is full,
+    /// later sessions of that day contribute only what remains of the cap.
+    pub fn allocate(&self, events: &[&NormalizedEvent]) -> Vec<i64> {
+        let mut allocated: Vec<i64> = events.iter().map(|e| self.event_minutes(e)).collect();
+        let Some(cap) = self.daily_cap_minutes else {
+            return allocated;
+        };
+
+        let mut order: Vec<usize> = (0..events.len()).collect();
+        order.sort_by_key(|&idx| events[idx].local_start());
+
+        let mut used_per_day: BTreeMap<NaiveDate, i64> = BTreeMap::new();
+        for idx in order {
+            let Some(day) = events[idx].local_start().map(|start| start.date()) else {
+                continue;
+            };
+            let used = used_per_day.entry(day).or_default();
+            let granted = allocated[idx].min((i64::from(cap) - *used).max(0));
+            *used += granted;
+            allocated[idx] = granted;
+        }
+        allocated
+    }
+
+    pub fn totals<'a, I>(&self, events: I) -> AccountingTotals
+    where
+        I: IntoIterator<Item = &'a NormalizedEvent>,
+    {
+        let events: Vec<&NormalizedEvent> = events.into_iter().collect();
+        let allocated = self.allocate(&events);
+
+        let mut totals = AccountingTotals::default();
+        for (event, billable) in events.iter().zip(allocated) {
+            totals.sessions += 1;
+            totals.raw_minutes += event.minutes().max(0);
+            totals.billable_minutes += billable;
+            totals.capped_minutes += self.event_minutes(event) - billable;
+        }
+        totals
+    }
+}
+
+pub fn minutes_to_hours(minutes: i64) -> f64 {
+    minutes as f64 / 60.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::normalizer::test_support;
+
+    fn event(start: &str, end: &str) -> NormalizedEvent {
+        test_support::event("CM Algo", "", start, end)
+    }
+
+    #[test]
+    fn default_policy_keeps_exact_minutes() {
+        let events: Vec<_> = (0..96)
+            .map(|_| event("20250101T080000", "20250101T100000"))
+            .collect();
+        let totals = AccountingPolicy::default().totals(&events);
+
+        assert_eq!(totals.sessions, 96);
+        assert_eq!(totals.raw_minutes, 192 * 60);
+        assert_eq!(totals.billable_minutes, 192 * 60);
+        assert_eq!(minutes_to_hours(totals.billable_minutes), 192.0);
+    }
+
+    #[test]
+    fn session_rules_apply_in_order() {
+        let policy = AccountingPolicy {
+            rounding: Some(Rounding {
+                step_minutes: 15,
+                mode: RoundingMode::Nearest,
+            }),
+            minimum_billable_minutes: Some(60),
+            long_session_break: Some(BreakDeduction {
+                threshold_minutes: 240,
+                deduct_minutes: 30,
+            }),
+            daily_cap_minutes: None,
+        };
+
+        assert_eq!(policy.session_minutes(20), 60);
+        assert_eq!(policy.session_minutes(97), 90);
+        assert_eq!(policy.session_minutes(98), 105);
+        assert_eq!(policy.session_minutes(240), 240);
+        assert_eq!(policy.session_minutes(300), 270);
+        assert_eq!(policy.session_minutes(0), 0);
+
+        let up = Rounding {
+            step_minutes: 15,
+            mode: RoundingMode::Up,
+        };
+        assert_eq!(round_to_step(61, up), 75);
+        assert_eq!(round_to_step(60, up), 60);
+        let down = Rounding {
+            step_minutes: 15,
+            mode: RoundingMode::Down,
+        };
+        assert_eq!(round_to_step(74, down), 60);
+    }
+
+    #[test]
+    fn daily_cap_limits_each_local_day() {
+        let policy = AccountingPolicy {
+            daily_cap_minutes: Some(360),
+            ..AccountingPolicy::default()
+        };
+        let events = vec![
+            event("20250106T080000", "20250106T120000"),
+            event("20250106T133000", "20250106T173000"),
+            event("20250107T080000", "20250107T100000"),
+        ];
+
+        let totals = policy.totals(&events);
+
+        assert_eq!(totals.raw_minutes, 600);
+        assert_eq!(totals.billable_minutes, 360 + 120);
+        assert_eq!(totals.capped_minutes, 120);
+    }
+}
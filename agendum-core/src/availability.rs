@@ -0,0 +1,444 @@
+use crate::conflicts::groups_overlap;
+use crate::normalizer::{text_key, NormalizedEvent};
+use crate::query::parse_clock;
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, Weekday};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Inclusive range of days with no teaching, e.g. the Christmas break.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Holiday {
+    pub from: NaiveDate,
+    pub until: NaiveDate,
+    #[serde(default)]
+    pub label: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct SlotRequest {
+    /// Everyone listed must be free.
+    pub teachers: Vec<String>,
+    pub groups: Vec<String>,
+    /// Candidate rooms; when non-empty at least one must be free.
+    pub rooms: Vec<String>,
+    pub from: Option<NaiveDate>,
+    /// Inclusive.
+    pub until: Option<NaiveDate>,
+    pub duration_minutes: u32,
+    /// Working hours, `"08:00"` / `"18:30"`.
+    pub day_start: String,
+    pub day_end: String,
+    /// Allowed start times, e.g. `["08:00", "09:45", "11:30"]`. When empty,
+    /// starts are every `step_minutes` from `day_start`.
+    pub grid: Vec<String>,
+    pub step_minutes: u32,
+    pub excluded_weekdays: Vec<Weekday>,
+    pub excluded_dates: Vec<NaiveDate>,
+    pub holidays: Vec<Holiday>,
+    /// Free time kept around existing sessions.
+    pub buffer_minutes: u32,
+    /// Day the session would ideally take place on; defaults to `from`.
+    pub preferred_date: Option<NaiveDate>,
+    pub limit: usize,
+}
+
+impl Default for SlotRequest {
+    fn default() -> Self {
+        SlotRequest {
+            teachers: Vec::new(),
+            groups: Vec::new(),
+            rooms: Vec::new(),
+            from: None,
+            until: None,
+            duration_minutes: 120,
+            day_start: "08:00".to_string(),
+            day_end: "18:30".to_string(),
+            grid: Vec::new(),
+            step_minutes: 15,
+            excluded_weekdays: vec![Weekday::Sat, Weekday::Sun],
+            excluded_dates: Vec::new(),
+            holidays: Vec::new(),
+            buffer_minutes: 0,
+            preferred_date: None,
+            limit: 20,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SlotCandidate {
+    /// Local wall-clock times, like `start_iso` on events.
+    pub start_iso: String,
+    pub end_iso: String,
+    pub free_rooms: Vec<String>,
+    /// Requested teachers and groups with a session ending or starting within
+    /// 30 minutes of the slot.
+    pub adjacent: usize,
+    /// Higher is better: close to the preferred date and packed against
+    /// existing sessions rather than leaving holes in the day.
+    pub score: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct SlotReport {
+    pub slots: Vec<SlotCandidate>,
+    /// Requested teachers, groups and rooms that appear in no event. Their
+    /// availability is unknown rather than free, so unknown rooms are never
+    /// offered in `free_rooms`.
+    pub unknown_resources: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Busy {
+    start: NaiveDateTime,
+    end: NaiveDateTime,
+}
+
+fn clock(value: &str, field: &str) -> Result<u32, String> {
+    parse_clock(value).ok_or_else(|| format!("invalid {field} `{value}`"))
+}
+
+fn at(date: NaiveDate, minutes: u32) -> NaiveDateTime {
+    date.and_hms_opt(0, 0, 0).unwrap_or_default() + Duration::minutes(i64::from(minutes))
+}
+
+fn overlaps(busy: &[Busy], start: NaiveDateTime, end: NaiveDateTime, buffer: Duration) -> bool {
+    busy.iter()
+        .any(|b| b.start - buffer < end && start < b.end + buffer)
+}
+
+/// Busy intervals of each participant, keyed by teacher or group name.
+fn participant_calendars<'a>(
+    events: &[NormalizedEvent],
+    request: &'a SlotRequest,
+) -> BTreeMap<&'a str, Vec<Busy>> {
+    let mut calendars: BTreeMap<&str, Vec<Busy>> = BTreeMap::new();
+    let teachers: Vec<(String, &str)> = request
+        .teachers
+        .iter()
+        .map(|name| (text_key(name), name.as_str()))
+        .collect();
+    let groups: Vec<(String, &str)> = request
+        .groups
+        .iter()
+        .map(|name| (text_key(name), name.as_str()))
+        .collect();
+    for (_, name) in teachers.iter().chain(&groups) {
+        calendars.entry(name).or_default();
+    }
+
+    for event in events {
+        let (Some(start), Some(end)) = (event.local_start(), event.local_end()) else {
+            continue;
+        };
+        let busy = Busy { start, end };
+        let event_teachers: Vec<String> =
+            event.known_teachers().into_iter().map(text_key).collect();
+        for (key, name) in &teachers {
+            if event_teachers.contains(key) {
+                calendars.entry(name).or_default().push(busy);
+            }
+        }
+        for (key, name) in &groups {
+            if event
+                .promos
+                .iter()
+                .any(|promo| groups_overlap(&text_key(promo), key))
+            {
+                calendars.entry(name).or_default().push(busy);
+            }
+        }
+    }
+    calendars
+}
+
+fn room_calendars<'a>(
+    events: &[NormalizedEvent],
+    request: &'a SlotRequest,
+) -> Vec<(&'a str, Vec<Busy>)> {
+    request
+        .rooms
+        .iter()
+        .map(|room| {
+            let key = text_key(room);
+            let busy = events
+                .iter()
+                .filter(|event| text_key(&event.raw.location) == key)
+                .filter_map(|event| {
+                    Some(Busy {
+                        start: event.local_start()?,
+                        end: event.local_end()?,
+                    })
+                })
+                .collect();
+            (room.as_str(), busy)
+        })
+        .collect()
+}
+
+fn is_off(date: NaiveDate, request: &SlotRequest) -> bool {
+    request.excluded_weekdays.contains(&date.weekday())
+        || request.excluded_dates.contains(&date)
+        || request
+            .holidays
+            .iter()
+            .any(|holiday| holiday.from <= date && date <= holiday.until)
+}
+
+/// Finds slots where every requested teacher and group (and one of the
+/// requested rooms, if any) is free, best candidates first.
+///
+/// Resources that appear in no event are listed in `unknown_resources`.
+///
+/// `events` need not be deduplicated: copies of a session only mark the same
+/// interval busy again.
+pub fn find_free_slots(
+    events: &[NormalizedEvent],
+    request: &SlotRequest,
+) -> Result<SlotReport, String> {
+    let (Some(from), Some(until)) = (request.from, request.until) else {
+        return Err("a date range (`from` and `until`) is required".to_string());
+    };
+    if until < from {
+        return Err("`until` is before `from`".to_string());
+    }
+    if request.duration_minutes == 0 {
+        return Err("`duration_minutes` must be positive".to_string());
+    }
+    let day_start = clock(&request.day_start, "day_start")?;
+    let day_end = clock(&request.day_end, "day_end")?;
+    let starts: Vec<u32> = if request.grid.is_empty() {
+        let step = request.step_minutes.max(1) as usize;
+        (day_start..day_end).step_by(step).collect()
+    } else {
+        let mut starts = request
+            .grid
+            .iter()
+            .map(|start| clock(start, "grid start"))
+            .collect::<Result<Vec<_>, _>>()?;
+        starts.sort_unstable();
+        starts.dedup();
+        starts
+    };
+
+    let participants = participant_calendars(events, request);
+    let mut unknown_resources: Vec<String> = participants
+        .iter()
+        .filter(|(_, busy)| busy.is_empty())
+        .map(|(name, _)| name.to_string())
+        .collect();
+    let (rooms, unknown_rooms): (Vec<_>, Vec<_>) = room_calendars(events, request)
+        .into_iter()
+        .partition(|(_, busy)| !busy.is_empty());
+    unknown_resources.extend(unknown_rooms.into_iter().map(|(room, _)| room.to_string()));
+    let duration = Duration::minutes(i64::from(request.duration_minutes));
+    let buffer = Duration::minutes(i64::from(request.buffer_minutes));
+    let adjacency = Duration::minutes(30).max(buffer);
+    let preferred = request.preferred_date.unwrap_or(from);
+
+    let mut candidates = Vec::new();
+    for date in from.iter_days().take_while(|date| *date <= until) {
+        if is_off(date, request) {
+            continue;
+        }
+        for &minutes in &starts {
+            if minutes < day_start || minutes + request.duration_minutes > day_end {
+                continue;
+            }
+            let start = at(date, minutes);
+            let end = start + duration;
+            if participants
+                .values()
+                .any(|busy| overlaps(busy, start, end, buffer))
+            {
+                continue;
+            }
+            let free_rooms: Vec<String> = rooms
+                .iter()
+                .filter(|(_, busy)| !overlaps(busy, start, end, buffer))
+                .map(|(room, _)| room.to_string())
+                .collect();
+            if !request.rooms.is_empty() && free_rooms.is_empty() {
+                continue;
+            }
+
+            let adjacent = participants
+                .values()
+                .filter(|busy| {
+                    busy.iter().any(|b| {
+                        (b.end <= start && start - b.end <= adjacency)
+                            || (b.start >= end && b.start - end <= adjacency)
+                    })
+                })
+                .count();
+            let compactness = if participants.is_empty() {
+                0.0
+            } else {
+                adjacent as f64 / participants.len() as f64
+            };
+            let days_away = (date - preferred).num_days().unsigned_abs() as f64;
+            let proximity = 1.0 / (1.0 + days_away / 7.0);
+            let score = ((0.6 * proximity + 0.4 * compactness) * 1000.0).round() / 1000.0;
+
+            candidates.push(SlotCandidate {
+                start_iso: start.format("%Y-%m-%dT%H:%M:%S").to_string(),
+                end_iso: end.format("%Y-%m-%dT%H:%M:%S").to_string(),
+                free_rooms,
+                adjacent,
+                score,
+            });
+        }
+    }
+
+    candidates.sort_by(|a, b| {
+        b.score
+            .total_cmp(&a.score)
+            .then_with(|| a.start_iso.cmp(&b.start_iso))
+    });
+    if request.limit > 0 {
+        candidates.truncate(request.limit);
+    }
+    Ok(SlotReport {
+        slots: candidates,
+        unknown_resources,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::normalizer::test_support::located;
+
+    fn date(value: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn finds_grid_slots_free_for_everyone() {
+        // Monday 6 January 2025, times in local Paris time.
+        let events = vec![
+            located(
+                "TD Algo",
+                "DUPONT Jean\nM1 Groupe B",
+                "B12",
+                "20250106T080000",
+                "20250106T094500",
+            ),
+            located(
+                "CM Web",
+                "MARTIN Paul\nM1",
+                "Amphi A",
+                "20250106T094500",
+                "20250106T113000",
+            ),
+            located(
+                "TD Maths",
+                "DURAND Marie\nM2",
+                "B14",
+                "20250106T113000",
+                "20250106T131500",
+            ),
+        ];
+        let request = SlotRequest {
+            teachers: vec!["DUPONT Jean".to_string()],
+            groups: vec!["M1 Groupe A".to_string()],
+            rooms: vec!["B12".to_string(), "B14".to_string()],
+            from: Some(date("2025-01-06")),
+            until: Some(date("2025-01-06")),
+            duration_minutes: 105,
+            grid: vec![
+                "08:00".into(),
+                "09:45".into(),
+                "11:30".into(),
+                "13:30".into(),
+            ],
+            ..SlotRequest::default()
+        };
+
+        let slots = find_free_slots(&events, &request).unwrap().slots;
+        let starts: Vec<&str> = slots.iter().map(|s| s.start_iso.as_str()).collect();
+
+        // 08:00 is taken by the teacher, 09:45 by the whole M1 promo.
+        assert_eq!(starts, vec!["2025-01-06T11:30:00", "2025-01-06T13:30:00"]);
+        // Right after the M1 lecture, so the group is kept busy without a hole.
+        assert_eq!(slots[0].adjacent, 1);
+        assert_eq!(slots[0].free_rooms, vec!["B12".to_string()]);
+        assert_eq!(
+            slots[1].free_rooms,
+            vec!["B12".to_string(), "B14".to_string()]
+        );
+    }
+
+    #[test]
+    fn skips_weekends_holidays_and_excluded_dates() {
+        let request = SlotRequest {
+            teachers: vec!["DUPONT Jean".to_string()],
+            from: Some(date("2024-12-20")),
+            until: Some(date("2025-01-07")),
+            grid: vec!["08:00".into()],
+            excluded_dates: vec![date("2025-01-06")],
+            holidays: vec![Holiday {
+                from: date("2024-12-21"),
+                until: date("2025-01-05"),
+                label: "Noël".to_string(),
+            }],
+            limit: 0,
+            ..SlotRequest::default()
+        };
+
+        let report = find_free_slots(&[], &request).unwrap();
+        let starts: Vec<&str> = report.slots.iter().map(|s| s.start_iso.as_str()).collect();
+
+        assert_eq!(starts, vec!["2024-12-20T08:00:00", "2025-01-07T08:00:00"]);
+        assert_eq!(report.unknown_resources, vec!["DUPONT Jean".to_string()]);
+    }
+
+    #[test]
+    fn reports_resources_missing_from_the_timetable() {
+        let events = vec![located(
+            "TD Algo",
+            "DUPONT Jean\nM1 Groupe B",
+            "B12",
+            "20250106T080000",
+            "20250106T094500",
+        )];
+        let request = SlotRequest {
+            teachers: vec!["DUPONT Jean".to_string(), "DUPOND Jean".to_string()],
+            groups: vec!["M3".to_string()],
+            rooms: vec!["B13".to_string()],
+            from: Some(date("2025-01-06")),
+            until: Some(date("2025-01-06")),
+            grid: vec!["10:00".into()],
+            ..SlotRequest::default()
+        };
+
+        let report = find_free_slots(&events, &request).unwrap();
+
+        assert_eq!(
+            report.unknown_resources,
+            vec![
+                "DUPOND Jean".to_string(),
+                "M3".to_string(),
+                "B13".to_string()
+            ]
+        );
+        // The only requested room is unknown, so it is not offered as free.
+        assert!(report.slots.is_empty());
+    }
+
+    #[test]
+    fn rejects_invalid_requests() {
+        let request = SlotRequest {
+            from: Some(date("2025-01-06")),
+            until: Some(date("2025-01-07")),
+            day_end: "25:00".to_string(),
+            ..SlotRequest::default()
+        };
+        assert_eq!(
+            find_free_slots(&[], &request).unwrap_err(),
+            "invalid day_end `25:00`"
+        );
+        assert!(find_free_slots(&[], &SlotRequest::default()).is_err());
+    }
+}
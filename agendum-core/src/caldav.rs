@@ -0,0 +1,894 @@
+//! CalDAV client (RFC 4791, sync-collection from RFC 6578); requests go
+//! through a [`DavTransport`].
+
+use crate::parser::{parse_ics_content_with_diagnostics, ParseOutput};
+use chrono::{TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+const DAV: &str = "DAV:";
+const CALDAV: &str = "urn:ietf:params:xml:ns:caldav";
+
+/// Hrefs per calendar-multiget request.
+const MULTIGET_BATCH: usize = 100;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct DavRequest {
+    pub method: String,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct DavResponse {
+    pub status: u16,
+    pub body: String,
+}
+
+/// Sends WebDAV requests. Non-2xx statuses are responses, not errors.
+pub trait DavTransport {
+    fn send(&mut self, request: &DavRequest) -> Result<DavResponse, String>;
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct CalendarCollection {
+    pub href: String,
+    pub display_name: Option<String>,
+    pub color: Option<String>,
+    /// Component types the collection accepts, e.g. `["VEVENT", "VTODO"]`;
+    /// empty when the server does not say.
+    pub components: Vec<String>,
+    pub sync_token: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct CalendarObject {
+    pub href: String,
+    pub etag: Option<String>,
+    /// iCalendar text of the resource.
+    pub data: String,
+}
+
+/// UTC bounds of a calendar-query, in epoch milliseconds.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeRange {
+    pub start_ms: i64,
+    pub end_ms: i64,
+}
+
+/// Local copy of a collection kept between incremental syncs.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+#[serde(default)]
+pub struct CollectionState {
+    pub href: String,
+    pub sync_token: Option<String>,
+    /// Objects by absolute URL.
+    pub objects: BTreeMap<String, CalendarObject>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+pub struct SyncChanges {
+    /// URLs of objects added or modified since the previous sync.
+    pub updated: Vec<String>,
+    pub removed: Vec<String>,
+    /// Changed objects the multiget did not return. The sync token is kept,
+    /// so they are listed again on the next sync.
+    pub missing: Vec<String>,
+    /// The server forgot the sync token, or does not support
+    /// sync-collection, and every object was listed again.
+    pub full_resync: bool,
+}
+
+impl CollectionState {
+    pub fn new(href: &str) -> CollectionState {
+        CollectionState {
+            href: href.to_string(),
+            ..CollectionState::default()
+        }
+    }
+
+    pub fn parse(&self) -> ParseOutput {
+        parse_objects(self.objects.values())
+    }
+}
+
+/// Parses calendar objects as one ICS stream, so diagnostics add up over the
+/// collection.
+pub fn parse_objects<'a>(objects: impl IntoIterator<Item = &'a CalendarObject>) -> ParseOutput {
+    let mut content = String::new();
+    for object in objects {
+        content.push_str(object.data.trim_end());
+        content.push_str("\r\n");
+    }
+    parse_ics_content_with_diagnostics(&content)
+}
+
+/// Properties of one `<response>` of a multistatus, from its 200 propstats.
+#[derive(Debug, Default)]
+struct Entry {
+    href: String,
+    /// Status of the response itself, e.g. 404 for members removed since the
+    /// last sync.
+    status: Option<u16>,
+    etag: Option<String>,
+    calendar_data: Option<String>,
+    display_name: Option<String>,
+    color: Option<String>,
+    is_calendar: bool,
+    components: Vec<String>,
+    principal: Option<String>,
+    calendar_home: Option<String>,
+    sync_token: Option<String>,
+}
+
+#[derive(Debug, Default)]
+struct MultiStatus {
+    entries: Vec<Entry>,
+    sync_token: Option<String>,
+}
+
+fn status_code(text: &str) -> Option<u16> {
+    text.split_whitespace().nth(1)?.parse().ok()
+}
+
+fn child<'a, 'i>(
+    node: roxmltree::Node<'a, 'i>,
+    namespace: &str,
+    name: &str,
+) -> Option<roxmltree::Node<'a, 'i>> {
+    node.children().find(|child| {
+        child.tag_name().name() == name && child.tag_name().namespace() == Some(namespace)
+    })
+}
+
+fn text(node: roxmltree::Node) -> String {
+    node.descendants()
+        .filter(|node| node.is_text())
+        .filter_map(|node| node.text())
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+fn parse_multistatus(body: &str) -> Result<MultiStatus, String> {
+    let document =
+        roxmltree::Document::parse(body).map_err(|e| format!("Invalid multistatus: {e}"))?;
+    let root = document.root_element();
+    let mut result = MultiStatus {
+        sync_token: child(root, DAV, "sync-token").map(text),
+        ..MultiStatus::default()
+    };
+    for response in root
+        .children()
+        .filter(|node| node.has_tag_name((DAV, "response")))
+    {
+        let mut entry = Entry {
+            href: child(response, DAV, "href").map(text).unwrap_or_default(),
+            status: child(response, DAV, "status").and_then(|node| status_code(&text(node))),
+            ..Entry::default()
+        };
+        for propstat in response
+            .children()
+            .filter(|node| node.has_tag_name((DAV, "propstat")))
+        {
+            let ok = child(propstat, DAV, "status")
+                .and_then(|node| status_code(&text(node)))
+                .is_some_and(|status| (200..300).contains(&status));
+            let Some(prop) = child(propstat, DAV, "prop").filter(|_| ok) else {
+                continue;
+            };
+            for property in prop.children().filter(|node| node.is_element()) {
+                let href = || child(property, DAV, "href").map(text);
+                match property.tag_name().name() {
+                    "getetag" => entry.etag = Some(text(property)),
+                    "calendar-data" => entry.calendar_data = Some(text(property)),
+                    "displayname" => entry.display_name = Some(text(property)),
+                    "calendar-color" => entry.color = Some(text(property)),
+                    "sync-token" => entry.sync_token = Some(text(property)),
+                    "current-user-principal" => entry.principal = href(),
+                    "calendar-home-set" => entry.calendar_home = href(),
+                    "resourcetype" => {
+                        entry.is_calendar = child(property, CALDAV, "calendar").is_some()
+                    }
+                    "supported-calendar-component-set" => {
+                        entry.components = property
+                            .children()
+                            .filter_map(|comp| comp.attribute("name"))
+                            .map(str::to_string)
+                            .collect()
+                    }
+                    _ => {}
+                }
+            }
+        }
+        result.entries.push(entry);
+    }
+    Ok(result)
+}
+
+fn escape_xml(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn utc_stamp(ms: i64) -> String {
+    Utc.timestamp_millis_opt(ms)
+        .single()
+        .map(|time| time.format("%Y%m%dT%H%M%SZ").to_string())
+        .unwrap_or_default()
+}
+
+const PROPFIND_BODY: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<d:propfind xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav" xmlns:i="http://apple.com/ns/ical/">
+  <d:prop>
+    <d:resourcetype/>
+    <d:displayname/>
+    <d:current-user-principal/>
+    <d:sync-token/>
+    <c:calendar-home-set/>
+    <c:supported-calendar-component-set/>
+    <i:calendar-color/>
+  </d:prop>
+</d:propfind>"#;
+
+fn calendar_query_body(range: Option<TimeRange>, with_data: bool) -> String {
+    let filter = match range {
+        Some(range) => format!(
+            r#"<c:comp-filter name="VEVENT"><c:time-range start="{}" end="{}"/></c:comp-filter>"#,
+            utc_stamp(range.start_ms),
+            utc_stamp(range.end_ms)
+        ),
+        None => r#"<c:comp-filter name="VEVENT"/>"#.to_string(),
+    };
+    // Recurring events are expanded by the server into the instances inside
+    // the range; the ICS parser only reads the first occurrence otherwise.
+    let data = match (with_data, range) {
+        (false, _) => String::new(),
+        (true, Some(range)) => format!(
+            r#"<c:calendar-data><c:expand start="{}" end="{}"/></c:calendar-data>"#,
+            utc_stamp(range.start_ms),
+            utc_stamp(range.end_ms)
+        ),
+        (true, None) => "<c:calendar-data/>".to_string(),
+    };
+    format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<c:calendar-query xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+  <d:prop><d:getetag/>{data}</d:prop>
+  <c:filter><c:comp-filter name="VCALENDAR">{filter}</c:comp-filter></c:filter>
+</c:calendar-query>"#
+    )
+}
+
+fn sync_collection_body(token: Option<&str>) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<d:sync-collection xmlns:d="DAV:">
+  <d:sync-token>{}</d:sync-token>
+  <d:sync-level>1</d:sync-level>
+  <d:prop><d:getetag/></d:prop>
+</d:sync-collection>"#,
+        escape_xml(token.unwrap_or_default())
+    )
+}
+
+fn multiget_body(hrefs: &[String]) -> String {
+    let hrefs: String = hrefs
+        .iter()
+        .map(|href| format!("<d:href>{}</d:href>", escape_xml(href)))
+        .collect();
+    format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<c:calendar-multiget xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+  <d:prop><d:getetag/><c:calendar-data/></d:prop>
+  {hrefs}
+</c:calendar-multiget>"#
+    )
+}
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn base64(input: &[u8]) -> String {
+    let mut out = String::new();
+    for chunk in input.chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = (u32::from(bytes[0]) << 16) | (u32::from(bytes[1]) << 8) | u32::from(bytes[2]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(BASE64[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+impl<T: DavTransport + ?Sized> DavTransport for &mut T {
+    fn send(&mut self, request: &DavRequest) -> Result<DavResponse, String> {
+        (**self).send(request)
+    }
+}
+
+pub struct CalDavClient<T> {
+    transport: T,
+    base_url: String,
+    authorization: Option<String>,
+}
+
+impl<T: DavTransport> CalDavClient<T> {
+    /// `base_url` is the server root, a principal or a calendar collection.
+    pub fn new(base_url: &str, transport: T) -> CalDavClient<T> {
+        CalDavClient {
+            transport,
+            base_url: base_url.to_string(),
+            authorization: None,
+        }
+    }
+
+    /// Sends HTTP Basic credentials with every request.
+    pub fn with_basic_auth(mut self, user: &str, password: &str) -> CalDavClient<T> {
+        let token = base64(format!("{user}:{password}").as_bytes());
+        self.authorization = Some(format!("Basic {token}"));
+        self
+    }
+
+    /// Absolute URL of an href returned by the server.
+    pub fn url(&self, href: &str) -> String {
+        if href.contains("://") {
+            return href.to_string();
+        }
+        let scheme_end = self.base_url.find("://").map_or(0, |index| index + 3);
+        let origin_end = self.base_url[scheme_end..]
+            .find('/')
+            .map_or(self.base_url.len(), |index| scheme_end + index);
+        if href.starts_with('/') {
+            return format!("{}{href}", &self.base_url[..origin_end]);
+        }
+        let directory_end = self
+            .base_url
+            .rfind('/')
+            .filter(|&index| index >= origin_end);
+        match directory_end {
+            Some(index) => format!("{}{href}", &self.base_url[..=index]),
+            None => format!("{}/{href}", &self.base_url[..origin_end]),
+        }
+    }
+
+    fn send(
+        &mut self,
+        method: &str,
+        href: &str,
+        depth: &str,
+        body: String,
+    ) -> Result<DavResponse, String> {
+        let mut headers = vec![
+            ("Depth".to_string(), depth.to_string()),
+            (
+                "Content-Type".to_string(),
+                "application/xml; charset=utf-8".to_string(),
+            ),
+        ];
+        if let Some(authorization) = &self.authorization {
+            headers.push(("Authorization".to_string(), authorization.clone()));
+        }
+        let request = DavRequest {
+            method: method.to_string(),
+            url: self.url(href),
+            headers,
+            body,
+        };
+        self.transport.send(&request)
+    }
+
+    fn multistatus(
+        &mut self,
+        method: &str,
+        href: &str,
+        depth: &str,
+        body: String,
+    ) -> Result<MultiStatus, String> {
+        let response = self.send(method, href, depth, body)?;
+        if response.status != 207 {
+            return Err(format!("{method} {href}: HTTP {}", response.status));
+        }
+        parse_multistatus(&response.body)
+    }
+
+    fn propfind(&mut self, href: &str, depth: &str) -> Result<MultiStatus, String> {
+        self.multistatus("PROPFIND", href, depth, PROPFIND_BODY.to_string())
+    }
+
+    /// Calendars holding events, found from the base URL through the
+    /// current user principal and its calendar home.
+    pub fn discover(&mut self) -> Result<Vec<CalendarCollection>, String> {
+        let base = self.base_url.clone();
+        let found = self.propfind(&base, "0")?;
+        let Some(entry) = found.entries.into_iter().next() else {
+            return Err(format!("PROPFIND {base}: empty multistatus"));
+        };
+        if entry.is_calendar {
+            return Ok(vec![collection(entry)]);
+        }
+        let home = match (entry.calendar_home, entry.principal) {
+            (Some(home), _) => home,
+            (None, Some(principal)) => self
+                .propfind(&principal, "0")?
+                .entries
+                .into_iter()
+                .find_map(|entry| entry.calendar_home)
+                .unwrap_or(principal),
+            (None, None) => base,
+        };
+        let listing = self.propfind(&home, "1")?;
+        Ok(listing
+            .entries
+            .into_iter()
+            .filter(|entry| entry.is_calendar)
+            .filter(|entry| {
+                entry.components.is_empty() || entry.components.iter().any(|c| c == "VEVENT")
+            })
+            .map(collection)
+            .collect())
+    }
+
+    /// Events of a collection, optionally overlapping `range`. With a range,
+    /// recurring events come back as one instance per occurrence; without
+    /// one they are counted in `recurring_events_not_expanded` when parsed.
+    pub fn query(
+        &mut self,
+        href: &str,
+        range: Option<TimeRange>,
+    ) -> Result<Vec<CalendarObject>, String> {
+        let found = self.multistatus("REPORT", href, "1", calendar_query_body(range, true))?;
+        Ok(found.entries.into_iter().filter_map(object).collect())
+    }
+
+    fn multiget(
+        &mut self,
+        collection: &str,
+        hrefs: &[String],
+    ) -> Result<Vec<CalendarObject>, String> {
+        let mut objects = Vec::new();
+        for batch in hrefs.chunks(MULTIGET_BATCH) {
+            let found = self.multistatus("REPORT", collection, "1", multiget_body(batch))?;
+            objects.extend(found.entries.into_iter().filter_map(object));
+        }
+        Ok(objects)
+    }
+
+    /// Brings `state` up to date with a sync-collection REPORT, downloading
+    /// only added and modified objects. Objects are keyed by absolute URL.
+    ///
+    /// When the server rejects the token, or does not support sync-collection,
+    /// members are listed with a calendar-query and compared by ETag instead.
+    /// The token only advances once every changed object was downloaded.
+    pub fn sync(&mut self, state: &mut CollectionState) -> Result<SyncChanges, String> {
+        let href = state.href.clone();
+        let body = sync_collection_body(state.sync_token.as_deref());
+        let response = self.send("REPORT", &href, "1", body)?;
+        let listing = match response.status {
+            207 => Some(parse_multistatus(&response.body)?),
+            400..=499 => None,
+            status => return Err(format!("REPORT {href}: HTTP {status}")),
+        };
+        let collection_url = self.url(&href);
+        let mut members = Vec::new();
+        let mut removed = Vec::new();
+        let (token, full_resync) = match listing {
+            Some(found) => {
+                for entry in found.entries {
+                    let url = self.url(&entry.href);
+                    if url == collection_url {
+                        continue;
+                    }
+                    if entry.status == Some(404) {
+                        removed.push(url);
+                    } else {
+                        members.push(Member {
+                            url,
+                            href: entry.href,
+                            etag: entry.etag,
+                        });
+                    }
+                }
+                (found.sync_token, state.sync_token.is_none())
+            }
+            None => {
+                let found =
+                    self.multistatus("REPORT", &href, "1", calendar_query_body(None, false))?;
+                for entry in found.entries {
+                    let url = self.url(&entry.href);
+                    if url != collection_url {
+                        members.push(Member {
+                            url,
+                            href: entry.href,
+                            etag: entry.etag,
+                        });
+                    }
+                }
+                (None, true)
+            }
+        };
+        if full_resync {
+            removed = stale(state, &members);
+        }
+
+        let changed: Vec<Member> = members
+            .into_iter()
+            .filter(|member| {
+                member.etag.is_none()
+                    || state
+                        .objects
+                        .get(&member.url)
+                        .and_then(|object| object.etag.as_ref())
+                        != member.etag.as_ref()
+            })
+            .collect();
+        let hrefs: Vec<String> = changed.iter().map(|member| member.href.clone()).collect();
+        let mut changes = SyncChanges {
+            full_resync,
+            ..SyncChanges::default()
+        };
+        for mut object in self.multiget(&href, &hrefs)? {
+            object.href = self.url(&object.href);
+            changes.updated.push(object.href.clone());
+            state.objects.insert(object.href.clone(), object);
+        }
+        changes.missing = changed
+            .into_iter()
+            .map(|member| member.url)
+            .filter(|url| !changes.updated.contains(url))
+            .collect();
+        for url in removed {
+            if state.objects.remove(&url).is_some() {
+                changes.removed.push(url);
+            }
+        }
+        if changes.missing.is_empty() {
+            state.sync_token = token;
+        }
+        Ok(changes)
+    }
+}
+
+/// A collection member listed by the server.
+struct Member {
+    url: String,
+    /// As sent by the server, for the multiget.
+    href: String,
+    etag: Option<String>,
+}
+
+/// Hrefs kept locally but missing from a full listing.
+fn stale(state: &CollectionState, members: &[Member]) -> Vec<String> {
+    state
+        .objects
+        .keys()
+        .filter(|url| !members.iter().any(|member| member.url == **url))
+        .cloned()
+        .collect()
+}
+
+fn collection(entry: Entry) -> CalendarCollection {
+    CalendarCollection {
+        href: entry.href,
+        display_name: entry.display_name.filter(|name| !name.is_empty()),
+        color: entry.color.filter(|color| !color.is_empty()),
+        components: entry.components,
+        sync_token: entry.sync_token,
+    }
+}
+
+fn object(entry: Entry) -> Option<CalendarObject> {
+    Some(CalendarObject {
+        data: entry.calendar_data?,
+        href: entry.href,
+        etag: entry.etag,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Answers requests with a handler and keeps them for inspection.
+    struct Fake<F> {
+        handler: F,
+        requests: Vec<DavRequest>,
+    }
+
+    impl<F: FnMut(&DavRequest) -> DavResponse> DavTransport for Fake<F> {
+        fn send(&mut self, request: &DavRequest) -> Result<DavResponse, String> {
+            self.requests.push(request.clone());
+            Ok((self.handler)(request))
+        }
+    }
+
+    fn fake<F: FnMut(&DavRequest) -> DavResponse>(handler: F) -> Fake<F> {
+        Fake {
+            handler,
+            requests: Vec::new(),
+        }
+    }
+
+    fn multistatus(responses: &str, token: Option<&str>) -> DavResponse {
+        let token = token
+            .map(|token| format!("<d:sync-token>{token}</d:sync-token>"))
+            .unwrap_or_default();
+        DavResponse {
+            status: 207,
+            body: format!(
+                r#"<?xml version="1.0"?><d:multistatus xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">{responses}{token}</d:multistatus>"#
+            ),
+        }
+    }
+
+    fn props(href: &str, props: &str) -> String {
+        format!(
+            "<d:response><d:href>{href}</d:href><d:propstat><d:prop>{props}</d:prop>\
+<d:status>HTTP/1.1 200 OK</d:status></d:propstat></d:response>"
+        )
+    }
+
+    fn ics(uid: &str, summary: &str) -> String {
+        format!(
+            "BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nUID:{uid}\r\nSUMMARY:{summary}\r\n\
+DTSTART:20250106T080000\r\nDTEND:20250106T100000\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n"
+        )
+    }
+
+    fn url(name: &str) -> String {
+        format!("http://localhost:5232/jean/jurys/{name}.ics")
+    }
+
+    fn data(href: &str, etag: &str, summary: &str) -> String {
+        let uid = href.trim_end_matches(".ics").rsplit('/').next().unwrap();
+        props(
+            href,
+            &format!(
+                "<d:getetag>{etag}</d:getetag><c:calendar-data>{}</c:calendar-data>",
+                escape_xml(&ics(uid, summary))
+            ),
+        )
+    }
+
+    #[test]
+    fn discovers_event_calendars_through_the_principal() {
+        let mut server = fake(|request: &DavRequest| match request.url.as_str() {
+            "http://localhost:5232/" => multistatus(
+                &props(
+                    "/",
+                    "<d:resourcetype><d:collection/></d:resourcetype>\
+<d:current-user-principal><d:href>/jean/</d:href></d:current-user-principal>",
+                ),
+                None,
+            ),
+            "http://localhost:5232/jean/"
+                if request.headers.contains(&("Depth".into(), "0".into())) =>
+            {
+                multistatus(
+                    &props(
+                        "/jean/",
+                        "<c:calendar-home-set><d:href>/jean/</d:href></c:calendar-home-set>",
+                    ),
+                    None,
+                )
+            }
+            _ => multistatus(
+                &[
+                    props("/jean/", "<d:resourcetype><d:collection/></d:resourcetype>"),
+                    props(
+                        "/jean/jurys/",
+                        "<d:resourcetype><d:collection/><c:calendar/></d:resourcetype>\
+<d:displayname>Jurys</d:displayname><d:sync-token>t1</d:sync-token>\
+<c:supported-calendar-component-set><c:comp name=\"VEVENT\"/></c:supported-calendar-component-set>",
+                    ),
+                    props(
+                        "/jean/todo/",
+                        "<d:resourcetype><d:collection/><c:calendar/></d:resourcetype>\
+<c:supported-calendar-component-set><c:comp name=\"VTODO\"/></c:supported-calendar-component-set>",
+                    ),
+                ]
+                .concat(),
+                None,
+            ),
+        });
+        let calendars = CalDavClient::new("http://localhost:5232/", &mut server)
+            .with_basic_auth("jean", "secret")
+            .discover()
+            .unwrap();
+
+        assert_eq!(calendars.len(), 1);
+        assert_eq!(calendars[0].href, "/jean/jurys/");
+        assert_eq!(calendars[0].display_name.as_deref(), Some("Jurys"));
+        assert_eq!(calendars[0].sync_token.as_deref(), Some("t1"));
+        assert_eq!(server.requests.len(), 3);
+        assert!(server
+            .requests
+            .iter()
+            .all(|request| request.method == "PROPFIND"
+                && request
+                    .headers
+                    .contains(&("Authorization".into(), "Basic amVhbjpzZWNyZXQ=".into()))));
+    }
+
+    #[test]
+    fn syncs_incrementally_and_falls_back_on_rejected_tokens() {
+        let mut server = fake(|request: &DavRequest| {
+            let body = &request.body;
+            if body.contains("sync-collection") {
+                if body.contains("<d:sync-token></d:sync-token>") {
+                    multistatus(
+                        &[
+                            props("/jean/jurys/", ""),
+                            props("/jean/jurys/a.ics", "<d:getetag>\"1\"</d:getetag>"),
+                            props("/jean/jurys/b.ics", "<d:getetag>\"1\"</d:getetag>"),
+                        ]
+                        .concat(),
+                        Some("t1"),
+                    )
+                } else if body.contains(">t1<") {
+                    multistatus(
+                        &[
+                            props("/jean/jurys/b.ics", "<d:getetag>\"2\"</d:getetag>"),
+                            "<d:response><d:href>/jean/jurys/a.ics</d:href>\
+<d:status>HTTP/1.1 404 Not Found</d:status></d:response>"
+                                .to_string(),
+                        ]
+                        .concat(),
+                        Some("t2"),
+                    )
+                } else {
+                    DavResponse {
+                        status: 403,
+                        body: String::new(),
+                    }
+                }
+            } else if body.contains("calendar-multiget") {
+                let mut found = String::new();
+                for (href, summary) in [
+                    ("/jean/jurys/a.ics", "Jury M1"),
+                    ("/jean/jurys/b.ics", "Jury M2"),
+                    ("/jean/jurys/c.ics", "Conseil"),
+                ] {
+                    if body.contains(href) {
+                        found.push_str(&data(href, "\"x\"", summary));
+                    }
+                }
+                multistatus(&found, None)
+            } else {
+                multistatus(
+                    &props("/jean/jurys/c.ics", "<d:getetag>\"1\"</d:getetag>"),
+                    None,
+                )
+            }
+        });
+        let mut client = CalDavClient::new("http://localhost:5232/jean/", &mut server);
+        let mut state = CollectionState::new("/jean/jurys/");
+
+        let first = client.sync(&mut state).unwrap();
+        assert_eq!(first.updated, [url("a"), url("b")]);
+        assert!(first.full_resync);
+        assert_eq!(state.sync_token.as_deref(), Some("t1"));
+        assert_eq!(state.parse().events.len(), 2);
+
+        let second = client.sync(&mut state).unwrap();
+        assert_eq!(second.updated, [url("b")]);
+        assert_eq!(second.removed, [url("a")]);
+        assert!(!second.full_resync);
+        let parsed = state.parse();
+        assert_eq!(parsed.events.len(), 1);
+        assert_eq!(parsed.events[0].summary, "Jury M2");
+
+        let third = client.sync(&mut state).unwrap();
+        assert!(third.full_resync);
+        assert_eq!(third.updated, [url("c")]);
+        assert_eq!(third.removed, [url("b")]);
+        assert_eq!(state.sync_token, None);
+        assert_eq!(server.requests[0].url, "http://localhost:5232/jean/jurys/");
+    }
+
+    #[test]
+    fn keeps_the_token_until_every_change_is_downloaded() {
+        let mut attempts = 0;
+        let mut server = fake(move |request: &DavRequest| {
+            if request.body.contains("sync-collection") {
+                return multistatus(
+                    &[
+                        props("/jean/jurys/a.ics", "<d:getetag>\"1\"</d:getetag>"),
+                        props("/jean/jurys/b.ics", "<d:getetag>\"1\"</d:getetag>"),
+                    ]
+                    .concat(),
+                    Some("t1"),
+                );
+            }
+            attempts += 1;
+            // Absolute hrefs, and b.ics only on the second attempt.
+            let mut found = String::new();
+            if request.body.contains("a.ics") {
+                found.push_str(&data(&url("a"), "\"1\"", "Jury M1"));
+            }
+            if attempts == 1 {
+                found.push_str(
+                    "<d:response><d:href>/jean/jurys/b.ics</d:href>\
+<d:status>HTTP/1.1 404 Not Found</d:status></d:response>",
+                );
+            } else {
+                found.push_str(&data("/jean/jurys/b.ics", "\"1\"", "Jury M2"));
+            }
+            multistatus(&found, None)
+        });
+        let mut client = CalDavClient::new("http://localhost:5232/jean/", &mut server);
+        let mut state = CollectionState::new("/jean/jurys/");
+
+        let first = client.sync(&mut state).unwrap();
+        assert_eq!(first.updated, [url("a")]);
+        assert_eq!(first.missing, [url("b")]);
+        assert_eq!(state.sync_token, None);
+        assert_eq!(state.objects.keys().collect::<Vec<_>>(), [&url("a")]);
+
+        let second = client.sync(&mut state).unwrap();
+        assert_eq!(second.updated, [url("b")]);
+        assert!(second.missing.is_empty());
+        assert_eq!(state.sync_token.as_deref(), Some("t1"));
+        assert_eq!(state.parse().events.len(), 2);
+    }
+
+    #[test]
+    fn queries_a_time_range() {
+        let mut server = fake(|_: &DavRequest| {
+            multistatus(&data("cours/a.ics", "\"1\"", "Jury & délibération"), None)
+        });
+        let mut client = CalDavClient::new("https://dav.example/jean/", &mut server);
+        let objects = client
+            .query(
+                "cours/",
+                Some(TimeRange {
+                    start_ms: 1_736_121_600_000,
+                    end_ms: 1_736_726_400_000,
+                }),
+            )
+            .unwrap();
+
+        assert_eq!(objects.len(), 1);
+        assert_eq!(
+            client.url(&objects[0].href),
+            "https://dav.example/jean/cours/a.ics"
+        );
+        client.query("cours/", None).unwrap();
+        let request = &server.requests[0];
+        assert_eq!(request.method, "REPORT");
+        assert_eq!(request.url, "https://dav.example/jean/cours/");
+        assert!(request
+            .body
+            .contains(r#"<c:time-range start="20250106T000000Z" end="20250113T000000Z"/>"#));
+        assert!(request
+            .body
+            .contains(r#"<c:expand start="20250106T000000Z" end="20250113T000000Z"/>"#));
+        assert_eq!(
+            parse_objects(&objects).events[0].summary,
+            "Jury & délibération"
+        );
+        assert!(server.requests[1].body.contains("<c:calendar-data/>"));
+        let weekly = CalendarObject {
+            href: "cours/conseil.ics".to_string(),
+            etag: None,
+            data: ics("conseil", "Conseil").replace(
+                "DTEND:20250106T100000\r\n",
+                "DTEND:20250106T100000\r\nRRULE:FREQ=WEEKLY;COUNT=4\r\n",
+            ),
+        };
+        let parsed = parse_objects([&weekly]);
+        assert_eq!(parsed.events.len(), 1);
+        assert_eq!(parsed.diagnostics.recurring_events_not_expanded, 1);
+    }
+}
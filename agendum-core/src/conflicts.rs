@@ -0,0 +1,381 @@
+use crate::dedup::{deduplicate, DedupConfig, SourcedEvent};
+use crate::normalizer::text_key;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct ConflictConfig {
+    /// Copies of one session are merged first and never reported as conflicts.
+    pub dedup: DedupConfig,
+    /// Overlaps up to this length are ignored (e.g. a session ending at 10:05
+    /// and the next one starting at 10:00).
+    pub tolerance_minutes: u32,
+    /// Minimum gap between two sessions of a teacher or group held in
+    /// different rooms. 0 disables the check.
+    pub travel_buffer_minutes: u32,
+    pub check_teachers: bool,
+    pub check_rooms: bool,
+    pub check_groups: bool,
+}
+
+impl Default for ConflictConfig {
+    fn default() -> Self {
+        ConflictConfig {
+            dedup: DedupConfig::default(),
+            tolerance_minutes: 0,
+            travel_buffer_minutes: 0,
+            check_teachers: true,
+            check_rooms: true,
+            check_groups: true,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictResource {
+    Teacher,
+    Room,
+    Group,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ConflictKind {
+    /// Both sessions need the resource at the same time.
+    Overlap { minutes: i64 },
+    /// Back-to-back sessions in different rooms without enough time in between.
+    TravelBuffer {
+        gap_minutes: i64,
+        required_minutes: i64,
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Conflict {
+    pub resource: ConflictResource,
+    /// Teacher, room or group as written in the first event.
+    pub name: String,
+    #[serde(flatten)]
+    pub kind: ConflictKind,
+    /// The two sessions involved, earliest first.
+    pub events: Vec<usize>,
+}
+
+/// One session imported several times, e.g. a lecture shared by several promos.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct MutualizedSession {
+    pub kept: usize,
+    pub copies: Vec<usize>,
+    pub promos: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+pub struct ConflictReport {
+    pub conflicts: Vec<Conflict>,
+    /// Overlapping copies that are not conflicts.
+    pub mutualized: Vec<MutualizedSession>,
+}
+
+struct Session {
+    index: usize,
+    start: i64,
+    end: i64,
+    /// (key, display name)
+    teachers: Vec<(String, String)>,
+    room: (String, String),
+    groups: Vec<(String, String)>,
+}
+
+/// "M1" contains "M1 Groupe A": a promo-wide session blocks all of its groups.
+pub(crate) fn groups_overlap(a: &str, b: &str) -> bool {
+    let contains = |outer: &str, inner: &str| {
+        inner
+            .strip_prefix(outer)
+            .is_some_and(|rest| rest.starts_with(' '))
+    };
+    a == b || contains(a, b) || contains(b, a)
+}
+
+fn shared<'a>(
+    a: &'a [(String, String)],
+    b: &[(String, String)],
+    related: impl Fn(&str, &str) -> bool,
+) -> Vec<&'a str> {
+    a.iter()
+        .filter(|(key, _)| b.iter().any(|(other, _)| related(key, other)))
+        .map(|(_, name)| name.as_str())
+        .collect()
+}
+
+/// Reports double bookings of teachers, rooms and student groups.
+///
+/// Events are deduplicated first so that one session seen through several
+/// calendars counts once; the merged copies are listed as mutualized.
+pub fn detect_conflicts(events: &[SourcedEvent], config: &ConflictConfig) -> ConflictReport {
+    let dedup = deduplicate(events, &config.dedup);
+    let mut promos: BTreeMap<usize, &[String]> = BTreeMap::new();
+    let mut mutualized = Vec::new();
+    for cluster in &dedup.clusters {
+        promos.insert(cluster.kept, &cluster.promos);
+        mutualized.push(MutualizedSession {
+            kept: cluster.kept,
+            copies: cluster.duplicates.iter().map(|d| d.index).collect(),
+            promos: cluster.promos.clone(),
+        });
+    }
+
+    let mut sessions: Vec<Session> = dedup
+        .kept_indices()
+        .filter_map(|index| {
+            let event = &events[index].event;
+            let (start, end) = (event.start_utc_ms?, event.end_utc_ms?);
+            let groups = promos
+                .get(&index)
+                .copied()
+                .unwrap_or(event.promos.as_slice());
+            Some(Session {
+                index,
+                start,
+                end,
+                teachers: event
+                    .known_teachers()
+                    .into_iter()
+                    .map(|name| (text_key(name), name.to_string()))
+                    .collect(),
+                room: (
+                    text_key(&event.raw.location),
+                    event.raw.location.trim().to_string(),
+                ),
+                groups: groups
+                    .iter()
+                    .map(|promo| (text_key(promo), promo.clone()))
+                    .filter(|(key, _)| !key.is_empty())
+                    .collect(),
+            })
+        })
+        .collect();
+    sessions.sort_by_key(|s| (s.start, s.end, s.index));
+
+    let tolerance = i64::from(config.tolerance_minutes) * 60_000;
+    let buffer = i64::from(config.travel_buffer_minutes) * 60_000;
+    let mut conflicts = Vec::new();
+
+    for (position, a) in sessions.iter().enumerate() {
+        for b in &sessions[position + 1..] {
+            // Sorted by start: every later session starts even further away.
+            if b.start >= a.end + buffer {
+                break;
+            }
+            let overlap = a.end.min(b.end) - b.start;
+            let mut report = |resource, name: &str, kind: ConflictKind| {
+                conflicts.push(Conflict {
+                    resource,
+                    name: name.to_string(),
+                    kind,
+                    events: vec![a.index, b.index],
+                });
+            };
+
+            if overlap > tolerance {
+                let kind = ConflictKind::Overlap {
+                    minutes: overlap / 60_000,
+                };
+                if config.check_teachers {
+                    for name in shared(&a.teachers, &b.teachers, |x, y| x == y) {
+                        report(ConflictResource::Teacher, name, kind.clone());
+                    }
+                }
+                if config.check_rooms && !a.room.0.is_empty() && a.room.0 == b.room.0 {
+                    report(ConflictResource::Room, &a.room.1, kind.clone());
+                }
+                if config.check_groups {
+                    for name in shared(&a.groups, &b.groups, groups_overlap) {
+                        report(ConflictResource::Group, name, kind.clone());
+                    }
+                }
+                continue;
+            }
+
+            let gap = b.start - a.end;
+            let different_rooms =
+                !a.room.0.is_empty() && !b.room.0.is_empty() && a.room.0 != b.room.0;
+            if buffer > 0 && gap < buffer && different_rooms {
+                let kind = ConflictKind::TravelBuffer {
+                    gap_minutes: gap.max(0) / 60_000,
+                    required_minutes: buffer / 60_000,
+                };
+                if config.check_teachers {
+                    for name in shared(&a.teachers, &b.teachers, |x, y| x == y) {
+                        report(ConflictResource::Teacher, name, kind.clone());
+                    }
+                }
+                if config.check_groups {
+                    for name in shared(&a.groups, &b.groups, groups_overlap) {
+                        report(ConflictResource::Group, name, kind.clone());
+                    }
+                }
+            }
+        }
+    }
+
+    ConflictReport {
+        conflicts,
+        mutualized,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::normalizer::test_support::{self, event_with_uid};
+
+    fn sourced(
+        calendar_id: &str,
+        summary: &str,
+        description: &str,
+        location: &str,
+        start: &str,
+        end: &str,
+    ) -> SourcedEvent {
+        let uid = format!("{calendar_id}-{summary}-{start}");
+        let event = event_with_uid(&uid, summary, description, location, start, end);
+        test_support::sourced(calendar_id, event)
+    }
+
+    #[test]
+    fn reports_teacher_room_and_group_overlaps() {
+        let events = vec![
+            sourced(
+                "m1",
+                "CM Algo",
+                "DUPONT Jean\nM1",
+                "Amphi A",
+                "20250106T080000",
+                "20250106T100000",
+            ),
+            sourced(
+                "m1",
+                "TD Web",
+                "DUPONT Jean\nM1 Groupe A",
+                "B12",
+                "20250106T090000",
+                "20250106T110000",
+            ),
+            sourced(
+                "m2",
+                "TD Maths",
+                "MARTIN Paul\nM2",
+                "B12",
+                "20250106T100000",
+                "20250106T120000",
+            ),
+        ];
+
+        let report = detect_conflicts(&events, &ConflictConfig::default());
+
+        let found: Vec<(ConflictResource, &str, &[usize])> = report
+            .conflicts
+            .iter()
+            .map(|c| (c.resource, c.name.as_str(), c.events.as_slice()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (ConflictResource::Teacher, "DUPONT Jean", &[0, 1][..]),
+                (ConflictResource::Group, "M1", &[0, 1][..]),
+                (ConflictResource::Room, "B12", &[1, 2][..]),
+            ]
+        );
+        assert_eq!(
+            report.conflicts[0].kind,
+            ConflictKind::Overlap { minutes: 60 }
+        );
+    }
+
+    #[test]
+    fn mutualized_copies_are_not_conflicts() {
+        let events = vec![
+            sourced(
+                "m1",
+                "CM Algo",
+                "DUPONT Jean\nM1",
+                "Amphi A",
+                "20250106T080000",
+                "20250106T100000",
+            ),
+            sourced(
+                "m2",
+                "CM Algo",
+                "DUPONT Jean\nM2",
+                "Amphi A",
+                "20250106T080000",
+                "20250106T100000",
+            ),
+        ];
+
+        let report = detect_conflicts(&events, &ConflictConfig::default());
+
+        assert!(report.conflicts.is_empty());
+        assert_eq!(
+            report.mutualized,
+            vec![MutualizedSession {
+                kept: 0,
+                copies: vec![1],
+                promos: vec!["M1".to_string(), "M2".to_string()],
+            }]
+        );
+    }
+
+    #[test]
+    fn travel_buffer_and_tolerance() {
+        let events = vec![
+            sourced(
+                "m1",
+                "TD Algo",
+                "DUPONT Jean",
+                "Bâtiment A",
+                "20250106T080000",
+                "20250106T100500",
+            ),
+            sourced(
+                "m2",
+                "TD Web",
+                "DUPONT Jean",
+                "Bâtiment C",
+                "20250106T100000",
+                "20250106T120000",
+            ),
+            sourced(
+                "m2",
+                "TD Web",
+                "DUPONT Jean",
+                "Bâtiment C",
+                "20250106T130000",
+                "20250106T150000",
+            ),
+        ];
+        let config = ConflictConfig {
+            tolerance_minutes: 5,
+            ..ConflictConfig::default()
+        };
+        assert!(detect_conflicts(&events, &config).conflicts.is_empty());
+
+        let config = ConflictConfig {
+            tolerance_minutes: 5,
+            travel_buffer_minutes: 15,
+            ..ConflictConfig::default()
+        };
+        let report = detect_conflicts(&events, &config);
+        assert_eq!(report.conflicts.len(), 1);
+        assert_eq!(
+            report.conflicts[0].kind,
+            ConflictKind::TravelBuffer {
+                gap_minutes: 0,
+                required_minutes: 15,
+            }
+        );
+        assert_eq!(report.conflicts[0].events, vec![0, 1]);
+    }
+}
@@ -0,0 +1,819 @@
+//! Timetables circulated as spreadsheets (CSV exports of Excel, LibreOffice or
+//! ADE), one session per row.
+
+use crate::identity::fnv1a;
+use crate::normalizer::{detect_type, normalize, NormalizedEvent};
+use crate::parser::RawEvent;
+use crate::search::fold;
+use chrono::{Duration, NaiveDate, NaiveDateTime, NaiveTime};
+use serde::{Deserialize, Serialize};
+
+/// Longest session accepted; anything longer is a misread end or duration.
+const MAX_SESSION_MINUTES: i64 = 12 * 60;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum CsvField {
+    Date,
+    Start,
+    End,
+    Duration,
+    Subject,
+    Type,
+    Teacher,
+    Group,
+    Room,
+    Uid,
+    Description,
+}
+
+impl CsvField {
+    pub const ALL: [CsvField; 11] = [
+        CsvField::Date,
+        CsvField::Start,
+        CsvField::End,
+        CsvField::Duration,
+        CsvField::Subject,
+        CsvField::Type,
+        CsvField::Teacher,
+        CsvField::Group,
+        CsvField::Room,
+        CsvField::Uid,
+        CsvField::Description,
+    ];
+}
+
+/// A column given by zero-based position, by header name, or by the first
+/// header found among several names. Names ignore case and accents.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum Column {
+    Index(usize),
+    Name(String),
+    AnyOf(Vec<String>),
+}
+
+impl Column {
+    fn any_of(names: &[&str]) -> Option<Column> {
+        Some(Column::AnyOf(
+            names.iter().map(|name| name.to_string()).collect(),
+        ))
+    }
+
+    fn resolve(&self, header: Option<&[String]>, width: usize) -> Option<usize> {
+        let names = match self {
+            Column::Index(index) => return (*index < width).then_some(*index),
+            Column::Name(name) => std::slice::from_ref(name),
+            Column::AnyOf(names) => names.as_slice(),
+        };
+        let header: Vec<String> = header?.iter().map(|cell| fold(cell.trim())).collect();
+        names
+            .iter()
+            .find_map(|name| header.iter().position(|cell| *cell == fold(name.trim())))
+    }
+}
+
+/// Where each field is read from; unmapped fields stay empty.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct ColumnMapping {
+    pub date: Option<Column>,
+    /// Start time, or date and time when there is no date column.
+    pub start: Option<Column>,
+    pub end: Option<Column>,
+    /// Used when there is no end column: `1h30`, `1:30` or decimal hours.
+    pub duration: Option<Column>,
+    pub subject: Option<Column>,
+    #[serde(rename = "type")]
+    pub session_type: Option<Column>,
+    pub teacher: Option<Column>,
+    pub group: Option<Column>,
+    pub room: Option<Column>,
+    /// Rows without one get a UID derived from their content.
+    pub uid: Option<Column>,
+    pub description: Option<Column>,
+}
+
+impl ColumnMapping {
+    pub fn column(&self, field: CsvField) -> Option<&Column> {
+        match field {
+            CsvField::Date => self.date.as_ref(),
+            CsvField::Start => self.start.as_ref(),
+            CsvField::End => self.end.as_ref(),
+            CsvField::Duration => self.duration.as_ref(),
+            CsvField::Subject => self.subject.as_ref(),
+            CsvField::Type => self.session_type.as_ref(),
+            CsvField::Teacher => self.teacher.as_ref(),
+            CsvField::Group => self.group.as_ref(),
+            CsvField::Room => self.room.as_ref(),
+            CsvField::Uid => self.uid.as_ref(),
+            CsvField::Description => self.description.as_ref(),
+        }
+    }
+}
+
+impl Default for ColumnMapping {
+    fn default() -> Self {
+        MappingPreset::Generic.mapping()
+    }
+}
+
+/// Header names of common spreadsheet layouts.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MappingPreset {
+    /// French and English headers typed by hand: `Date`, `Début`, `Fin`,
+    /// `Matière`, `Type`, `Enseignant`, `Groupe`, `Salle`…
+    Generic,
+    /// CSV export of ADE: `Date`, `Heure`, `Durée`, `Activité`, `Formateurs`,
+    /// `Trainees`, `Salles`.
+    Ade,
+}
+
+impl MappingPreset {
+    pub fn mapping(self) -> ColumnMapping {
+        match self {
+            MappingPreset::Generic => ColumnMapping {
+                date: Column::any_of(&["date", "jour"]),
+                start: Column::any_of(&[
+                    "début",
+                    "heure début",
+                    "heure de début",
+                    "start",
+                    "heure",
+                ]),
+                end: Column::any_of(&["fin", "heure fin", "heure de fin", "end"]),
+                duration: Column::any_of(&["durée", "duration"]),
+                subject: Column::any_of(&[
+                    "matière",
+                    "module",
+                    "enseignement",
+                    "cours",
+                    "intitulé",
+                    "subject",
+                ]),
+                session_type: Column::any_of(&["type", "nature"]),
+                teacher: Column::any_of(&["enseignant", "enseignants", "intervenant", "teacher"]),
+                group: Column::any_of(&["groupe", "groupes", "promo", "promotion", "group"]),
+                room: Column::any_of(&["salle", "salles", "lieu", "room"]),
+                uid: None,
+                description: Column::any_of(&["remarque", "commentaire", "description"]),
+            },
+            MappingPreset::Ade => ColumnMapping {
+                date: Column::any_of(&["date"]),
+                start: Column::any_of(&["heure", "heure début"]),
+                end: Column::any_of(&["heure fin"]),
+                duration: Column::any_of(&["durée"]),
+                subject: Column::any_of(&["activité", "nom", "matière"]),
+                session_type: Column::any_of(&["type"]),
+                teacher: Column::any_of(&["formateurs", "enseignants", "instructors"]),
+                group: Column::any_of(&["trainees", "groupes", "étudiants"]),
+                room: Column::any_of(&["salles", "classrooms"]),
+                uid: Column::any_of(&["id", "identifiant"]),
+                description: Column::any_of(&["commentaire", "remarques"]),
+            },
+        }
+    }
+}
+
+/// How `01/02/2025` is read; ISO dates (`2025-02-01`) are always year first.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum DateOrder {
+    /// French `dd/mm/yyyy`.
+    #[default]
+    DayFirst,
+    MonthFirst,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct CsvImportOptions {
+    pub mapping: ColumnMapping,
+    /// `None` picks whichever of `;`, `,` and tab is most frequent in the
+    /// first line.
+    pub delimiter: Option<char>,
+    /// Without a header, columns can only be mapped by position.
+    pub has_header: bool,
+    pub date_order: DateOrder,
+}
+
+impl Default for CsvImportOptions {
+    fn default() -> Self {
+        CsvImportOptions {
+            mapping: ColumnMapping::default(),
+            delimiter: None,
+            has_header: true,
+            date_order: DateOrder::default(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedColumn {
+    pub field: CsvField,
+    pub index: usize,
+    pub header: Option<String>,
+}
+
+/// Why a row was not imported. Lines count from 1, header included.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct RowIssue {
+    pub line: usize,
+    pub field: Option<CsvField>,
+    pub message: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct CsvDiagnostics {
+    pub delimiter: char,
+    pub columns: Vec<ResolvedColumn>,
+    /// Non-empty data rows.
+    pub rows_read: usize,
+    pub events_imported: usize,
+    pub issues: Vec<RowIssue>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct CsvImport {
+    pub events: Vec<RawEvent>,
+    pub diagnostics: CsvDiagnostics,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct CsvNormalizedImport {
+    pub events: Vec<NormalizedEvent>,
+    pub diagnostics: CsvDiagnostics,
+}
+
+/// A row with its mapped cells, kept to override what [`normalize`] guesses.
+struct Row {
+    raw: RawEvent,
+    subject: Option<String>,
+    session_type: Option<String>,
+    teachers: Vec<String>,
+    groups: Vec<String>,
+}
+
+const DELIMITERS: [char; 3] = [';', ',', '\t'];
+
+fn detect_delimiter(text: &str) -> char {
+    let mut counts = [0usize; DELIMITERS.len()];
+    let mut quoted = false;
+    for ch in text.chars() {
+        match ch {
+            '"' => quoted = !quoted,
+            '\n' if !quoted => break,
+            _ if !quoted => {
+                if let Some(index) = DELIMITERS.iter().position(|delimiter| *delimiter == ch) {
+                    counts[index] += 1;
+                }
+            }
+            _ => {}
+        }
+    }
+    // Ties go to `;`, what French spreadsheets write.
+    let mut best = 0;
+    for index in 1..DELIMITERS.len() {
+        if counts[index] > counts[best] {
+            best = index;
+        }
+    }
+    if counts[best] == 0 {
+        ','
+    } else {
+        DELIMITERS[best]
+    }
+}
+
+/// RFC 4180 records with the line each one starts on.
+fn records(text: &str, delimiter: char) -> Vec<(usize, Vec<String>)> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut line = 1;
+    let mut start_line = 1;
+    let mut chars = text.chars().peekable();
+    while let Some(ch) = chars.next() {
+        if quoted {
+            match ch {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => quoted = false,
+                '\r' => {}
+                _ => {
+                    line += usize::from(ch == '\n');
+                    field.push(ch);
+                }
+            }
+            continue;
+        }
+        match ch {
+            '"' if field.is_empty() => quoted = true,
+            '\r' => {}
+            '\n' => {
+                record.push(std::mem::take(&mut field));
+                records.push((start_line, std::mem::take(&mut record)));
+                line += 1;
+                start_line = line;
+            }
+            _ if ch == delimiter => record.push(std::mem::take(&mut field)),
+            _ => field.push(ch),
+        }
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push((start_line, record));
+    }
+    records
+}
+
+/// Days since 1899-12-30, how spreadsheets store dates.
+fn spreadsheet_date(value: &str) -> Option<NaiveDate> {
+    let serial: f64 = value.replace(',', ".").parse().ok()?;
+    if !(20_000.0..=80_000.0).contains(&serial) {
+        return None;
+    }
+    NaiveDate::from_ymd_opt(1899, 12, 30)?.checked_add_signed(Duration::days(serial as i64))
+}
+
+fn parse_date(value: &str, order: DateOrder) -> Option<NaiveDate> {
+    let value = value.trim();
+    if value.len() == 8 && value.bytes().all(|byte| byte.is_ascii_digit()) {
+        return NaiveDate::parse_from_str(value, "%Y%m%d").ok();
+    }
+    if let Some(date) = spreadsheet_date(value) {
+        return Some(date);
+    }
+    let parts: Vec<&str> = value.split(['/', '-', '.']).collect();
+    let [first, second, third] = parts[..] else {
+        return None;
+    };
+    let number = |part: &str| part.trim().parse::<u32>().ok();
+    let (year, month, day) = if first.len() == 4 {
+        (number(first)?, number(second)?, number(third)?)
+    } else {
+        let (day, month) = match order {
+            DateOrder::DayFirst => (number(first)?, number(second)?),
+            DateOrder::MonthFirst => (number(second)?, number(first)?),
+        };
+        let year = number(third)?;
+        (
+            if third.len() == 2 { 2000 + year } else { year },
+            month,
+            day,
+        )
+    };
+    NaiveDate::from_ymd_opt(year as i32, month, day)
+}
+
+/// `8:30`, `08:30:00`, `8h30`, `8h` or decimal hours (`8.5`, `8,5`), in
+/// minutes.
+fn parse_minutes(value: &str) -> Option<i64> {
+    let value = value.trim().to_lowercase();
+    if let Some((hours, minutes)) = value.split_once([':', 'h']) {
+        let hours: i64 = hours.trim().parse().ok()?;
+        let minutes = minutes.split(':').next().unwrap_or_default().trim();
+        let minutes: i64 = if minutes.is_empty() {
+            0
+        } else {
+            minutes.parse().ok()?
+        };
+        return (hours >= 0 && (0..60).contains(&minutes)).then_some(hours * 60 + minutes);
+    }
+    if let Some(minutes) = value
+        .strip_suffix("min")
+        .or_else(|| value.strip_suffix("mn"))
+    {
+        return minutes.trim().parse().ok().filter(|minutes| *minutes >= 0);
+    }
+    let hours: f64 = value.replace(',', ".").parse().ok()?;
+    (hours >= 0.0).then(|| (hours * 60.0).round() as i64)
+}
+
+/// A duration cell: like [`parse_minutes`], except that a bare integer above
+/// 24 cannot be hours and is read as minutes (`90`).
+fn parse_duration(value: &str) -> Option<i64> {
+    match value.trim().parse::<i64>() {
+        Ok(minutes) if minutes > 24 => Some(minutes),
+        _ => parse_minutes(value),
+    }
+}
+
+fn parse_time(value: &str) -> Option<NaiveTime> {
+    let minutes = parse_minutes(value).filter(|minutes| *minutes < 24 * 60)?;
+    NaiveTime::from_hms_opt((minutes / 60) as u32, (minutes % 60) as u32, 0)
+}
+
+/// A time, optionally preceded by a date (`06/01/2025 08:00`,
+/// `2025-01-06T08:00`); `date` is used when the cell has none.
+fn parse_datetime(value: &str, date: Option<NaiveDate>, order: DateOrder) -> Option<NaiveDateTime> {
+    if let Some((day, time)) = value.trim().split_once([' ', 'T']) {
+        if let Some(day) = parse_date(day, order) {
+            return Some(day.and_time(parse_time(time)?));
+        }
+    }
+    Some(date?.and_time(parse_time(value)?))
+}
+
+/// Names separated by commas, semicolons, slashes or new lines.
+fn split_names(value: Option<&str>) -> Vec<String> {
+    value
+        .unwrap_or_default()
+        .split([',', ';', '/', '\n'])
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+fn read_row(
+    line: usize,
+    cell: impl Fn(CsvField) -> Option<String>,
+    order: DateOrder,
+) -> Result<Row, RowIssue> {
+    let issue = |field: CsvField, message: String| RowIssue {
+        line,
+        field: Some(field),
+        message,
+    };
+    let date = match cell(CsvField::Date) {
+        Some(value) => Some(
+            parse_date(&value, order)
+                .ok_or_else(|| issue(CsvField::Date, format!("Unrecognized date \"{value}\"")))?,
+        ),
+        None => None,
+    };
+    let start_cell = cell(CsvField::Start)
+        .ok_or_else(|| issue(CsvField::Start, "Missing start time".to_string()))?;
+    let start = parse_datetime(&start_cell, date, order).ok_or_else(|| {
+        let message = match date {
+            Some(_) => format!("Unrecognized start time \"{start_cell}\""),
+            None => format!("Missing date for start time \"{start_cell}\""),
+        };
+        issue(CsvField::Start, message)
+    })?;
+    let end = match (cell(CsvField::End), cell(CsvField::Duration)) {
+        (Some(value), _) => parse_datetime(&value, Some(start.date()), order)
+            .ok_or_else(|| issue(CsvField::End, format!("Unrecognized end time \"{value}\"")))?,
+        (None, Some(value)) => {
+            let minutes = parse_duration(&value).ok_or_else(|| {
+                issue(
+                    CsvField::Duration,
+                    format!("Unrecognized duration \"{value}\""),
+                )
+            })?;
+            start + Duration::minutes(minutes)
+        }
+        (None, None) => {
+            return Err(issue(
+                CsvField::End,
+                "Missing end time or duration".to_string(),
+            ));
+        }
+    };
+    if end <= start {
+        return Err(issue(
+            CsvField::End,
+            "Session ends before it starts".to_string(),
+        ));
+    }
+    if end - start > Duration::minutes(MAX_SESSION_MINUTES) {
+        return Err(issue(
+            CsvField::End,
+            format!("Session lasts more than {} hours", MAX_SESSION_MINUTES / 60),
+        ));
+    }
+
+    let subject = cell(CsvField::Subject);
+    let session_type = cell(CsvField::Type);
+    let teachers = split_names(cell(CsvField::Teacher).as_deref());
+    let groups = split_names(cell(CsvField::Group).as_deref());
+    let summary = match (&session_type, &subject) {
+        (Some(kind), Some(subject)) => format!("{kind} {subject}"),
+        (kind, subject) => kind.clone().or(subject.clone()).unwrap_or_default(),
+    };
+    let description: Vec<String> = groups
+        .iter()
+        .chain(&teachers)
+        .cloned()
+        .chain(cell(CsvField::Description))
+        .collect();
+    let location = cell(CsvField::Room).unwrap_or_default();
+    let start = start.format("%Y%m%dT%H%M%S").to_string();
+    let end = end.format("%Y%m%dT%H%M%S").to_string();
+    let uid = cell(CsvField::Uid).unwrap_or_else(|| {
+        let groups = groups.join(",");
+        let key = [&start, &end, &summary, &groups, &location].map(String::as_str);
+        format!("csv-{:016x}", fnv1a(&key.join("\u{1f}")))
+    });
+    Ok(Row {
+        raw: RawEvent {
+            uid,
+            summary,
+            description: description.join("\n"),
+            location,
+            start,
+            end,
+        },
+        subject,
+        session_type,
+        teachers,
+        groups,
+    })
+}
+
+fn read_rows(content: &str, options: &CsvImportOptions) -> (Vec<Row>, CsvDiagnostics) {
+    let content = content.strip_prefix('\u{feff}').unwrap_or(content);
+    let delimiter = options
+        .delimiter
+        .unwrap_or_else(|| detect_delimiter(content));
+    let mut diagnostics = CsvDiagnostics {
+        delimiter,
+        ..CsvDiagnostics::default()
+    };
+    let mut records = records(content, delimiter).into_iter();
+    let header = if options.has_header {
+        records.next().map(|(_, cells)| cells)
+    } else {
+        None
+    };
+    let records: Vec<(usize, Vec<String>)> = records
+        .filter(|(_, cells)| cells.iter().any(|cell| !cell.trim().is_empty()))
+        .collect();
+    let width = header
+        .as_ref()
+        .map(Vec::len)
+        .into_iter()
+        .chain(records.iter().map(|(_, cells)| cells.len()))
+        .max()
+        .unwrap_or_default();
+
+    for field in CsvField::ALL {
+        let Some(index) = options
+            .mapping
+            .column(field)
+            .and_then(|column| column.resolve(header.as_deref(), width))
+        else {
+            continue;
+        };
+        diagnostics.columns.push(ResolvedColumn {
+            field,
+            index,
+            header: header
+                .as_ref()
+                .and_then(|header| header.get(index))
+                .cloned(),
+        });
+    }
+    let index = |field: CsvField| {
+        diagnostics
+            .columns
+            .iter()
+            .find(|column| column.field == field)
+            .map(|column| column.index)
+    };
+    let required = [
+        (index(CsvField::Start).is_some(), CsvField::Start, "start"),
+        (
+            index(CsvField::End).or(index(CsvField::Duration)).is_some(),
+            CsvField::End,
+            "end or duration",
+        ),
+    ];
+    let missing: Vec<RowIssue> = required
+        .into_iter()
+        .filter(|(found, _, _)| !found)
+        .map(|(_, field, name)| RowIssue {
+            line: 1,
+            field: Some(field),
+            message: format!("No {name} column"),
+        })
+        .collect();
+    diagnostics.rows_read = records.len();
+    if !missing.is_empty() {
+        diagnostics.issues = missing;
+        return (Vec::new(), diagnostics);
+    }
+
+    let columns: Vec<(CsvField, usize)> = diagnostics
+        .columns
+        .iter()
+        .map(|column| (column.field, column.index))
+        .collect();
+    let mut rows = Vec::new();
+    for (line, cells) in records {
+        let cell = |field: CsvField| {
+            let (_, index) = columns.iter().find(|(mapped, _)| *mapped == field)?;
+            let value = cells.get(*index)?.trim();
+            (!value.is_empty()).then(|| value.to_string())
+        };
+        match read_row(line, cell, options.date_order) {
+            Ok(row) => rows.push(row),
+            Err(issue) => diagnostics.issues.push(issue),
+        }
+    }
+    diagnostics.events_imported = rows.len();
+    (rows, diagnostics)
+}
+
+/// Reads a CSV timetable into raw events; rows that cannot be placed in time
+/// are reported in the diagnostics and left out.
+pub fn import_csv(content: &str, options: &CsvImportOptions) -> CsvImport {
+    let (rows, diagnostics) = read_rows(content, options);
+    CsvImport {
+        events: rows.into_iter().map(|row| row.raw).collect(),
+        diagnostics,
+    }
+}
+
+/// Like [`import_csv`], then normalized with the mapped subject, type,
+/// teachers and groups taking precedence over what [`normalize`] infers.
+pub fn import_csv_normalized(content: &str, options: &CsvImportOptions) -> CsvNormalizedImport {
+    let (rows, diagnostics) = read_rows(content, options);
+    let raw: Vec<RawEvent> = rows.iter().map(|row| row.raw.clone()).collect();
+    let events = normalize(raw)
+        .into_iter()
+        .zip(rows)
+        .map(|(mut event, row)| {
+            if let Some(subject) = row.subject {
+                event.subject = subject;
+            }
+            if let Some(session_type) = row.session_type {
+                event.type_ = detect_type(&session_type);
+            }
+            if !row.teachers.is_empty() {
+                event.teachers = row.teachers;
+            }
+            if !row.groups.is_empty() {
+                event.promos = row.groups;
+            }
+            event
+        })
+        .collect();
+    CsvNormalizedImport {
+        events,
+        diagnostics,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::normalizer::test_support;
+
+    const TIMETABLE: &str = "\u{feff}Date;Début;Fin;Matière;Type;Enseignant;Groupe;Salle\r\n\
+06/01/2025;8h00;10h00;Algorithmique;CM;DUPONT Jean;M1 INFO;Amphi A\r\n\
+07/01/2025;13,5;15.75;\"Réseaux; avancé\";TD;MARTIN Paul, DURAND Léa;M1 INFO / M1 MIAGE;B12\r\n\
+\r\n\
+31/02/2025;8:00;10:00;Algorithmique;CM;DUPONT Jean;M1 INFO;Amphi A\r\n\
+08/01/2025;10:00;9:00;Algorithmique;CM;DUPONT Jean;M1 INFO;Amphi A\r\n";
+
+    #[test]
+    fn imports_french_rows_with_diagnostics() {
+        let import = import_csv(TIMETABLE, &CsvImportOptions::default());
+        let diagnostics = &import.diagnostics;
+        assert_eq!(diagnostics.delimiter, ';');
+        assert_eq!(diagnostics.rows_read, 4);
+        assert_eq!(diagnostics.events_imported, 2);
+        assert_eq!(diagnostics.columns.len(), 8);
+        assert_eq!(diagnostics.columns[1].header.as_deref(), Some("Début"));
+        assert_eq!(
+            diagnostics.issues,
+            vec![
+                RowIssue {
+                    line: 5,
+                    field: Some(CsvField::Date),
+                    message: "Unrecognized date \"31/02/2025\"".to_string(),
+                },
+                RowIssue {
+                    line: 6,
+                    field: Some(CsvField::End),
+                    message: "Session ends before it starts".to_string(),
+                },
+            ]
+        );
+
+        let cm = &import.events[0];
+        assert_eq!(cm.summary, "CM Algorithmique");
+        assert_eq!(cm.description, "M1 INFO\nDUPONT Jean");
+        assert_eq!(
+            (cm.start.as_str(), cm.end.as_str()),
+            ("20250106T080000", "20250106T100000")
+        );
+        assert!(cm.uid.starts_with("csv-"));
+        let td = &import.events[1];
+        assert_eq!(td.summary, "TD Réseaux; avancé");
+        assert_eq!(
+            (td.start.as_str(), td.end.as_str()),
+            ("20250107T133000", "20250107T154500")
+        );
+        assert_eq!(
+            import_csv(TIMETABLE, &CsvImportOptions::default()).events[1].uid,
+            td.uid
+        );
+
+        let normalized = import_csv_normalized(TIMETABLE, &CsvImportOptions::default()).events;
+        assert_eq!(normalized[1].subject, "Réseaux; avancé");
+        assert_eq!(normalized[1].type_, "TD");
+        assert_eq!(normalized[1].teachers, vec!["MARTIN Paul", "DURAND Léa"]);
+        assert_eq!(normalized[1].promos, vec!["M1 INFO", "M1 MIAGE"]);
+        assert_eq!(normalized[1].minutes(), 135);
+        assert_eq!(
+            normalized[0].start_rfc3339.as_deref(),
+            Some("2025-01-06T08:00:00+01:00")
+        );
+    }
+
+    #[test]
+    fn maps_columns_by_position_and_preset() {
+        let options = CsvImportOptions {
+            mapping: ColumnMapping {
+                date: None,
+                start: Some(Column::Index(0)),
+                duration: Some(Column::Index(1)),
+                subject: Some(Column::Index(2)),
+                ..ColumnMapping::default()
+            },
+            has_header: false,
+            date_order: DateOrder::MonthFirst,
+            ..CsvImportOptions::default()
+        };
+        let import = import_csv(
+            "01/06/2025 08:00,1h30,Algo\n2025-01-07T14:00,2,Algo\n",
+            &options,
+        );
+        assert_eq!(import.diagnostics.delimiter, ',');
+        assert_eq!(import.events[0].start, "20250106T080000");
+        assert_eq!(import.events[0].end, "20250106T093000");
+        assert_eq!(import.events[1].end, "20250107T160000");
+
+        let ade = CsvImportOptions {
+            mapping: MappingPreset::Ade.mapping(),
+            ..CsvImportOptions::default()
+        };
+        let import = import_csv(
+            "Date\tHeure\tDurée\tActivité\tSalles\n45663\t08:00\t2h\tCM Algo\tAmphi A\n",
+            &ade,
+        );
+        assert_eq!(import.diagnostics.delimiter, '\t');
+        assert_eq!(import.events[0].start, "20250106T080000");
+        assert_eq!(import.events[0].end, "20250106T100000");
+        assert_eq!(import.events[0].location, "Amphi A");
+
+        let missing = import_csv(
+            "Date;Matière\n06/01/2025;Algo\n",
+            &CsvImportOptions::default(),
+        );
+        assert!(missing.events.is_empty());
+        assert_eq!(missing.diagnostics.issues.len(), 2);
+        assert_eq!(missing.diagnostics.issues[0].message, "No start column");
+    }
+
+    #[test]
+    fn reads_durations_short_years_and_serial_dates() {
+        let options = CsvImportOptions {
+            mapping: ColumnMapping {
+                date: None,
+                end: None,
+                start: Some(Column::Index(0)),
+                duration: Some(Column::Index(1)),
+                subject: Some(Column::Index(2)),
+                session_type: Some(Column::Index(3)),
+                ..ColumnMapping::default()
+            },
+            has_header: false,
+            ..CsvImportOptions::default()
+        };
+        let import = import_csv_normalized(
+            "06/01/25 08:00;90;Algo;td2\n\
+45664 14:00;45 min;Algo;Cours\n\
+08/01/2025 08:00;30;Algo;CM\n\
+09/01/2025 08:00;13h;Algo;CM\n",
+            &options,
+        );
+
+        let events = &import.events;
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[0].raw.start, "20250106T080000");
+        assert_eq!(events[0].minutes(), 90);
+        assert_eq!(events[0].type_, "TD");
+        assert_eq!(events[1].raw.start, "20250107T140000");
+        assert_eq!(events[1].minutes(), 45);
+        // Unknown types read like those of unclassified ICS summaries.
+        let ics = test_support::event("Cours", "", "20250107T140000", "20250107T144500");
+        assert_eq!(events[1].type_, ics.type_);
+        assert_eq!(events[2].minutes(), 30);
+        assert_eq!(
+            import.diagnostics.issues,
+            vec![RowIssue {
+                line: 4,
+                field: Some(CsvField::End),
+                message: "Session lasts more than 12 hours".to_string(),
+            }]
+        );
+    }
+}
@@ -0,0 +1,546 @@
+use crate::normalizer::{text_key, NormalizedEvent};
+use crate::session_type::SessionType;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// A normalized event tagged with the calendar it was imported from.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SourcedEvent {
+    pub calendar_id: String,
+    pub event: NormalizedEvent,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum TeacherMatch {
+    /// Same set of named teachers.
+    #[default]
+    Exact,
+    /// At least one teacher in common, or no named teacher on either side.
+    Overlap,
+    Ignore,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct DedupConfig {
+    /// Maximum drift of start and end times between two copies of a session.
+    pub time_tolerance_minutes: u32,
+    pub match_subject: bool,
+    pub match_type: bool,
+    pub teachers: TeacherMatch,
+    /// Require the same room when both copies have one.
+    pub match_room: bool,
+    /// Treat a shared non-empty UID at the same start instant as proof of
+    /// identity, whatever the other keys. The start is required because the
+    /// occurrences of a recurring event share their UID.
+    pub match_uid: bool,
+}
+
+impl Default for DedupConfig {
+    fn default() -> Self {
+        DedupConfig {
+            time_tolerance_minutes: 0,
+            match_subject: true,
+            match_type: true,
+            teachers: TeacherMatch::Exact,
+            match_room: false,
+            match_uid: true,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum DedupKey {
+    Uid,
+    Time,
+    Subject,
+    Type,
+    Teachers,
+    Room,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ClusterKind {
+    /// The same session seen through several calendars (e.g. one CM per promo).
+    CrossCalendar,
+    /// The same session repeated inside a single calendar.
+    SameCalendar,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateMember {
+    pub index: usize,
+    pub calendar_id: String,
+    /// Keys on which this copy agrees with the kept representative.
+    pub matched_on: Vec<DedupKey>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct DedupCluster {
+    /// Index of the kept representative: the first copy in input order.
+    pub kept: usize,
+    pub kind: ClusterKind,
+    pub duplicates: Vec<DuplicateMember>,
+    pub calendar_ids: Vec<String>,
+    /// Union of the promos of every copy.
+    pub promos: Vec<String>,
+}
+
+/// Distinct sessions sharing a time slot, subject and type: parallel groups
+/// taught simultaneously, each of which counts in the service.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ParallelSessions {
+    /// Kept representative of each parallel session.
+    pub sessions: Vec<usize>,
+    /// Keys that tell the sessions apart.
+    pub differing_on: Vec<DedupKey>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+pub struct DedupResult {
+    /// Only sessions seen more than once.
+    pub clusters: Vec<DedupCluster>,
+    pub parallel: Vec<ParallelSessions>,
+    /// One flag per input event; `true` for every copy but the kept one.
+    pub duplicate: Vec<bool>,
+}
+
+impl DedupResult {
+    pub fn kept_indices(&self) -> impl Iterator<Item = usize> + '_ {
+        self.duplicate
+            .iter()
+            .enumerate()
+            .filter(|(_, duplicate)| !**duplicate)
+            .map(|(index, _)| index)
+    }
+}
+
+struct Keys {
+    uid: String,
+    start: Option<i64>,
+    end: Option<i64>,
+    subject: String,
+    type_: String,
+    teachers: BTreeSet<String>,
+    room: String,
+}
+
+impl Keys {
+    fn new(event: &NormalizedEvent) -> Self {
+        let session_type = SessionType::from_type(&event.type_);
+        Keys {
+            uid: event.raw.uid.trim().to_string(),
+            start: event.start_utc_ms,
+            end: event.end_utc_ms,
+            subject: text_key(&event.subject),
+            type_: if session_type == SessionType::Other {
+                text_key(&event.type_)
+            } else {
+                session_type.label().to_string()
+            },
+            teachers: event.known_teachers().into_iter().map(text_key).collect(),
+            room: text_key(&event.raw.location),
+        }
+    }
+}
+
+struct Comparison {
+    matched: Vec<DedupKey>,
+    same_slot: bool,
+    same_identity: bool,
+}
+
+fn compare(a: &Keys, b: &Keys, config: &DedupConfig) -> Comparison {
+    let mut matched = Vec::new();
+    let tolerance = i64::from(config.time_tolerance_minutes) * 60_000;
+    let close = |x: Option<i64>, y: Option<i64>| match (x, y) {
+        (Some(x), Some(y)) => (x - y).abs() <= tolerance,
+        _ => false,
+    };
+
+    if !a.uid.is_empty() && a.uid == b.uid {
+        matched.push(DedupKey::Uid);
+    }
+    let same_time = close(a.start, b.start) && close(a.end, b.end);
+    if same_time {
+        matched.push(DedupKey::Time);
+    }
+    let same_subject = a.subject == b.subject;
+    if same_subject {
+        matched.push(DedupKey::Subject);
+    }
+    let same_type = a.type_ == b.type_;
+    if same_type {
+        matched.push(DedupKey::Type);
+    }
+    let same_teachers = match config.teachers {
+        TeacherMatch::Exact => a.teachers == b.teachers,
+        TeacherMatch::Overlap => {
+            a.teachers.is_empty() || b.teachers.is_empty() || !a.teachers.is_disjoint(&b.teachers)
+        }
+        TeacherMatch::Ignore => true,
+    };
+    if a.teachers == b.teachers {
+        matched.push(DedupKey::Teachers);
+    }
+    let same_room =
+        !config.match_room || a.room.is_empty() || b.room.is_empty() || a.room == b.room;
+    if a.room == b.room {
+        matched.push(DedupKey::Room);
+    }
+
+    let same_slot =
+        same_time && (!config.match_subject || same_subject) && (!config.match_type || same_type);
+
+    Comparison {
+        matched,
+        same_slot,
+        same_identity: same_teachers && same_room,
+    }
+}
+
+fn find(parents: &mut [usize], index: usize) -> usize {
+    let mut root = index;
+    while parents[root] != root {
+        root = parents[root];
+    }
+    let mut current = index;
+    while parents[current] != root {
+        let next = parents[current];
+        parents[current] = root;
+        current = next;
+    }
+    root
+}
+
+/// Keeps the smallest index as root so the first copy in input order wins.
+fn union(parents: &mut [usize], a: usize, b: usize) {
+    let (ra, rb) = (find(parents, a), find(parents, b));
+    if ra != rb {
+        parents[ra.max(rb)] = ra.min(rb);
+    }
+}
+
+/// Groups copies of the same session across calendars.
+///
+/// Input order is the priority order: the first copy of a session is kept.
+pub fn deduplicate(events: &[SourcedEvent], config: &DedupConfig) -> DedupResult {
+    let keys: Vec<Keys> = events
+        .iter()
+        .map(|sourced| Keys::new(&sourced.event))
+        .collect();
+    let mut sessions: Vec<usize> = (0..events.len()).collect();
+    let mut slot_pairs: Vec<(usize, usize)> = Vec::new();
+
+    if config.match_uid {
+        let mut by_uid: BTreeMap<(&str, Option<i64>), usize> = BTreeMap::new();
+        for (index, key) in keys.iter().enumerate() {
+            if key.uid.is_empty() {
+                continue;
+            }
+            match by_uid.get(&(key.uid.as_str(), key.start)) {
+                Some(&first) => union(&mut sessions, first, index),
+                None => {
+                    by_uid.insert((&key.uid, key.start), index);
+                }
+            }
+        }
+    }
+
+    // Sweep events by start time so only candidates within the tolerance are compared.
+    let mut by_start: Vec<usize> = (0..events.len())
+        .filter(|&i| keys[i].start.is_some())
+        .collect();
+    by_start.sort_by_key(|&i| keys[i].start);
+    let tolerance = i64::from(config.time_tolerance_minutes) * 60_000;
+    for (position, &a) in by_start.iter().enumerate() {
+        let start_a = keys[a].start.unwrap_or_default();
+        for &b in &by_start[position + 1..] {
+            if keys[b].start.unwrap_or_default() - start_a > tolerance {
+                break;
+            }
+            let comparison = compare(&keys[a], &keys[b], config);
+            if !comparison.same_slot {
+                continue;
+            }
+            if comparison.same_identity {
+                union(&mut sessions, a, b);
+            } else {
+                slot_pairs.push((a, b));
+            }
+        }
+    }
+
+    let mut members: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+    for index in 0..events.len() {
+        let root = find(&mut sessions, index);
+        members.entry(root).or_default().push(index);
+    }
+
+    let mut duplicate = vec![false; events.len()];
+    let mut clusters = Vec::new();
+    for (&kept, copies) in &members {
+        if copies.len() < 2 {
+            continue;
+        }
+        let calendar_ids: BTreeSet<&str> = copies
+            .iter()
+            .map(|&i| events[i].calendar_id.as_str())
+            .collect();
+        let promos: BTreeSet<&str> = copies
+            .iter()
+            .flat_map(|&i| events[i].event.promos.iter().map(String::as_str))
+            .collect();
+        let duplicates = copies[1..]
+            .iter()
+            .map(|&index| {
+                duplicate[index] = true;
+                DuplicateMember {
+                    index,
+                    calendar_id: events[index].calendar_id.clone(),
+                    matched_on: compare(&keys[kept], &keys[index], config).matched,
+                }
+            })
+            .collect();
+
+        clusters.push(DedupCluster {
+            kept,
+            kind: if calendar_ids.len() > 1 {
+                ClusterKind::CrossCalendar
+            } else {
+                ClusterKind::SameCalendar
+            },
+            duplicates,
+            calendar_ids: calendar_ids.into_iter().map(str::to_string).collect(),
+            promos: promos.into_iter().map(str::to_string).collect(),
+        });
+    }
+
+    // Slot pairs whose sessions did not merge are parallel groups.
+    let mut slots: Vec<usize> = (0..events.len()).collect();
+    for &(a, b) in &slot_pairs {
+        let (ra, rb) = (find(&mut sessions, a), find(&mut sessions, b));
+        if ra != rb {
+            union(&mut slots, ra, rb);
+        }
+    }
+    let mut slot_members: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+    for &kept in members.keys() {
+        let slot = find(&mut slots, kept);
+        slot_members.entry(slot).or_default().push(kept);
+    }
+    let parallel = slot_members
+        .into_values()
+        .filter(|sessions| sessions.len() > 1)
+        .map(|sessions| {
+            let mut differing_on = BTreeSet::new();
+            for (position, &a) in sessions.iter().enumerate() {
+                for &b in &sessions[position + 1..] {
+                    let matched = compare(&keys[a], &keys[b], config).matched;
+                    for key in [DedupKey::Teachers, DedupKey::Room] {
+                        if !matched.contains(&key) {
+                            differing_on.insert(key);
+                        }
+                    }
+                }
+            }
+            ParallelSessions {
+                sessions,
+                differing_on: differing_on.into_iter().collect(),
+            }
+        })
+        .collect();
+
+    DedupResult {
+        clusters,
+        parallel,
+        duplicate,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::normalizer::test_support::{self, event_with_uid};
+
+    fn sourced(
+        calendar_id: &str,
+        uid: &str,
+        summary: &str,
+        description: &str,
+        location: &str,
+        start: &str,
+        end: &str,
+    ) -> SourcedEvent {
+        let event = event_with_uid(uid, summary, description, location, start, end);
+        test_support::sourced(calendar_id, event)
+    }
+
+    #[test]
+    fn merges_mutualized_cm_across_promo_calendars() {
+        let events = vec![
+            sourced(
+                "m1a",
+                "a1",
+                "CM Algo",
+                "DUPONT Jean\nM1 Groupe A",
+                "Amphi B",
+                "20250106T080000",
+                "20250106T100000",
+            ),
+            sourced(
+                "m1b",
+                "b1",
+                "CM Algo",
+                "DUPONT Jean\nM1 Groupe B",
+                "Amphi B",
+                "20250106T080000",
+                "20250106T100000",
+            ),
+            sourced(
+                "m1c",
+                "c1",
+                "CM Algo",
+                "DUPONT Jean\nM1 Groupe C",
+                "",
+                "20250106T080000",
+                "20250106T100000",
+            ),
+            sourced(
+                "m1a",
+                "a2",
+                "TD Algo",
+                "DUPONT Jean\nM1 Groupe A",
+                "B12",
+                "20250106T100000",
+                "20250106T120000",
+            ),
+        ];
+
+        let result = deduplicate(&events, &DedupConfig::default());
+
+        assert_eq!(result.duplicate, vec![false, true, true, false]);
+        assert_eq!(result.clusters.len(), 1);
+        let cluster = &result.clusters[0];
+        assert_eq!(cluster.kept, 0);
+        assert_eq!(cluster.kind, ClusterKind::CrossCalendar);
+        assert_eq!(cluster.calendar_ids, vec!["m1a", "m1b", "m1c"]);
+        assert_eq!(cluster.promos.len(), 3);
+        assert!(cluster.duplicates[0].matched_on.contains(&DedupKey::Room));
+        assert!(!cluster.duplicates[1].matched_on.contains(&DedupKey::Room));
+        assert!(result.parallel.is_empty());
+        assert_eq!(result.kept_indices().collect::<Vec<_>>(), vec![0, 3]);
+    }
+
+    #[test]
+    fn parallel_groups_with_different_teachers_are_kept() {
+        let events = vec![
+            sourced(
+                "m1a",
+                "a1",
+                "TP Réseaux",
+                "DUPONT Jean\nM1 Groupe A",
+                "B12",
+                "20250107T080000",
+                "20250107T100000",
+            ),
+            sourced(
+                "m1b",
+                "b1",
+                "TP Réseaux",
+                "MARTIN Paul\nM1 Groupe B",
+                "B13",
+                "20250107T080000",
+                "20250107T100000",
+            ),
+        ];
+
+        let result = deduplicate(&events, &DedupConfig::default());
+
+        assert_eq!(result.duplicate, vec![false, false]);
+        assert!(result.clusters.is_empty());
+        assert_eq!(result.parallel.len(), 1);
+        assert_eq!(result.parallel[0].sessions, vec![0, 1]);
+        assert_eq!(
+            result.parallel[0].differing_on,
+            vec![DedupKey::Teachers, DedupKey::Room]
+        );
+    }
+
+    #[test]
+    fn room_and_tolerance_and_uid_rules_are_configurable() {
+        let events = vec![
+            sourced(
+                "m1a",
+                "x",
+                "TD Algo",
+                "DUPONT Jean",
+                "B12",
+                "20250106T080000",
+                "20250106T100000",
+            ),
+            sourced(
+                "m1b",
+                "y",
+                "TD Algo",
+                "DUPONT Jean",
+                "B13",
+                "20250106T080500",
+                "20250106T100000",
+            ),
+            sourced(
+                "m1a",
+                "x",
+                "TD Algo (prolongé)",
+                "DUPONT Jean",
+                "B12",
+                "20250106T080000",
+                "20250106T103000",
+            ),
+            // Next occurrence of a recurring event: same UID, another session.
+            sourced(
+                "m1a",
+                "x",
+                "TD Algo",
+                "DUPONT Jean",
+                "B12",
+                "20250113T080000",
+                "20250113T100000",
+            ),
+        ];
+
+        let strict = deduplicate(
+            &events,
+            &DedupConfig {
+                match_room: true,
+                time_tolerance_minutes: 10,
+                ..DedupConfig::default()
+            },
+        );
+        assert_eq!(strict.duplicate, vec![false, false, true, false]);
+        assert_eq!(strict.clusters[0].kind, ClusterKind::SameCalendar);
+        assert_eq!(
+            strict.clusters[0].duplicates[0].matched_on,
+            vec![
+                DedupKey::Uid,
+                DedupKey::Type,
+                DedupKey::Teachers,
+                DedupKey::Room
+            ]
+        );
+        assert_eq!(strict.parallel[0].differing_on, vec![DedupKey::Room]);
+
+        let loose = deduplicate(
+            &events,
+            &DedupConfig {
+                time_tolerance_minutes: 10,
+                match_uid: false,
+                ..DedupConfig::default()
+            },
+        );
+        assert_eq!(loose.duplicate, vec![false, true, false, false]);
+    }
+}
@@ -0,0 +1,379 @@
+use crate::identity::{match_events, IdentityConfig};
+use crate::normalizer::{text_key, NormalizedEvent};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+pub use crate::identity::MatchMethod;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum EventChangeDetail {
+    /// Start or end time changed.
+    Rescheduled {
+        old_start: String,
+        old_end: String,
+        new_start: String,
+        new_end: String,
+    },
+    /// Room changed.
+    Relocated { from: String, to: String },
+    /// Teacher set changed.
+    Reassigned { from: Vec<String>, to: Vec<String> },
+    /// Any other normalized field changed.
+    Modified {
+        field: String,
+        from: String,
+        to: String,
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct EventChange {
+    pub old_index: usize,
+    pub new_index: usize,
+    pub matched_by: MatchMethod,
+    pub confidence: f64,
+    pub details: Vec<EventChangeDetail>,
+}
+
+impl EventChange {
+    pub fn is_rescheduled(&self) -> bool {
+        self.details
+            .iter()
+            .any(|d| matches!(d, EventChangeDetail::Rescheduled { .. }))
+    }
+
+    pub fn is_relocated(&self) -> bool {
+        self.details
+            .iter()
+            .any(|d| matches!(d, EventChangeDetail::Relocated { .. }))
+    }
+
+    pub fn is_reassigned(&self) -> bool {
+        self.details
+            .iter()
+            .any(|d| matches!(d, EventChangeDetail::Reassigned { .. }))
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+pub struct DiffSummary {
+    pub added: usize,
+    pub removed: usize,
+    pub rescheduled: usize,
+    pub relocated: usize,
+    pub reassigned: usize,
+    pub modified: usize,
+    pub unchanged: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct SnapshotDiff {
+    /// Indices into the new snapshot.
+    pub added: Vec<usize>,
+    /// Indices into the old snapshot.
+    pub removed: Vec<usize>,
+    /// Matched events with at least one difference, in new snapshot order.
+    pub changed: Vec<EventChange>,
+    pub summary: DiffSummary,
+}
+
+fn sorted_teachers(event: &NormalizedEvent) -> Vec<String> {
+    let teachers: BTreeSet<String> = event
+        .known_teachers()
+        .into_iter()
+        .map(str::to_string)
+        .collect();
+    teachers.into_iter().collect()
+}
+
+fn teacher_keys(event: &NormalizedEvent) -> BTreeSet<String> {
+    event.known_teachers().into_iter().map(text_key).collect()
+}
+
+fn instants(event: &NormalizedEvent) -> (Option<i64>, Option<i64>) {
+    (event.start_utc_ms, event.end_utc_ms)
+}
+
+fn compare_events(old: &NormalizedEvent, new: &NormalizedEvent) -> Vec<EventChangeDetail> {
+    let mut details = Vec::new();
+
+    let same_time = match (instants(old), instants(new)) {
+        ((Some(os), Some(oe)), (Some(ns), Some(ne))) => os == ns && oe == ne,
+        _ => old.start_iso == new.start_iso && old.end_iso == new.end_iso,
+    };
+    if !same_time {
+        details.push(EventChangeDetail::Rescheduled {
+            old_start: old.start_iso.clone(),
+            old_end: old.end_iso.clone(),
+            new_start: new.start_iso.clone(),
+            new_end: new.end_iso.clone(),
+        });
+    }
+
+    if text_key(&old.raw.location) != text_key(&new.raw.location) {
+        details.push(EventChangeDetail::Relocated {
+            from: old.raw.location.trim().to_string(),
+            to: new.raw.location.trim().to_string(),
+        });
+    }
+
+    let teachers_changed = teacher_keys(old) != teacher_keys(new);
+    if teachers_changed {
+        details.push(EventChangeDetail::Reassigned {
+            from: sorted_teachers(old),
+            to: sorted_teachers(new),
+        });
+    }
+
+    let mut fields = vec![
+        ("subject", old.subject.clone(), new.subject.clone()),
+        ("type", old.type_.clone(), new.type_.clone()),
+        ("promos", old.promos.join(", "), new.promos.join(", ")),
+    ];
+    // Teachers and promos are read from the description, so it only counts
+    // as modified when neither of them explains the change.
+    let promos_changed = text_key(&fields[2].1) != text_key(&fields[2].2);
+    if !teachers_changed && !promos_changed {
+        fields.push((
+            "description",
+            old.cleaned_description.clone(),
+            new.cleaned_description.clone(),
+        ));
+    }
+    for (field, from, to) in fields {
+        if text_key(&from) != text_key(&to) {
+            details.push(EventChangeDetail::Modified {
+                field: field.to_string(),
+                from,
+                to,
+            });
+        }
+    }
+
+    details
+}
+
+/// Compares two snapshots of one calendar with the default identity rules.
+pub fn diff_snapshots(old: &[NormalizedEvent], new: &[NormalizedEvent]) -> SnapshotDiff {
+    diff_snapshots_with(old, new, &IdentityConfig::default())
+}
+
+/// Compares two snapshots of one calendar.
+///
+/// Events are paired by [`match_events`], so regenerated UIDs and small
+/// shifts do not show up as a removal plus an addition.
+pub fn diff_snapshots_with(
+    old: &[NormalizedEvent],
+    new: &[NormalizedEvent],
+    config: &IdentityConfig,
+) -> SnapshotDiff {
+    let matching = match_events(old, new, config);
+    let mut diff = SnapshotDiff {
+        added: matching.unmatched_new,
+        removed: matching.unmatched_old,
+        ..SnapshotDiff::default()
+    };
+    diff.summary.added = diff.added.len();
+    diff.summary.removed = diff.removed.len();
+
+    for pair in matching.matches {
+        let details = compare_events(&old[pair.old_index], &new[pair.new_index]);
+        if details.is_empty() {
+            diff.summary.unchanged += 1;
+            continue;
+        }
+        let change = EventChange {
+            old_index: pair.old_index,
+            new_index: pair.new_index,
+            matched_by: pair.method,
+            confidence: pair.confidence,
+            details,
+        };
+        diff.summary.rescheduled += usize::from(change.is_rescheduled());
+        diff.summary.relocated += usize::from(change.is_relocated());
+        diff.summary.reassigned += usize::from(change.is_reassigned());
+        diff.summary.modified += usize::from(
+            change
+                .details
+                .iter()
+                .any(|d| matches!(d, EventChangeDetail::Modified { .. })),
+        );
+        diff.changed.push(change);
+    }
+
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::normalizer::test_support::event_with_uid;
+
+    #[test]
+    fn classifies_changes_between_refreshes() {
+        let old = vec![
+            event_with_uid(
+                "a",
+                "TP Réseaux",
+                "DUPONT Jean",
+                "B12",
+                "20250109T080000",
+                "20250109T100000",
+            ),
+            event_with_uid(
+                "b",
+                "CM Algo",
+                "DUPONT Jean",
+                "Amphi A",
+                "20250110T080000",
+                "20250110T100000",
+            ),
+            event_with_uid(
+                "c",
+                "TD Algo",
+                "DUPONT Jean",
+                "B13",
+                "20250110T100000",
+                "20250110T120000",
+            ),
+            event_with_uid(
+                "d",
+                "TD Algo",
+                "MARTIN Paul",
+                "B14",
+                "20250111T100000",
+                "20250111T120000",
+            ),
+            event_with_uid(
+                "e",
+                "TD Maths",
+                "MARTIN Paul",
+                "B14",
+                "20250112T100000",
+                "20250112T120000",
+            ),
+        ];
+        let new = vec![
+            // Thursday's TP moved to Friday and to another room
+            event_with_uid(
+                "a",
+                "TP Réseaux",
+                "DUPONT Jean",
+                "B15",
+                "20250110T140000",
+                "20250110T160000",
+            ),
+            // CM cancelled (b missing), new exam added
+            event_with_uid(
+                "f",
+                "CC Algo",
+                "",
+                "Amphi A",
+                "20250117T080000",
+                "20250117T100000",
+            ),
+            event_with_uid(
+                "c",
+                "TD Algo",
+                "MARTIN Paul",
+                "B13",
+                "20250110T100000",
+                "20250110T120000",
+            ),
+            event_with_uid(
+                "d",
+                "TD Algorithmique",
+                "MARTIN Paul",
+                "B14",
+                "20250111T100000",
+                "20250111T120000",
+            ),
+            event_with_uid(
+                "e",
+                "TD Maths",
+                "MARTIN Paul",
+                "B14",
+                "20250112T100000",
+                "20250112T120000",
+            ),
+        ];
+
+        let diff = diff_snapshots(&old, &new);
+
+        assert_eq!(diff.added, vec![1]);
+        assert_eq!(diff.removed, vec![1]);
+        assert_eq!(
+            diff.summary,
+            DiffSummary {
+                added: 1,
+                removed: 1,
+                rescheduled: 1,
+                relocated: 1,
+                reassigned: 1,
+                modified: 1,
+                unchanged: 1,
+            }
+        );
+        assert_eq!(diff.changed.len(), 3);
+        assert!(diff.changed[0].is_rescheduled() && diff.changed[0].is_relocated());
+        assert_eq!(
+            diff.changed[1].details,
+            vec![EventChangeDetail::Reassigned {
+                from: vec!["DUPONT Jean".to_string()],
+                to: vec!["MARTIN Paul".to_string()],
+            }]
+        );
+        assert!(matches!(
+            &diff.changed[2].details[0],
+            EventChangeDetail::Modified { field, .. } if field == "subject"
+        ));
+    }
+
+    #[test]
+    fn regenerated_uids_fall_back_to_fingerprint() {
+        let old = vec![
+            event_with_uid(
+                "x1",
+                "TD Algo",
+                "DUPONT Jean",
+                "B12",
+                "20250106T080000",
+                "20250106T100000",
+            ),
+            event_with_uid(
+                "x2",
+                "TD Algo",
+                "DUPONT Jean",
+                "B12",
+                "20250113T080000",
+                "20250113T100000",
+            ),
+        ];
+        let new = vec![
+            event_with_uid(
+                "y2",
+                "TD Algo",
+                "DUPONT Jean",
+                "B12",
+                "20250113T080000",
+                "20250113T100000",
+            ),
+            event_with_uid(
+                "y1",
+                "TD Algo",
+                "DUPONT Jean",
+                "B12",
+                "20250106T080000",
+                "20250106T100000",
+            ),
+        ];
+
+        let diff = diff_snapshots(&old, &new);
+
+        assert!(diff.added.is_empty());
+        assert!(diff.removed.is_empty());
+        assert!(diff.changed.is_empty());
+        assert_eq!(diff.summary.unchanged, 2);
+    }
+}
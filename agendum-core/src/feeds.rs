@@ -0,0 +1,260 @@
+//! Saved queries published as subscribable ICS feeds.
+
+use crate::dedup::{deduplicate, DedupConfig, SourcedEvent};
+use crate::ics_writer::{write_ics, IcsExportOptions, UidMode};
+use crate::identity::fnv1a;
+use crate::normalizer::NormalizedEvent;
+use crate::query::{Query, QueryError};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct SavedFeed {
+    /// Identifier used in the feed URL: lowercase letters, digits, `-` and `_`.
+    pub name: String,
+    /// Calendar title shown by subscribing clients; defaults to the name.
+    pub title: Option<String>,
+    pub query: String,
+    /// Copies of a session imported from several calendars are published
+    /// once. `null` keeps every copy.
+    pub dedup: Option<DedupConfig>,
+    pub uid_mode: UidMode,
+    pub alarm_minutes_before: Option<u32>,
+}
+
+impl Default for SavedFeed {
+    fn default() -> Self {
+        SavedFeed {
+            name: String::new(),
+            title: None,
+            query: String::new(),
+            dedup: Some(DedupConfig::default()),
+            uid_mode: UidMode::Original,
+            alarm_minutes_before: None,
+        }
+    }
+}
+
+impl SavedFeed {
+    pub fn validate(&self) -> Result<(), String> {
+        let valid_name = !self.name.is_empty()
+            && self
+                .name
+                .chars()
+                .all(|ch| ch.is_ascii_lowercase() || ch.is_ascii_digit() || ch == '-' || ch == '_');
+        if !valid_name {
+            return Err(format!(
+                "Invalid feed name `{}`: use lowercase letters, digits, `-` and `_`",
+                self.name
+            ));
+        }
+        Query::parse(&self.query)
+            .map(|_| ())
+            .map_err(|e| format!("Invalid query for feed `{}`: {e}", self.name))
+    }
+
+    /// ICS options the feed is published with.
+    pub fn export_options(&self, generated_at: Option<&str>) -> IcsExportOptions {
+        IcsExportOptions {
+            calendar_name: Some(self.title.clone().unwrap_or_else(|| self.name.clone())),
+            uid_mode: self.uid_mode,
+            alarm_minutes_before: self.alarm_minutes_before,
+            generated_at: generated_at.map(str::to_string),
+            ..IcsExportOptions::default()
+        }
+    }
+}
+
+/// Named feeds, kept in the order they were added.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+#[serde(transparent)]
+pub struct FeedStore {
+    feeds: Vec<SavedFeed>,
+}
+
+impl FeedStore {
+    /// Builds a store, rejecting invalid and duplicate feeds.
+    pub fn new(feeds: Vec<SavedFeed>) -> Result<FeedStore, String> {
+        let mut store = FeedStore::default();
+        for feed in feeds {
+            if store.get(&feed.name).is_some() {
+                return Err(format!("Duplicate feed `{}`", feed.name));
+            }
+            store.save(feed)?;
+        }
+        Ok(store)
+    }
+
+    pub fn feeds(&self) -> &[SavedFeed] {
+        &self.feeds
+    }
+
+    pub fn get(&self, name: &str) -> Option<&SavedFeed> {
+        self.feeds.iter().find(|feed| feed.name == name)
+    }
+
+    /// Adds a feed, or replaces the one with the same name.
+    pub fn save(&mut self, feed: SavedFeed) -> Result<(), String> {
+        feed.validate()?;
+        match self.feeds.iter_mut().find(|saved| saved.name == feed.name) {
+            Some(saved) => *saved = feed,
+            None => self.feeds.push(feed),
+        }
+        Ok(())
+    }
+
+    pub fn remove(&mut self, name: &str) -> bool {
+        let before = self.feeds.len();
+        self.feeds.retain(|feed| feed.name != name);
+        self.feeds.len() != before
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct RenderedFeed {
+    pub ics: String,
+    /// Quoted ETag of the published content; `DTSTAMP` is left out so that it
+    /// only changes with the events.
+    pub etag: String,
+    pub events: usize,
+}
+
+/// Events of a feed among those of every imported calendar.
+///
+/// Events are ordered by start, calendar and UID before deduplication, so the
+/// copy that is kept does not depend on import order.
+pub fn select_feed_events(
+    feed: &SavedFeed,
+    events: &[SourcedEvent],
+) -> Result<Vec<NormalizedEvent>, QueryError> {
+    let query = Query::parse(&feed.query)?;
+    let mut selected: Vec<&SourcedEvent> = events
+        .iter()
+        .filter(|sourced| query.matches_sourced(sourced))
+        .collect();
+    selected.sort_by(|a, b| {
+        (a.event.start_utc_ms, &a.calendar_id, &a.event.raw.uid).cmp(&(
+            b.event.start_utc_ms,
+            &b.calendar_id,
+            &b.event.raw.uid,
+        ))
+    });
+    let selected: Vec<SourcedEvent> = selected.into_iter().cloned().collect();
+    Ok(match &feed.dedup {
+        Some(config) => deduplicate(&selected, config)
+            .kept_indices()
+            .map(|index| selected[index].event.clone())
+            .collect(),
+        None => selected.into_iter().map(|sourced| sourced.event).collect(),
+    })
+}
+
+/// Renders a feed over the events of every imported calendar.
+pub fn render_feed(
+    feed: &SavedFeed,
+    events: &[SourcedEvent],
+    generated_at: Option<&str>,
+) -> Result<RenderedFeed, QueryError> {
+    let kept = select_feed_events(feed, events)?;
+    let ics = write_ics(&kept, &feed.export_options(generated_at))?;
+    let content: String = ics
+        .split_inclusive("\r\n")
+        .filter(|line| !line.starts_with("DTSTAMP:"))
+        .collect();
+    Ok(RenderedFeed {
+        etag: format!("\"{:016x}\"", fnv1a(&content)),
+        events: kept.len(),
+        ics,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::normalizer::test_support::{self, event_with_uid};
+
+    fn sourced(
+        calendar_id: &str,
+        uid: &str,
+        summary: &str,
+        description: &str,
+        start: &str,
+        end: &str,
+    ) -> SourcedEvent {
+        let event = event_with_uid(uid, summary, description, "", start, end);
+        test_support::sourced(calendar_id, event)
+    }
+
+    fn feed(name: &str, query: &str) -> SavedFeed {
+        SavedFeed {
+            name: name.to_string(),
+            query: query.to_string(),
+            ..SavedFeed::default()
+        }
+    }
+
+    #[test]
+    fn store_validates_names_and_queries() {
+        let mut store = FeedStore::new(vec![feed("dupont", "teacher:\"DUPONT Jean\"")]).unwrap();
+        assert!(store.save(feed("Dupont Jean", "")).is_err());
+        assert!(store.save(feed("broken", "after:never")).is_err());
+        assert!(FeedStore::new(vec![feed("a", ""), feed("a", "type:TD")]).is_err());
+
+        store.save(feed("dupont", "teacher:DUPONT*")).unwrap();
+        assert_eq!(store.feeds().len(), 1);
+        assert_eq!(store.get("dupont").unwrap().query, "teacher:DUPONT*");
+        assert!(store.remove("dupont"));
+        assert!(!store.remove("dupont"));
+    }
+
+    #[test]
+    fn publishes_one_copy_with_a_stable_uid_and_etag() {
+        let m2 = sourced(
+            "m2",
+            "ade-b",
+            "CM Algo",
+            "M2 INFO\nDUPONT Jean",
+            "20250106T080000",
+            "20250106T100000",
+        );
+        let m1 = sourced(
+            "m1",
+            "ade-a",
+            "CM Algo",
+            "M1 INFO\nDUPONT Jean",
+            "20250106T080000",
+            "20250106T100000",
+        );
+        let other = sourced(
+            "m1",
+            "ade-c",
+            "TP Réseaux",
+            "M1 INFO\nMARTIN Paul",
+            "20250107T080000",
+            "20250107T100000",
+        );
+        let dedup = DedupConfig {
+            match_uid: false,
+            ..DedupConfig::default()
+        };
+        let feed = SavedFeed {
+            title: Some("Service DUPONT".to_string()),
+            dedup: Some(dedup),
+            ..feed("dupont", "teacher:\"DUPONT Jean\"")
+        };
+
+        let first = render_feed(
+            &feed,
+            &[m2.clone(), m1.clone(), other.clone()],
+            Some("2025-01-01T00:00:00Z"),
+        )
+        .unwrap();
+        let second = render_feed(&feed, &[other, m1, m2], Some("2025-02-01T00:00:00Z")).unwrap();
+
+        assert_eq!(first.events, 1);
+        assert!(first.ics.contains("UID:ade-a\r\n"));
+        assert!(first.ics.contains("X-WR-CALNAME:Service DUPONT\r\n"));
+        assert_ne!(first.ics, second.ics);
+        assert_eq!(first.etag, second.etag);
+    }
+}
@@ -0,0 +1,230 @@
+//! "Fiche de service" documents rendered from a department's own template.
+
+use crate::hetd::{compute_hetd, credited_teachers, HetdConfig, HetdTypeLine};
+use crate::normalizer::{is_unknown_teacher, NormalizedEvent};
+use crate::session_type::SessionType;
+use crate::template::{NumberFormat, OutputFormat, Template, TemplateError, Value};
+use crate::template_map;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct FicheOptions {
+    pub hetd: HetdConfig,
+    /// Only render this teacher (case-insensitive).
+    pub teacher: Option<String>,
+    pub format: OutputFormat,
+    pub number: NumberFormat,
+    pub fields: BTreeMap<String, String>,
+}
+
+fn type_lines(lines: &[HetdTypeLine]) -> (Value, Value) {
+    let list = lines
+        .iter()
+        .map(|line| {
+            template_map! {
+                "type" => line.session_type.label(),
+                "sessions" => line.sessions,
+                "minutes" => line.minutes,
+                "hours" => line.hours,
+                "hetd" => line.hetd,
+            }
+        })
+        .collect::<Vec<_>>();
+    // Every type is present so templates can address a fixed column.
+    let by_type = SessionType::ALL
+        .iter()
+        .map(|&session_type| {
+            let line = lines.iter().find(|line| line.session_type == session_type);
+            (
+                session_type.label().to_lowercase(),
+                template_map! {
+                    "sessions" => line.map_or(0, |line| line.sessions),
+                    "minutes" => line.map_or(0, |line| line.minutes),
+                    "hetd" => line.map_or(0.0, |line| line.hetd),
+                },
+            )
+        })
+        .collect();
+    (Value::List(list), Value::Map(by_type))
+}
+
+/// Template context for the given events: `fields.*` from the options,
+/// `totals`, and `teachers[]` and `subjects[]` with their minutes, HETD,
+/// `types[]` and `by_type`. Print minutes with the `hours` filter.
+pub fn fiche_context(events: &[NormalizedEvent], options: &FicheOptions) -> Value {
+    let report = compute_hetd(events, &options.hetd);
+
+    let mut promos: BTreeMap<(&str, &str), BTreeSet<&str>> = BTreeMap::new();
+    for event in events {
+        for teacher in credited_teachers(event) {
+            promos
+                .entry((teacher, event.subject.trim()))
+                .or_default()
+                .extend(event.promos.iter().map(String::as_str));
+        }
+    }
+
+    let selected = options
+        .teacher
+        .as_deref()
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(str::to_lowercase);
+
+    let teachers: Vec<Value> = report
+        .teachers
+        .iter()
+        .filter(|teacher| {
+            selected
+                .as_ref()
+                .is_none_or(|name| teacher.teacher.to_lowercase() == *name)
+        })
+        .map(|teacher| {
+            let subjects: Vec<Value> = teacher
+                .subjects
+                .iter()
+                .map(|subject| {
+                    let (types, by_type) = type_lines(&subject.types);
+                    let subject_promos: Vec<&str> = promos
+                        .get(&(teacher.teacher.as_str(), subject.subject.as_str()))
+                        .map(|set| set.iter().copied().collect())
+                        .unwrap_or_default();
+                    template_map! {
+                        "name" => subject.subject.as_str(),
+                        "promos" => subject_promos,
+                        "minutes" => subject.minutes,
+                        "hetd" => subject.hetd,
+                        "types" => types,
+                        "by_type" => by_type,
+                    }
+                })
+                .collect();
+            let (types, by_type) = type_lines(&teacher.types);
+            template_map! {
+                "name" => teacher.teacher.as_str(),
+                "is_unknown" => is_unknown_teacher(&teacher.teacher),
+                "minutes" => teacher.minutes,
+                "hetd" => teacher.hetd,
+                "statutory_hours" => teacher.statutory_hours,
+                "statutory_hetd" => teacher.statutory_hetd,
+                "complementary_hetd" => teacher.complementary_hetd,
+                "missing_hetd" => teacher.missing_hetd,
+                "types" => types,
+                "by_type" => by_type,
+                "subjects" => subjects,
+            }
+        })
+        .collect();
+
+    let fields = options
+        .fields
+        .iter()
+        .map(|(key, value)| (key.clone(), Value::from(value.as_str())))
+        .collect();
+
+    template_map! {
+        "fields" => Value::Map(fields),
+        "totals" => template_map! {
+            "minutes" => report.minutes,
+            "hetd" => report.hetd,
+        },
+        "teachers" => teachers,
+    }
+}
+
+/// Renders a fiche de service with a user-supplied template.
+///
+/// Events are expected to be deduplicated already.
+pub fn render_fiche(
+    events: &[NormalizedEvent],
+    template: &str,
+    options: &FicheOptions,
+) -> Result<String, TemplateError> {
+    let template = Template::parse(template)?;
+    template.render(
+        &fiche_context(events, options),
+        options.format,
+        &options.number,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::normalizer::test_support::event;
+
+    fn events() -> Vec<NormalizedEvent> {
+        vec![
+            event(
+                "CM Algo",
+                "M1 INFO\nDUPONT Jean",
+                "20250106T080000",
+                "20250106T100000",
+            ),
+            event(
+                "TD Algo",
+                "M1 INFO\nDUPONT Jean",
+                "20250106T100000",
+                "20250106T113000",
+            ),
+            event(
+                "TP Réseaux",
+                "L3 INFO\nMARTIN Paul",
+                "20250107T080000",
+                "20250107T110000",
+            ),
+        ]
+    }
+
+    #[test]
+    fn renders_a_markdown_table_per_teacher() {
+        let template = "# {{ fields.department }}\n\
+{% for teacher in teachers %}\n\
+## {{ teacher.name }}\n\
+| Matière | Promos | CM | TD | HETD |\n\
+|---|---|---|---|---|\n\
+{% for subject in teacher.subjects %}\n\
+| {{ subject.name }} | {{ subject.promos | join }} | {{ subject.by_type.cm.minutes | hours }} | {{ subject.by_type.td.minutes | hours }} | {{ subject.hetd | number }} |\n\
+{% endfor %}\n\
+{% endfor %}\n";
+        let options = FicheOptions {
+            teacher: Some("dupont jean".to_string()),
+            format: OutputFormat::Markdown,
+            number: NumberFormat {
+                decimal_separator: ",".to_string(),
+                ..NumberFormat::default()
+            },
+            fields: BTreeMap::from([("department".to_string(), "Informatique".to_string())]),
+            ..FicheOptions::default()
+        };
+
+        let output = render_fiche(&events(), template, &options).unwrap();
+
+        assert_eq!(
+            output,
+            "# Informatique\n\
+## DUPONT Jean\n\
+| Matière | Promos | CM | TD | HETD |\n\
+|---|---|---|---|---|\n\
+| Algo | M1 INFO | 2,00 | 1,50 | 4,50 |\n"
+        );
+    }
+
+    #[test]
+    fn html_output_escapes_values_and_exposes_totals() {
+        let options = FicheOptions::default();
+        let template = "<p>{{ fields.title | default:\"Service <prévisionnel>\" }}</p>\
+{% for teacher in teachers %}<p>{{ teacher.name }}: {{ teacher.hetd | number:1 }}</p>{% endfor %}\
+<p>{{ totals.minutes | hours:1 }} h</p>";
+
+        let output = render_fiche(&events(), template, &options).unwrap();
+
+        assert_eq!(
+            output,
+            "<p>Service &lt;prévisionnel&gt;</p><p>DUPONT Jean: 4.5</p><p>MARTIN Paul: 3.0</p><p>6.5 h</p>"
+        );
+        assert!(render_fiche(&events(), "{% if x %}", &options).is_err());
+    }
+}
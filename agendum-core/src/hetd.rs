@@ -0,0 +1,355 @@
+use crate::accounting::{minutes_to_hours, AccountingPolicy};
+use crate::normalizer::{is_unknown_teacher, NormalizedEvent, UNKNOWN_TEACHER};
+use crate::session_type::SessionType;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Default statutory obligation of an enseignant-chercheur, in HETD.
+pub const STATUTORY_HETD: f64 = 192.0;
+
+/// Weight of one hour of each session type, in "heures équivalent TD".
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(default)]
+pub struct TypeCoefficients {
+    pub cm: f64,
+    pub td: f64,
+    pub tp: f64,
+    pub project: f64,
+    pub reunion: f64,
+    pub exam: f64,
+    pub other: f64,
+}
+
+impl Default for TypeCoefficients {
+    fn default() -> Self {
+        TypeCoefficients {
+            cm: 1.5,
+            td: 1.0,
+            tp: 1.0,
+            project: 0.0,
+            reunion: 0.0,
+            exam: 0.0,
+            other: 0.0,
+        }
+    }
+}
+
+impl TypeCoefficients {
+    pub fn get(&self, session_type: SessionType) -> f64 {
+        match session_type {
+            SessionType::Cm => self.cm,
+            SessionType::Td => self.td,
+            SessionType::Tp => self.tp,
+            SessionType::Project => self.project,
+            SessionType::Reunion => self.reunion,
+            SessionType::Exam => self.exam,
+            SessionType::Other => self.other,
+        }
+    }
+}
+
+/// Coefficients overriding the defaults for sessions starting between
+/// `from` and `until` (both inclusive, open-ended when absent).
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CoefficientPeriod {
+    #[serde(default)]
+    pub from: Option<NaiveDate>,
+    #[serde(default)]
+    pub until: Option<NaiveDate>,
+    pub coefficients: TypeCoefficients,
+}
+
+impl CoefficientPeriod {
+    fn contains(&self, day: NaiveDate) -> bool {
+        self.from.is_none_or(|from| day >= from) && self.until.is_none_or(|until| day <= until)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct HetdConfig {
+    pub coefficients: TypeCoefficients,
+    /// Checked in order; the first period containing the session date wins.
+    pub periods: Vec<CoefficientPeriod>,
+    pub statutory_hours: f64,
+    /// Per-teacher obligations (part-time, décharges), keyed by teacher name.
+    pub statutory_hours_by_teacher: BTreeMap<String, f64>,
+    pub accounting: AccountingPolicy,
+}
+
+impl Default for HetdConfig {
+    fn default() -> Self {
+        HetdConfig {
+            coefficients: TypeCoefficients::default(),
+            periods: Vec::new(),
+            statutory_hours: STATUTORY_HETD,
+            statutory_hours_by_teacher: BTreeMap::new(),
+            accounting: AccountingPolicy::default(),
+        }
+    }
+}
+
+impl HetdConfig {
+    pub fn coefficient(&self, event: &NormalizedEvent, session_type: SessionType) -> f64 {
+        let day = event.local_start().map(|start| start.date());
+        day.and_then(|day| self.periods.iter().find(|period| period.contains(day)))
+            .map(|period| period.coefficients.get(session_type))
+            .unwrap_or_else(|| self.coefficients.get(session_type))
+    }
+
+    pub fn statutory_hours_for(&self, teacher: &str) -> f64 {
+        if is_unknown_teacher(teacher) {
+            return 0.0;
+        }
+        self.statutory_hours_by_teacher
+            .get(teacher)
+            .copied()
+            .unwrap_or(self.statutory_hours)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct HetdTypeLine {
+    pub session_type: SessionType,
+    pub sessions: u32,
+    pub minutes: i64,
+    pub hours: f64,
+    pub hetd: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct HetdSubject {
+    pub subject: String,
+    pub types: Vec<HetdTypeLine>,
+    pub minutes: i64,
+    pub hetd: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct HetdTeacher {
+    pub teacher: String,
+    pub subjects: Vec<HetdSubject>,
+    pub types: Vec<HetdTypeLine>,
+    pub minutes: i64,
+    pub hetd: f64,
+    pub statutory_hours: f64,
+    /// HETD within the statutory obligation.
+    pub statutory_hetd: f64,
+    /// "Heures complémentaires": HETD beyond the obligation.
+    pub complementary_hetd: f64,
+    pub missing_hetd: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct HetdReport {
+    /// Sorted by name, with the unknown-teacher bucket last.
+    pub teachers: Vec<HetdTeacher>,
+    pub minutes: i64,
+    pub hetd: f64,
+}
+
+#[derive(Default, Clone, Copy)]
+struct Acc {
+    sessions: u32,
+    minutes: i64,
+    hetd: f64,
+}
+
+impl Acc {
+    fn add(&mut self, minutes: i64, hetd: f64) {
+        self.sessions += 1;
+        self.minutes += minutes;
+        self.hetd += hetd;
+    }
+}
+
+fn type_lines(per_type: &BTreeMap<SessionType, Acc>) -> Vec<HetdTypeLine> {
+    per_type
+        .iter()
+        .map(|(&session_type, acc)| HetdTypeLine {
+            session_type,
+            sessions: acc.sessions,
+            minutes: acc.minutes,
+            hours: minutes_to_hours(acc.minutes),
+            hetd: acc.hetd,
+        })
+        .collect()
+}
+
+/// Teachers an event is credited to: every named teacher, or the unknown bucket.
+pub(crate) fn credited_teachers(event: &NormalizedEvent) -> Vec<&str> {
+    let known = event.known_teachers();
+    if known.is_empty() {
+        vec![UNKNOWN_TEACHER]
+    } else {
+        known
+    }
+}
+
+/// Weighted service per teacher, subject and type.
+///
+/// Events are expected to be deduplicated already; each named teacher of an
+/// event is credited with the full session.
+pub fn compute_hetd(events: &[NormalizedEvent], config: &HetdConfig) -> HetdReport {
+    let mut per_teacher: BTreeMap<&str, Vec<&NormalizedEvent>> = BTreeMap::new();
+    for event in events {
+        for teacher in credited_teachers(event) {
+            per_teacher.entry(teacher).or_default().push(event);
+        }
+    }
+
+    let mut teachers: Vec<HetdTeacher> = per_teacher
+        .into_iter()
+        .map(|(teacher, events)| {
+            let allocated = config.accounting.allocate(&events);
+            let mut subjects: BTreeMap<&str, BTreeMap<SessionType, Acc>> = BTreeMap::new();
+            let mut types: BTreeMap<SessionType, Acc> = BTreeMap::new();
+
+            for (event, minutes) in events.iter().zip(allocated) {
+                let session_type = SessionType::from_type(&event.type_);
+                let hetd = minutes_to_hours(minutes) * config.coefficient(event, session_type);
+                subjects
+                    .entry(event.subject.trim())
+                    .or_default()
+                    .entry(session_type)
+                    .or_default()
+                    .add(minutes, hetd);
+                types.entry(session_type).or_default().add(minutes, hetd);
+            }
+
+            let minutes = types.values().map(|acc| acc.minutes).sum();
+            let hetd: f64 = types.values().map(|acc| acc.hetd).sum();
+            let statutory_hours = config.statutory_hours_for(teacher);
+
+            HetdTeacher {
+                teacher: teacher.to_string(),
+                subjects: subjects
+                    .iter()
+                    .map(|(subject, per_type)| HetdSubject {
+                        subject: subject.to_string(),
+                        types: type_lines(per_type),
+                        minutes: per_type.values().map(|acc| acc.minutes).sum(),
+                        hetd: per_type.values().map(|acc| acc.hetd).sum(),
+                    })
+                    .collect(),
+                types: type_lines(&types),
+                minutes,
+                hetd,
+                statutory_hours,
+                statutory_hetd: hetd.min(statutory_hours),
+                complementary_hetd: (hetd - statutory_hours).max(0.0),
+                missing_hetd: (statutory_hours - hetd).max(0.0),
+            }
+        })
+        .collect();
+
+    teachers.sort_by_key(|teacher| is_unknown_teacher(&teacher.teacher));
+
+    HetdReport {
+        minutes: teachers.iter().map(|teacher| teacher.minutes).sum(),
+        hetd: teachers.iter().map(|teacher| teacher.hetd).sum(),
+        teachers,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::normalizer::test_support::event;
+
+    #[test]
+    fn weights_types_and_splits_statutory_and_complementary_hours() {
+        let events = vec![
+            event(
+                "CM Algo",
+                "DUPONT Jean",
+                "20250106T080000",
+                "20250106T100000",
+            ),
+            event(
+                "TD Algo",
+                "DUPONT Jean",
+                "20250106T100000",
+                "20250106T120000",
+            ),
+            event(
+                "TP Réseaux",
+                "DUPONT Jean",
+                "20250107T080000",
+                "20250107T110000",
+            ),
+            event(
+                "Réunion pédagogique",
+                "DUPONT Jean",
+                "20250107T120000",
+                "20250107T130000",
+            ),
+            event("CM Algo", "", "20250108T080000", "20250108T100000"),
+        ];
+        let config = HetdConfig {
+            statutory_hours: 8.0,
+            ..HetdConfig::default()
+        };
+
+        let report = compute_hetd(&events, &config);
+
+        assert_eq!(report.teachers.len(), 2);
+        let dupont = &report.teachers[0];
+        assert_eq!(dupont.teacher, "DUPONT Jean");
+        assert_eq!(dupont.minutes, 8 * 60);
+        // 2h CM x1.5 + 2h TD + 3h TP + 1h réunion x0
+        assert_eq!(dupont.hetd, 8.0);
+        assert_eq!(dupont.statutory_hetd, 8.0);
+        assert_eq!(dupont.complementary_hetd, 0.0);
+        assert_eq!(dupont.subjects.len(), 3);
+        assert_eq!(dupont.subjects[0].subject, "Algo");
+        assert_eq!(dupont.subjects[0].hetd, 5.0);
+        assert_eq!(dupont.types[0].session_type, SessionType::Cm);
+        assert_eq!(dupont.types[0].hours, 2.0);
+
+        let unknown = &report.teachers[1];
+        assert_eq!(unknown.teacher, UNKNOWN_TEACHER);
+        assert_eq!(unknown.statutory_hours, 0.0);
+        assert_eq!(unknown.hetd, 3.0);
+        assert_eq!(report.hetd, 11.0);
+    }
+
+    #[test]
+    fn applies_period_coefficients_and_teacher_obligations() {
+        let events = vec![
+            event(
+                "TP Réseaux",
+                "MARTIN Paul",
+                "20240601T080000",
+                "20240601T110000",
+            ),
+            event(
+                "TP Réseaux",
+                "MARTIN Paul",
+                "20250106T080000",
+                "20250106T110000",
+            ),
+        ];
+        let config = HetdConfig {
+            periods: vec![CoefficientPeriod {
+                from: None,
+                until: NaiveDate::from_ymd_opt(2024, 8, 31),
+                coefficients: TypeCoefficients {
+                    tp: 2.0 / 3.0,
+                    ..TypeCoefficients::default()
+                },
+            }],
+            statutory_hours_by_teacher: BTreeMap::from([("MARTIN Paul".to_string(), 4.0)]),
+            ..HetdConfig::default()
+        };
+
+        let report = compute_hetd(&events, &config);
+        let martin = &report.teachers[0];
+
+        assert!((martin.hetd - 5.0).abs() < 1e-9);
+        assert_eq!(martin.statutory_hours, 4.0);
+        assert!((martin.complementary_hetd - 1.0).abs() < 1e-9);
+        assert_eq!(martin.missing_hetd, 0.0);
+    }
+}
@@ -0,0 +1,422 @@
+use crate::identity::{stable_fingerprint, IdentityConfig};
+use crate::normalizer::{NormalizedEvent, DEFAULT_TIMEZONE};
+use crate::query::{Query, QueryError};
+use crate::session_type::SessionType;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+const PARIS_VTIMEZONE: &[&str] = &[
+    "BEGIN:VTIMEZONE",
+    "TZID:Europe/Paris",
+    "BEGIN:DAYLIGHT",
+    "TZOFFSETFROM:+0100",
+    "TZOFFSETTO:+0200",
+    "TZNAME:CEST",
+    "DTSTART:19700329T020000",
+    "RRULE:FREQ=YEARLY;BYMONTH=3;BYDAY=-1SU",
+    "END:DAYLIGHT",
+    "BEGIN:STANDARD",
+    "TZOFFSETFROM:+0200",
+    "TZOFFSETTO:+0100",
+    "TZNAME:CET",
+    "DTSTART:19701025T030000",
+    "RRULE:FREQ=YEARLY;BYMONTH=10;BYDAY=-1SU",
+    "END:STANDARD",
+    "END:VTIMEZONE",
+];
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum UidMode {
+    /// Keep the source UID, falling back to the fingerprint when it is empty.
+    #[default]
+    Original,
+    /// Always derive the UID from the session content, so it survives
+    /// re-exports of sources that regenerate their UIDs.
+    Fingerprint,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct IcsLabels {
+    pub teachers: String,
+    pub groups: String,
+}
+
+impl Default for IcsLabels {
+    fn default() -> Self {
+        IcsLabels {
+            teachers: "Teachers".to_string(),
+            groups: "Groups".to_string(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct IcsExportOptions {
+    /// `X-WR-CALNAME`, shown by most clients as the subscription title.
+    pub calendar_name: Option<String>,
+    pub prod_id: String,
+    /// `METHOD` of the calendar; `None` leaves it out, as CalDAV requires for
+    /// stored calendar objects.
+    pub method: Option<String>,
+    /// Only events matching this query are exported.
+    pub query: Option<String>,
+    pub uid_mode: UidMode,
+    /// Write UTC times instead of Europe/Paris local times with a VTIMEZONE.
+    pub utc: bool,
+    /// Adds a display reminder this many minutes before each session.
+    pub alarm_minutes_before: Option<u32>,
+    /// `DTSTAMP` as RFC 3339; defaults to the export time.
+    pub generated_at: Option<String>,
+    pub labels: IcsLabels,
+}
+
+impl Default for IcsExportOptions {
+    fn default() -> Self {
+        IcsExportOptions {
+            calendar_name: None,
+            prod_id: "-//Agendum//Agendum Core//EN".to_string(),
+            method: Some("PUBLISH".to_string()),
+            query: None,
+            uid_mode: UidMode::Original,
+            utc: false,
+            alarm_minutes_before: None,
+            generated_at: None,
+            labels: IcsLabels::default(),
+        }
+    }
+}
+
+/// `UID` written for `event`.
+pub fn export_uid(event: &NormalizedEvent, mode: UidMode, identity: &IdentityConfig) -> String {
+    match mode {
+        UidMode::Original if !event.raw.uid.trim().is_empty() => event.raw.uid.trim().to_string(),
+        _ => format!("{}@agendum", stable_fingerprint(event, identity)),
+    }
+}
+
+/// Escapes a TEXT value (RFC 5545 §3.3.11).
+pub fn escape_text(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for ch in value.replace("\r\n", "\n").chars() {
+        match ch {
+            '\\' => escaped.push_str("\\\\"),
+            ';' => escaped.push_str("\\;"),
+            ',' => escaped.push_str("\\,"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            other => escaped.push(other),
+        }
+    }
+    escaped
+}
+
+/// Folds a content line at 75 octets without splitting UTF-8 sequences.
+pub fn fold_line(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len() + line.len() / 74 * 3);
+    let mut width = 0;
+    for ch in line.chars() {
+        let size = ch.len_utf8();
+        if width + size > 75 {
+            folded.push_str("\r\n ");
+            // The leading space counts towards the next line.
+            width = 1;
+        }
+        folded.push(ch);
+        width += size;
+    }
+    folded
+}
+
+fn utc_stamp(time: DateTime<Utc>) -> String {
+    time.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+fn summary(event: &NormalizedEvent) -> String {
+    let subject = event.subject.trim();
+    let type_ = event.type_.trim();
+    let raw = event.raw.summary.trim();
+    if subject.is_empty() {
+        return raw.to_string();
+    }
+    if type_.is_empty() {
+        return subject.to_string();
+    }
+    match SessionType::from_type(type_) {
+        // Not session kinds worth a prefix: "AUTRE Conférence" reads wrong, and
+        // the original summary already says "Réunion".
+        SessionType::Other | SessionType::Reunion if !raw.is_empty() => raw.to_string(),
+        SessionType::Other | SessionType::Reunion => subject.to_string(),
+        _ => format!("{type_} {subject}"),
+    }
+}
+
+fn description(event: &NormalizedEvent, labels: &IcsLabels) -> String {
+    let mut lines = Vec::new();
+    let teachers = event.known_teachers();
+    if !teachers.is_empty() {
+        lines.push(format!("{}: {}", labels.teachers, teachers.join(", ")));
+    }
+    if !event.promos.is_empty() {
+        lines.push(format!("{}: {}", labels.groups, event.promos.join(", ")));
+    }
+    let notes = event.cleaned_description.trim();
+    if !notes.is_empty() {
+        if !lines.is_empty() {
+            lines.push(String::new());
+        }
+        lines.push(notes.to_string());
+    }
+    lines.join("\n")
+}
+
+struct Writer {
+    out: String,
+}
+
+impl Writer {
+    fn line(&mut self, line: &str) {
+        self.out.push_str(&fold_line(line));
+        self.out.push_str("\r\n");
+    }
+
+    fn property(&mut self, name: &str, value: &str) {
+        self.line(&format!("{name}:{value}"));
+    }
+}
+
+/// Serializes events to an iCalendar document with CRLF line endings.
+///
+/// Events without a usable start or end are skipped.
+pub fn write_ics(
+    events: &[NormalizedEvent],
+    options: &IcsExportOptions,
+) -> Result<String, QueryError> {
+    let query = options.query.as_deref().map(Query::parse).transpose()?;
+    let stamp = options
+        .generated_at
+        .as_deref()
+        .and_then(|value| DateTime::parse_from_rfc3339(value).ok())
+        .map_or_else(Utc::now, |time| time.with_timezone(&Utc));
+    let identity = IdentityConfig::default();
+
+    let mut writer = Writer { out: String::new() };
+    writer.line("BEGIN:VCALENDAR");
+    writer.property("VERSION", "2.0");
+    writer.property("PRODID", &options.prod_id);
+    writer.property("CALSCALE", "GREGORIAN");
+    if let Some(method) = &options.method {
+        writer.property("METHOD", method);
+    }
+    if let Some(name) = &options.calendar_name {
+        writer.property("X-WR-CALNAME", &escape_text(name));
+    }
+    if !options.utc {
+        writer.property("X-WR-TIMEZONE", DEFAULT_TIMEZONE);
+        for line in PARIS_VTIMEZONE {
+            writer.line(line);
+        }
+    }
+
+    for event in events {
+        if query.as_ref().is_some_and(|query| !query.matches(event)) {
+            continue;
+        }
+        let in_utc = options.utc || event.timezone != DEFAULT_TIMEZONE;
+        let times = if in_utc {
+            let utc = |ms: Option<i64>| ms.and_then(DateTime::<Utc>::from_timestamp_millis);
+            match (utc(event.start_utc_ms), utc(event.end_utc_ms)) {
+                (Some(start), Some(end)) => (
+                    format!(":{}", utc_stamp(start)),
+                    format!(":{}", utc_stamp(end)),
+                ),
+                _ => continue,
+            }
+        } else {
+            match (event.local_start(), event.local_end()) {
+                (Some(start), Some(end)) => (
+                    format!(";TZID={DEFAULT_TIMEZONE}:{}", start.format("%Y%m%dT%H%M%S")),
+                    format!(";TZID={DEFAULT_TIMEZONE}:{}", end.format("%Y%m%dT%H%M%S")),
+                ),
+                _ => continue,
+            }
+        };
+
+        let uid = export_uid(event, options.uid_mode, &identity);
+        let title = summary(event);
+
+        writer.line("BEGIN:VEVENT");
+        writer.property("UID", &escape_text(&uid));
+        writer.property("DTSTAMP", &utc_stamp(stamp));
+        writer.line(&format!("DTSTART{}", times.0));
+        writer.line(&format!("DTEND{}", times.1));
+        writer.property("SUMMARY", &escape_text(&title));
+        let details = description(event, &options.labels);
+        if !details.is_empty() {
+            writer.property("DESCRIPTION", &escape_text(&details));
+        }
+        if !event.raw.location.trim().is_empty() {
+            writer.property("LOCATION", &escape_text(event.raw.location.trim()));
+        }
+        let session_type = SessionType::from_type(&event.type_);
+        if session_type != SessionType::Other {
+            writer.property("CATEGORIES", session_type.label());
+        }
+        if let Some(minutes) = options.alarm_minutes_before {
+            writer.line("BEGIN:VALARM");
+            writer.property("ACTION", "DISPLAY");
+            writer.property("TRIGGER", &format!("-PT{minutes}M"));
+            writer.property("DESCRIPTION", &escape_text(&title));
+            writer.line("END:VALARM");
+        }
+        writer.line("END:VEVENT");
+    }
+
+    writer.line("END:VCALENDAR");
+    Ok(writer.out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::normalizer::normalize;
+    use crate::normalizer::test_support::event_with_uid;
+    use crate::parser::parse_ics_content;
+
+    fn options() -> IcsExportOptions {
+        IcsExportOptions {
+            generated_at: Some("2025-01-01T00:00:00Z".to_string()),
+            ..IcsExportOptions::default()
+        }
+    }
+
+    #[test]
+    fn escapes_and_folds_text() {
+        assert_eq!(escape_text("a;b,c\\d\r\ne"), "a\\;b\\,c\\\\d\\ne");
+
+        let folded = fold_line(&format!("DESCRIPTION:{}", "é".repeat(60)));
+        let lines: Vec<&str> = folded.split("\r\n").collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines.iter().all(|line| line.len() <= 75));
+        assert!(lines[1].starts_with(' '));
+    }
+
+    #[test]
+    fn writes_local_times_with_vtimezone_and_round_trips() {
+        let events = vec![event_with_uid(
+            "abc-1",
+            "TD Algo",
+            "DUPONT Jean\nM1 Groupe A",
+            "B12, bâtiment C",
+            "20250106T080000",
+            "20250106T100000",
+        )];
+        let ics = write_ics(
+            &events,
+            &IcsExportOptions {
+                calendar_name: Some("Mes TD".to_string()),
+                alarm_minutes_before: Some(15),
+                ..options()
+            },
+        )
+        .unwrap();
+
+        assert!(ics.contains("BEGIN:VTIMEZONE\r\nTZID:Europe/Paris\r\n"));
+        assert!(ics.contains("DTSTART;TZID=Europe/Paris:20250106T080000\r\n"));
+        assert!(ics.contains("DTSTAMP:20250101T000000Z\r\n"));
+        assert!(ics.contains("LOCATION:B12\\, bâtiment C\r\n"));
+        assert!(ics.contains("CATEGORIES:TD\r\n"));
+        assert!(ics.contains("TRIGGER:-PT15M\r\n"));
+        assert!(ics.ends_with("END:VCALENDAR\r\n"));
+
+        let reparsed = normalize(parse_ics_content(&ics));
+        assert_eq!(reparsed.len(), 1);
+        assert_eq!(reparsed[0].raw.uid, "abc-1");
+        assert_eq!(reparsed[0].subject, events[0].subject);
+        assert_eq!(reparsed[0].type_, events[0].type_);
+        assert_eq!(reparsed[0].start_utc_ms, events[0].start_utc_ms);
+        assert_eq!(reparsed[0].raw.location, "B12, bâtiment C");
+    }
+
+    #[test]
+    fn prefixes_only_session_kinds_in_summaries() {
+        let events = vec![
+            event_with_uid("a", "TD Algo", "", "", "20250106T080000", "20250106T100000"),
+            event_with_uid(
+                "b",
+                "Réunion pédagogique Responsables formation",
+                "",
+                "",
+                "20250106T120000",
+                "20250106T130000",
+            ),
+            event_with_uid(
+                "c",
+                "Conférence",
+                "",
+                "",
+                "20250107T080000",
+                "20250107T100000",
+            ),
+        ];
+        let ics = write_ics(&events, &options()).unwrap();
+        let summaries: Vec<&str> = ics
+            .lines()
+            .filter_map(|line| line.strip_prefix("SUMMARY:"))
+            .collect();
+        assert_eq!(
+            summaries,
+            vec![
+                "TD Algo",
+                "Réunion pédagogique Responsables formation",
+                "Conférence"
+            ]
+        );
+    }
+
+    #[test]
+    fn filters_with_query_and_derives_stable_uids() {
+        let events = vec![
+            event_with_uid(
+                "",
+                "TD Algo",
+                "DUPONT Jean",
+                "",
+                "20250106T080000",
+                "20250106T100000",
+            ),
+            event_with_uid(
+                "x",
+                "CM Algo",
+                "DUPONT Jean",
+                "",
+                "20250107T080000",
+                "20250107T100000",
+            ),
+        ];
+        let options = IcsExportOptions {
+            query: Some("type:TD".to_string()),
+            utc: true,
+            ..options()
+        };
+
+        let ics = write_ics(&events, &options).unwrap();
+        assert_eq!(ics.matches("BEGIN:VEVENT").count(), 1);
+        assert!(!ics.contains("VTIMEZONE"));
+        assert!(ics.contains("DTSTART:20250106T070000Z\r\n"));
+        let uid = format!(
+            "UID:{}@agendum\r\n",
+            stable_fingerprint(&events[0], &IdentityConfig::default())
+        );
+        assert!(ics.contains(&uid));
+        assert_eq!(write_ics(&events, &options).unwrap(), ics);
+
+        let invalid = IcsExportOptions {
+            query: Some("teacher:".to_string()),
+            ..IcsExportOptions::default()
+        };
+        assert!(write_ics(&events, &invalid).is_err());
+    }
+}
@@ -0,0 +1,522 @@
+use crate::normalizer::NormalizedEvent;
+use crate::search::fold;
+use crate::session_type::SessionType;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct IdentityConfig {
+    /// Start and end are rounded to this grid before fingerprinting, so a
+    /// session shifted by a few minutes keeps its fingerprint.
+    pub fingerprint_slot_minutes: u32,
+    /// Largest start or end shift still considered the same session.
+    pub time_tolerance_minutes: u32,
+    /// Similarity matches scoring below this are discarded.
+    pub min_confidence: f64,
+    /// Pair events sharing a UID before anything else. Disable for exports
+    /// whose UIDs are reused across unrelated sessions.
+    pub trust_uids: bool,
+}
+
+impl Default for IdentityConfig {
+    fn default() -> Self {
+        IdentityConfig {
+            fingerprint_slot_minutes: 5,
+            time_tolerance_minutes: 60,
+            min_confidence: 0.6,
+            trust_uids: true,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchMethod {
+    Uid,
+    /// UIDs differ but the stable fingerprints agree.
+    Fingerprint,
+    /// Best scoring candidate within the time tolerance.
+    Similarity,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum IdentityField {
+    Subject,
+    Type,
+    Group,
+    Time,
+    Teachers,
+    Room,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct EventMatch {
+    pub old_index: usize,
+    pub new_index: usize,
+    pub method: MatchMethod,
+    /// 1 for UID and fingerprint matches, below 1 for similarity matches.
+    pub confidence: f64,
+    /// Identity fields on which the two events disagree.
+    pub differing: Vec<IdentityField>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct MatchResult {
+    /// Sorted by new index.
+    pub matches: Vec<EventMatch>,
+    pub unmatched_old: Vec<usize>,
+    pub unmatched_new: Vec<usize>,
+}
+
+/// Normalized identity fields of a session.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IdentityKeys {
+    pub subject: String,
+    pub session_type: String,
+    pub group: String,
+    pub start_ms: Option<i64>,
+    pub end_ms: Option<i64>,
+    pub teachers: BTreeSet<String>,
+    pub room: String,
+}
+
+fn fold_words(text: &str) -> String {
+    fold(text).split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Rooms are compared on letters and digits only: "Salle B-12" and "salle b12" agree.
+fn fold_room(text: &str) -> String {
+    fold(text)
+        .chars()
+        .filter(|ch| ch.is_alphanumeric())
+        .collect()
+}
+
+impl IdentityKeys {
+    pub fn new(event: &NormalizedEvent) -> Self {
+        let session_type = SessionType::from_type(&event.type_);
+        let group: BTreeSet<String> = event
+            .promos
+            .iter()
+            .map(|promo| fold_words(promo))
+            .filter(|promo| !promo.is_empty())
+            .collect();
+        IdentityKeys {
+            subject: fold_words(if event.subject.trim().is_empty() {
+                &event.raw.summary
+            } else {
+                &event.subject
+            }),
+            session_type: if session_type == SessionType::Other {
+                fold_words(&event.type_)
+            } else {
+                session_type.label().to_string()
+            },
+            group: group.into_iter().collect::<Vec<_>>().join("|"),
+            start_ms: event.start_utc_ms,
+            end_ms: event.end_utc_ms,
+            teachers: event.known_teachers().into_iter().map(fold_words).collect(),
+            room: fold_room(&event.raw.location),
+        }
+    }
+
+    fn differing(&self, other: &IdentityKeys) -> Vec<IdentityField> {
+        let mut fields = Vec::new();
+        if self.subject != other.subject {
+            fields.push(IdentityField::Subject);
+        }
+        if self.session_type != other.session_type {
+            fields.push(IdentityField::Type);
+        }
+        if self.group != other.group {
+            fields.push(IdentityField::Group);
+        }
+        if self.start_ms != other.start_ms || self.end_ms != other.end_ms {
+            fields.push(IdentityField::Time);
+        }
+        if self.teachers != other.teachers {
+            fields.push(IdentityField::Teachers);
+        }
+        if self.room != other.room {
+            fields.push(IdentityField::Room);
+        }
+        fields
+    }
+}
+
+fn round_to_slot(ms: i64, slot_minutes: u32) -> i64 {
+    let slot = i64::from(slot_minutes.max(1)) * 60_000;
+    (ms + slot / 2).div_euclid(slot) * slot
+}
+
+/// 64-bit FNV-1a: stable across builds and platforms, unlike `DefaultHasher`.
+pub(crate) fn fnv1a(text: &str) -> u64 {
+    text.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Identifier of a session derived from its normalized content, e.g.
+/// `"ev-3f9a0c1d2b4e5f60"`. Survives UID regeneration, accent and case
+/// changes, and time shifts smaller than half a fingerprint slot.
+pub fn stable_fingerprint(event: &NormalizedEvent, config: &IdentityConfig) -> String {
+    let keys = IdentityKeys::new(event);
+    let time = |ms: Option<i64>, iso: &str| {
+        ms.map_or_else(
+            || iso.to_string(),
+            |ms| round_to_slot(ms, config.fingerprint_slot_minutes).to_string(),
+        )
+    };
+    let material = format!(
+        "{}\u{1f}{}\u{1f}{}\u{1f}{}\u{1f}{}\u{1f}{}\u{1f}{}",
+        keys.subject,
+        keys.session_type,
+        keys.group,
+        time(keys.start_ms, &event.start_iso),
+        time(keys.end_ms, &event.end_iso),
+        keys.teachers.iter().cloned().collect::<Vec<_>>().join(","),
+        keys.room,
+    );
+    format!("ev-{:016x}", fnv1a(&material))
+}
+
+fn jaccard(a: &BTreeSet<String>, b: &BTreeSet<String>) -> f64 {
+    if a.is_empty() && b.is_empty() {
+        return 1.0;
+    }
+    a.intersection(b).count() as f64 / a.union(b).count() as f64
+}
+
+/// Similarity of two sessions in `[0, 0.95]`, or `None` when they cannot be
+/// the same session: start or end moved beyond the tolerance, or subject and
+/// type both changed.
+fn similarity(old: &IdentityKeys, new: &IdentityKeys, config: &IdentityConfig) -> Option<f64> {
+    let tolerance = i64::from(config.time_tolerance_minutes) * 60_000;
+    let shift = match (old.start_ms, old.end_ms, new.start_ms, new.end_ms) {
+        (Some(os), Some(oe), Some(ns), Some(ne)) => (os - ns).abs().max((oe - ne).abs()),
+        _ => return None,
+    };
+    if shift > tolerance {
+        return None;
+    }
+    if old.subject != new.subject && old.session_type != new.session_type {
+        return None;
+    }
+
+    let equal = |same: bool| if same { 1.0 } else { 0.0 };
+    let room = if old.room.is_empty() || new.room.is_empty() {
+        0.5
+    } else {
+        equal(old.room == new.room)
+    };
+    let time = if tolerance == 0 {
+        1.0
+    } else {
+        1.0 - shift as f64 / (tolerance as f64 * 2.0)
+    };
+    let score = 0.30 * equal(old.subject == new.subject)
+        + 0.15 * equal(old.session_type == new.session_type)
+        + 0.15 * equal(old.group == new.group)
+        + 0.15 * jaccard(&old.teachers, &new.teachers)
+        + 0.10 * room
+        + 0.15 * time;
+    // Keep similarity strictly below exact identity.
+    Some(score * 0.95)
+}
+
+struct Linker {
+    old_keys: Vec<IdentityKeys>,
+    new_keys: Vec<IdentityKeys>,
+    old_matched: Vec<bool>,
+    new_matched: Vec<bool>,
+    matches: Vec<EventMatch>,
+}
+
+impl Linker {
+    fn link(&mut self, old_index: usize, new_index: usize, method: MatchMethod, confidence: f64) {
+        self.old_matched[old_index] = true;
+        self.new_matched[new_index] = true;
+        self.matches.push(EventMatch {
+            old_index,
+            new_index,
+            method,
+            confidence,
+            differing: self.old_keys[old_index].differing(&self.new_keys[new_index]),
+        });
+    }
+}
+
+/// Links the events of two imports of the same calendar.
+///
+/// Pairs are found by UID (unless disabled), then by stable fingerprint, then
+/// greedily by decreasing similarity among the remaining events.
+pub fn match_events(
+    old: &[NormalizedEvent],
+    new: &[NormalizedEvent],
+    config: &IdentityConfig,
+) -> MatchResult {
+    let mut linker = Linker {
+        old_keys: old.iter().map(IdentityKeys::new).collect(),
+        new_keys: new.iter().map(IdentityKeys::new).collect(),
+        old_matched: vec![false; old.len()],
+        new_matched: vec![false; new.len()],
+        matches: Vec::new(),
+    };
+
+    if config.trust_uids {
+        let mut old_by_uid: HashMap<&str, Vec<usize>> = HashMap::new();
+        for (index, event) in old.iter().enumerate() {
+            let uid = event.raw.uid.trim();
+            if !uid.is_empty() {
+                old_by_uid.entry(uid).or_default().push(index);
+            }
+        }
+        for (new_index, event) in new.iter().enumerate() {
+            let candidates = old_by_uid.get_mut(event.raw.uid.trim());
+            if let Some(old_index) = candidates.filter(|c| !c.is_empty()).map(|c| c.remove(0)) {
+                linker.link(old_index, new_index, MatchMethod::Uid, 1.0);
+            }
+        }
+    }
+
+    let mut old_by_fingerprint: HashMap<String, Vec<usize>> = HashMap::new();
+    for (index, event) in old.iter().enumerate() {
+        if !linker.old_matched[index] {
+            old_by_fingerprint
+                .entry(stable_fingerprint(event, config))
+                .or_default()
+                .push(index);
+        }
+    }
+    for (new_index, event) in new.iter().enumerate() {
+        if linker.new_matched[new_index] {
+            continue;
+        }
+        let candidates = old_by_fingerprint.get_mut(&stable_fingerprint(event, config));
+        if let Some(old_index) = candidates.filter(|c| !c.is_empty()).map(|c| c.remove(0)) {
+            linker.link(old_index, new_index, MatchMethod::Fingerprint, 1.0);
+        }
+    }
+
+    let mut remaining_old: Vec<usize> = (0..old.len())
+        .filter(|&i| !linker.old_matched[i] && linker.old_keys[i].start_ms.is_some())
+        .collect();
+    remaining_old.sort_by_key(|&i| linker.old_keys[i].start_ms);
+    let tolerance = i64::from(config.time_tolerance_minutes) * 60_000;
+    let mut candidates: Vec<(f64, usize, usize)> = Vec::new();
+    for new_index in (0..new.len()).filter(|&i| !linker.new_matched[i]) {
+        let Some(start) = linker.new_keys[new_index].start_ms else {
+            continue;
+        };
+        let from = remaining_old
+            .partition_point(|&i| linker.old_keys[i].start_ms < Some(start - tolerance));
+        for &old_index in &remaining_old[from..] {
+            if linker.old_keys[old_index].start_ms > Some(start + tolerance) {
+                break;
+            }
+            if let Some(score) = similarity(
+                &linker.old_keys[old_index],
+                &linker.new_keys[new_index],
+                config,
+            ) {
+                if score >= config.min_confidence {
+                    candidates.push((score, old_index, new_index));
+                }
+            }
+        }
+    }
+    candidates.sort_by(|a, b| b.0.total_cmp(&a.0).then(a.1.cmp(&b.1)).then(a.2.cmp(&b.2)));
+    for (score, old_index, new_index) in candidates {
+        if linker.old_matched[old_index] || linker.new_matched[new_index] {
+            continue;
+        }
+        linker.link(
+            old_index,
+            new_index,
+            MatchMethod::Similarity,
+            (score * 1000.0).round() / 1000.0,
+        );
+    }
+
+    linker.matches.sort_by_key(|m| m.new_index);
+    MatchResult {
+        unmatched_old: (0..old.len()).filter(|&i| !linker.old_matched[i]).collect(),
+        unmatched_new: (0..new.len()).filter(|&i| !linker.new_matched[i]).collect(),
+        matches: linker.matches,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::normalizer::test_support::event_with_uid;
+
+    #[test]
+    fn fingerprint_ignores_uid_case_accents_and_small_shifts() {
+        let config = IdentityConfig::default();
+        let a = event_with_uid(
+            "1",
+            "TD Réseaux",
+            "DUPONT Jean\nM1 Groupe A",
+            "Salle B-12",
+            "20250106T080000",
+            "20250106T100000",
+        );
+        let b = event_with_uid(
+            "2",
+            "TD reseaux",
+            "DUPONT Jean\nM1 Groupe A",
+            "salle b12",
+            "20250106T080100",
+            "20250106T100000",
+        );
+        let c = event_with_uid(
+            "3",
+            "TD Réseaux",
+            "DUPONT Jean\nM1 Groupe B",
+            "Salle B-12",
+            "20250106T080000",
+            "20250106T100000",
+        );
+
+        let fingerprint = stable_fingerprint(&a, &config);
+        assert!(fingerprint.starts_with("ev-") && fingerprint.len() == 19);
+        assert_eq!(fingerprint, stable_fingerprint(&b, &config));
+        assert_ne!(fingerprint, stable_fingerprint(&c, &config));
+    }
+
+    #[test]
+    fn links_regenerated_events_and_reports_confidence() {
+        let old = vec![
+            event_with_uid(
+                "a1",
+                "TD Algo",
+                "DUPONT Jean\nM1 Groupe A",
+                "B12",
+                "20250106T080000",
+                "20250106T100000",
+            ),
+            event_with_uid(
+                "a2",
+                "TD Algo",
+                "DUPONT Jean\nM1 Groupe A",
+                "B12",
+                "20250113T080000",
+                "20250113T100000",
+            ),
+            event_with_uid(
+                "a3",
+                "CM Algo",
+                "DUPONT Jean\nM1",
+                "Amphi A",
+                "20250114T080000",
+                "20250114T100000",
+            ),
+            event_with_uid(
+                "a4",
+                "TP Web",
+                "MARTIN Paul\nM1 Groupe A",
+                "C01",
+                "20250115T080000",
+                "20250115T100000",
+            ),
+        ];
+        let new = vec![
+            // Unchanged but re-exported with new UIDs
+            event_with_uid(
+                "b1",
+                "TD Algo",
+                "DUPONT Jean\nM1 Groupe A",
+                "B12",
+                "20250106T080000",
+                "20250106T100000",
+            ),
+            // Moved by half an hour and to another room
+            event_with_uid(
+                "b2",
+                "TD Algo",
+                "DUPONT Jean\nM1 Groupe A",
+                "B14",
+                "20250113T083000",
+                "20250113T103000",
+            ),
+            // Kept its UID
+            event_with_uid(
+                "a3",
+                "CM Algo",
+                "DUPONT Jean\nM1",
+                "Amphi B",
+                "20250114T080000",
+                "20250114T100000",
+            ),
+            // Unrelated new session in the old TP slot
+            event_with_uid(
+                "b4",
+                "Réunion pédagogique",
+                "",
+                "Salle du conseil",
+                "20250115T080000",
+                "20250115T100000",
+            ),
+        ];
+
+        let result = match_events(&old, &new, &IdentityConfig::default());
+
+        let methods: Vec<(usize, usize, MatchMethod)> = result
+            .matches
+            .iter()
+            .map(|m| (m.old_index, m.new_index, m.method))
+            .collect();
+        assert_eq!(
+            methods,
+            vec![
+                (0, 0, MatchMethod::Fingerprint),
+                (1, 1, MatchMethod::Similarity),
+                (2, 2, MatchMethod::Uid),
+            ]
+        );
+        let moved = &result.matches[1];
+        assert!(moved.confidence > 0.6 && moved.confidence < 1.0);
+        assert_eq!(
+            moved.differing,
+            vec![IdentityField::Time, IdentityField::Room]
+        );
+        assert_eq!(result.matches[2].differing, vec![IdentityField::Room]);
+        assert_eq!(result.unmatched_old, vec![3]);
+        assert_eq!(result.unmatched_new, vec![3]);
+    }
+
+    #[test]
+    fn untrusted_uids_are_ignored() {
+        let old = vec![event_with_uid(
+            "1",
+            "TD Algo",
+            "DUPONT Jean",
+            "",
+            "20250106T080000",
+            "20250106T100000",
+        )];
+        let new = vec![event_with_uid(
+            "1",
+            "TP Web",
+            "MARTIN Paul",
+            "",
+            "20250110T080000",
+            "20250110T100000",
+        )];
+        let config = IdentityConfig {
+            trust_uids: false,
+            ..IdentityConfig::default()
+        };
+
+        let result = match_events(&old, &new, &config);
+
+        assert!(result.matches.is_empty());
+        assert_eq!(
+            match_events(&old, &new, &IdentityConfig::default()).matches[0].method,
+            MatchMethod::Uid
+        );
+    }
+}
@@ -0,0 +1,552 @@
+//! jCal (RFC 7265), sharing its component tree with [`crate::xcal`].
+
+use crate::ics_writer::{write_ics, IcsExportOptions};
+use crate::normalizer::NormalizedEvent;
+use crate::parser::{ParseDiagnostics, ParseOutput, RawEvent};
+use crate::query::QueryError;
+use serde_json::{json, Map, Value as Json};
+
+/// Kept in diagnostics, like the ICS parser.
+const MAX_ERROR_MESSAGES: usize = 5;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Value {
+    Text(String),
+    /// `RRULE` parts in order, e.g. `("freq", "YEARLY")`.
+    Recur(Vec<(String, String)>),
+}
+
+impl Value {
+    fn as_text(&self) -> String {
+        match self {
+            Value::Text(text) => text.clone(),
+            Value::Recur(parts) => parts
+                .iter()
+                .map(|(key, value)| format!("{}={value}", key.to_uppercase()))
+                .collect::<Vec<_>>()
+                .join(";"),
+        }
+    }
+}
+
+/// A property with lowercase name, parameter names and value type; values are
+/// in their jCal/xCal form (`2025-01-06T08:00:00`, `+01:00`, unescaped text).
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Property {
+    pub name: String,
+    pub params: Vec<(String, String)>,
+    pub value_type: String,
+    pub values: Vec<Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub(crate) struct Component {
+    pub name: String,
+    pub properties: Vec<Property>,
+    pub components: Vec<Component>,
+}
+
+impl Component {
+    fn text(&self, name: &str) -> String {
+        self.properties
+            .iter()
+            .find(|property| property.name == name)
+            .and_then(|property| property.values.first())
+            .map(Value::as_text)
+            .unwrap_or_default()
+    }
+}
+
+/// Collects parse errors the way the ICS parser does.
+pub(crate) fn record_error(diagnostics: &mut ParseDiagnostics, message: String) {
+    diagnostics.parser_errors += 1;
+    if diagnostics.parser_error_messages.len() < MAX_ERROR_MESSAGES {
+        diagnostics.parser_error_messages.push(message);
+    }
+}
+
+/// `2025-01-06T08:00:00` → `20250106T080000`, as found in ICS files.
+fn basic_format(value: &str) -> String {
+    value
+        .chars()
+        .filter(|ch| *ch != '-' && *ch != ':')
+        .collect()
+}
+
+/// Events of parsed calendars, with the same fields and skipping rules as
+/// the ICS parser.
+pub(crate) fn raw_events(
+    calendars: &[Component],
+    diagnostics: &mut ParseDiagnostics,
+) -> Vec<RawEvent> {
+    let mut events = Vec::new();
+    for calendar in calendars {
+        diagnostics.calendars_parsed += 1;
+        for event in calendar
+            .components
+            .iter()
+            .filter(|component| component.name == "vevent")
+        {
+            if event
+                .properties
+                .iter()
+                .any(|property| property.name == "rrule" || property.name == "rdate")
+            {
+                diagnostics.recurring_events_not_expanded += 1;
+            }
+            let uid = event.text("uid");
+            if uid.is_empty() {
+                diagnostics.skipped_events_without_uid += 1;
+                continue;
+            }
+            events.push(RawEvent {
+                uid,
+                summary: event.text("summary"),
+                description: event.text("description"),
+                location: event.text("location"),
+                start: basic_format(&event.text("dtstart")),
+                end: basic_format(&event.text("dtend")),
+            });
+        }
+    }
+    events
+}
+
+fn unescape_text(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            out.push(ch);
+            continue;
+        }
+        match chars.next() {
+            Some('n' | 'N') => out.push('\n'),
+            Some(other) => out.push(other),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+/// Splits on separators not escaped with a backslash.
+fn split_unescaped(value: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut escaped = false;
+    for (index, ch) in value.char_indices() {
+        match ch {
+            '\\' if !escaped => escaped = true,
+            _ if ch == separator && !escaped => {
+                parts.push(&value[start..index]);
+                start = index + ch.len_utf8();
+            }
+            _ => escaped = false,
+        }
+    }
+    parts.push(&value[start..]);
+    parts
+}
+
+fn date_value(value: &str) -> (String, String) {
+    let digits = |range: std::ops::Range<usize>| value.get(range).unwrap_or_default();
+    if value.len() == 8 {
+        let date = format!("{}-{}-{}", digits(0..4), digits(4..6), digits(6..8));
+        return ("date".to_string(), date);
+    }
+    let time = format!(
+        "{}-{}-{}T{}:{}:{}{}",
+        digits(0..4),
+        digits(4..6),
+        digits(6..8),
+        digits(9..11),
+        digits(11..13),
+        digits(13..15),
+        if value.ends_with('Z') { "Z" } else { "" }
+    );
+    ("date-time".to_string(), time)
+}
+
+/// Type and jCal value of an ICS property value.
+fn typed_value(name: &str, value_param: Option<&str>, value: &str) -> (String, Vec<Value>) {
+    match name {
+        "dtstart" | "dtend" | "dtstamp" | "created" | "last-modified" | "recurrence-id" | "due"
+        | "exdate" | "rdate"
+            if value_param.is_none_or(|kind| kind == "date" || kind == "date-time") =>
+        {
+            let mut kind = String::new();
+            let values = value
+                .split(',')
+                .map(|part| {
+                    let (part_kind, text) = date_value(part);
+                    kind = part_kind;
+                    Value::Text(text)
+                })
+                .collect();
+            (kind, values)
+        }
+        "trigger" if value_param.is_none() => {
+            ("duration".to_string(), vec![Value::Text(value.to_string())])
+        }
+        "rrule" | "exrule" => {
+            let parts = value
+                .split(';')
+                .filter_map(|part| part.split_once('='))
+                .map(|(key, value)| (key.to_lowercase(), value.to_string()))
+                .collect();
+            ("recur".to_string(), vec![Value::Recur(parts)])
+        }
+        "tzoffsetfrom" | "tzoffsetto" => {
+            let offset = match value.len() {
+                5 => format!("{}:{}", &value[..3], &value[3..]),
+                _ => value.to_string(),
+            };
+            ("utc-offset".to_string(), vec![Value::Text(offset)])
+        }
+        "tzurl" | "url" => ("uri".to_string(), vec![Value::Text(value.to_string())]),
+        "categories" | "resources" => (
+            "text".to_string(),
+            split_unescaped(value, ',')
+                .into_iter()
+                .map(|part| Value::Text(unescape_text(part)))
+                .collect(),
+        ),
+        _ => (
+            value_param.unwrap_or("text").to_string(),
+            vec![Value::Text(unescape_text(value))],
+        ),
+    }
+}
+
+/// Splits `NAME;PARAM=VALUE:value` at the first colon outside quotes.
+fn content_line(line: &str) -> Option<(&str, &str)> {
+    let mut quoted = false;
+    for (index, ch) in line.char_indices() {
+        match ch {
+            '"' => quoted = !quoted,
+            ':' if !quoted => return Some((&line[..index], &line[index + 1..])),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Components of an iCalendar document.
+pub(crate) fn ics_tree(ics: &str) -> Vec<Component> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in ics.split('\n') {
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        match (
+            line.strip_prefix(' ').or_else(|| line.strip_prefix('\t')),
+            lines.last_mut(),
+        ) {
+            (Some(continuation), Some(last)) => last.push_str(continuation),
+            _ if line.is_empty() => {}
+            _ => lines.push(line.to_string()),
+        }
+    }
+
+    let mut stack: Vec<Component> = vec![Component::default()];
+    for line in &lines {
+        let Some((head, value)) = content_line(line) else {
+            continue;
+        };
+        let mut head = head.split(';');
+        let name = head.next().unwrap_or_default().to_lowercase();
+        match name.as_str() {
+            "begin" => {
+                stack.push(Component {
+                    name: value.to_lowercase(),
+                    ..Component::default()
+                });
+                continue;
+            }
+            "end" if stack.len() > 1 => {
+                let done = stack.pop().unwrap_or_default();
+                if let Some(parent) = stack.last_mut() {
+                    parent.components.push(done);
+                }
+                continue;
+            }
+            _ => {}
+        }
+        let mut params: Vec<(String, String)> = head
+            .filter_map(|param| param.split_once('='))
+            .map(|(key, value)| (key.to_lowercase(), value.trim_matches('"').to_string()))
+            .collect();
+        let value_param = params
+            .iter()
+            .position(|(key, _)| key == "value")
+            .map(|index| params.remove(index).1.to_lowercase());
+        let (value_type, values) = typed_value(&name, value_param.as_deref(), value);
+        if let Some(component) = stack.last_mut() {
+            component.properties.push(Property {
+                name,
+                params,
+                value_type,
+                values,
+            });
+        }
+    }
+    stack.into_iter().next().unwrap_or_default().components
+}
+
+fn recur_json(parts: &[(String, String)]) -> Json {
+    let mut object = Map::new();
+    for (key, value) in parts {
+        let value = match value.parse::<i64>() {
+            Ok(number) => json!(number),
+            Err(_) => json!(value),
+        };
+        object.insert(key.clone(), value);
+    }
+    Json::Object(object)
+}
+
+fn component_json(component: &Component) -> Json {
+    let properties: Vec<Json> = component
+        .properties
+        .iter()
+        .map(|property| {
+            let params: Map<String, Json> = property
+                .params
+                .iter()
+                .map(|(key, value)| (key.clone(), json!(value)))
+                .collect();
+            let mut item = vec![
+                json!(property.name),
+                Json::Object(params),
+                json!(property.value_type),
+            ];
+            item.extend(property.values.iter().map(|value| match value {
+                Value::Text(text) => json!(text),
+                Value::Recur(parts) => recur_json(parts),
+            }));
+            Json::Array(item)
+        })
+        .collect();
+    let components: Vec<Json> = component.components.iter().map(component_json).collect();
+    json!([component.name, properties, components])
+}
+
+/// Serializes events to a jCal document, with the same content as
+/// [`write_ics`].
+pub fn write_jcal(
+    events: &[NormalizedEvent],
+    options: &IcsExportOptions,
+) -> Result<String, QueryError> {
+    let ics = write_ics(events, options)?;
+    let calendar = ics_tree(&ics).into_iter().next().unwrap_or_default();
+    Ok(component_json(&calendar).to_string())
+}
+
+fn json_value(value: &Json) -> Value {
+    match value {
+        Json::String(text) => Value::Text(text.clone()),
+        Json::Object(parts) => Value::Recur(
+            parts
+                .iter()
+                .map(|(key, value)| {
+                    let text = match value {
+                        Json::String(text) => text.clone(),
+                        Json::Array(items) => items
+                            .iter()
+                            .map(|item| {
+                                item.as_str()
+                                    .map_or_else(|| item.to_string(), str::to_string)
+                            })
+                            .collect::<Vec<_>>()
+                            .join(","),
+                        other => other.to_string(),
+                    };
+                    (key.clone(), text)
+                })
+                .collect(),
+        ),
+        Json::Array(items) => Value::Text(
+            items
+                .iter()
+                .map(|item| {
+                    item.as_str()
+                        .map_or_else(|| item.to_string(), str::to_string)
+                })
+                .collect::<Vec<_>>()
+                .join(";"),
+        ),
+        other => Value::Text(other.to_string()),
+    }
+}
+
+fn json_component(value: &Json) -> Result<Component, String> {
+    let shape = || "expected [name, properties, components]".to_string();
+    let items = value
+        .as_array()
+        .filter(|items| items.len() == 3)
+        .ok_or_else(shape)?;
+    let name = items[0].as_str().ok_or_else(shape)?.to_lowercase();
+    let mut component = Component {
+        name: name.clone(),
+        ..Component::default()
+    };
+    for property in items[1].as_array().ok_or_else(shape)? {
+        let parts = property
+            .as_array()
+            .filter(|parts| parts.len() >= 4)
+            .ok_or_else(|| {
+                format!("Invalid property in {name}: expected [name, parameters, type, value]")
+            })?;
+        let params = parts[1]
+            .as_object()
+            .map(|params| {
+                params
+                    .iter()
+                    .map(|(key, value)| {
+                        let value = value
+                            .as_str()
+                            .map_or_else(|| value.to_string(), str::to_string);
+                        (key.to_lowercase(), value)
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        component.properties.push(Property {
+            name: parts[0].as_str().unwrap_or_default().to_lowercase(),
+            params,
+            value_type: parts[2].as_str().unwrap_or("unknown").to_lowercase(),
+            values: parts[3..].iter().map(json_value).collect(),
+        });
+    }
+    for child in items[2].as_array().ok_or_else(shape)? {
+        component.components.push(json_component(child)?);
+    }
+    Ok(component)
+}
+
+/// Parses a jCal document: one `["vcalendar", …]` component or an array of
+/// them.
+pub fn parse_jcal_with_diagnostics(content: &str) -> ParseOutput {
+    let mut diagnostics = ParseDiagnostics::default();
+    let document: Json = match serde_json::from_str(content) {
+        Ok(document) => document,
+        Err(e) => {
+            record_error(&mut diagnostics, format!("Invalid jCal: {e}"));
+            return ParseOutput {
+                events: Vec::new(),
+                diagnostics,
+            };
+        }
+    };
+    let items = match &document {
+        Json::Array(items) if items.first().is_some_and(Json::is_string) => vec![&document],
+        Json::Array(items) => items.iter().collect(),
+        _ => vec![&document],
+    };
+    let mut calendars = Vec::new();
+    for item in items {
+        match json_component(item) {
+            Ok(component) if component.name == "vcalendar" => calendars.push(component),
+            Ok(component) => record_error(
+                &mut diagnostics,
+                format!("Invalid jCal: expected vcalendar, found {}", component.name),
+            ),
+            Err(message) => record_error(&mut diagnostics, format!("Invalid jCal: {message}")),
+        }
+    }
+    ParseOutput {
+        events: raw_events(&calendars, &mut diagnostics),
+        diagnostics,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::normalizer::normalize;
+    use crate::parser::parse_ics_content_with_diagnostics;
+
+    const ICS: &str = "BEGIN:VCALENDAR\r\nVERSION:2.0\r\n\
+BEGIN:VEVENT\r\nUID:evt-1\r\nSUMMARY:CM Algo\r\nDESCRIPTION:L1\\nGroupe A\r\n\
+LOCATION:Salle\\, B12\r\nDTSTART:20250106T080000\r\nDTEND:20250106T100000\r\nEND:VEVENT\r\n\
+BEGIN:VEVENT\r\nSUMMARY:No UID\r\nDTSTART:20250106T110000\r\nDTEND:20250106T120000\r\nEND:VEVENT\r\n\
+END:VCALENDAR\r\n";
+
+    #[test]
+    fn round_trips_the_ics_writer_output() {
+        let events = normalize(parse_ics_content_with_diagnostics(ICS).events);
+        let options = IcsExportOptions {
+            generated_at: Some("2025-01-01T00:00:00Z".to_string()),
+            alarm_minutes_before: Some(15),
+            ..IcsExportOptions::default()
+        };
+        let jcal = write_jcal(&events, &options).unwrap();
+        let document: Json = serde_json::from_str(&jcal).unwrap();
+        assert_eq!(document[0], "vcalendar");
+        let vevent = document[2]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|component| component[0] == "vevent")
+            .unwrap();
+        let dtstart = vevent[1]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|property| property[0] == "dtstart")
+            .unwrap();
+        assert_eq!(
+            dtstart,
+            &json!(["dtstart", {"tzid": "Europe/Paris"}, "date-time", "2025-01-06T08:00:00"])
+        );
+        let vtimezone = &document[2][0];
+        assert_eq!(vtimezone[0], "vtimezone");
+        assert_eq!(
+            vtimezone[2][0][1][4],
+            json!(["rrule", {}, "recur", {"freq": "YEARLY", "bymonth": 3, "byday": "-1SU"}])
+        );
+        assert_eq!(vevent[2][0][0], "valarm");
+
+        let parsed = parse_jcal_with_diagnostics(&jcal);
+        assert_eq!(parsed.diagnostics.parser_errors, 0);
+        assert_eq!(parsed.diagnostics.calendars_parsed, 1);
+        let event = &parsed.events[0];
+        assert_eq!(event.uid, "evt-1");
+        assert_eq!(event.start, "20250106T080000");
+        assert_eq!(event.location, "Salle, B12");
+        assert_eq!(
+            normalize(parsed.events)[0].start_utc_ms,
+            events[0].start_utc_ms
+        );
+    }
+
+    #[test]
+    fn reports_the_same_diagnostics_as_the_ics_parser() {
+        let jcal = r#"["vcalendar", [["version", {}, "text", "2.0"]], [
+            ["vevent", [
+                ["uid", {}, "text", "evt-1"],
+                ["summary", {}, "text", "CM Algo"],
+                ["description", {}, "text", "L1\nGroupe A"],
+                ["location", {}, "text", "Salle, B12"],
+                ["dtstart", {}, "date-time", "2025-01-06T08:00:00"],
+                ["dtend", {}, "date-time", "2025-01-06T10:00:00"]
+            ], []],
+            ["vevent", [["summary", {}, "text", "No UID"]], []]
+        ]]"#;
+        let from_jcal = parse_jcal_with_diagnostics(jcal);
+        let from_ics = parse_ics_content_with_diagnostics(ICS);
+        assert_eq!(
+            serde_json::to_value(&from_jcal).unwrap(),
+            serde_json::to_value(&from_ics).unwrap()
+        );
+
+        let broken = parse_jcal_with_diagnostics(r#"[["vevent", [], []], ["vcalendar", {}]]"#);
+        assert_eq!(broken.diagnostics.parser_errors, 2);
+        assert_eq!(
+            broken.diagnostics.parser_error_messages[0],
+            "Invalid jCal: expected vcalendar, found vevent"
+        );
+        assert_eq!(
+            parse_jcal_with_diagnostics("{").diagnostics.parser_errors,
+            1
+        );
+    }
+}
@@ -1,9 +1,51 @@
 use serde::Serialize;
 use wasm_bindgen::prelude::*;
-mod normalizer;
-mod parser;
+pub mod accounting;
+pub mod availability;
+pub mod caldav;
+pub mod conflicts;
+pub mod csv_import;
+pub mod dedup;
+pub mod diff;
+pub mod feeds;
+pub mod fiche;
+pub mod hetd;
+pub mod ics_writer;
+pub mod identity;
+pub mod jcal;
+pub mod normalizer;
+pub mod ordinals;
+pub mod parser;
+pub mod query;
+pub mod search;
+pub mod service;
+pub mod service_export;
+pub mod session_type;
+pub mod sync;
+pub mod template;
+pub mod timeseries;
+pub mod wellbeing;
+pub mod xcal;
+use accounting::AccountingPolicy;
+use availability::{find_free_slots, SlotRequest};
+use conflicts::{detect_conflicts, ConflictConfig};
+use dedup::{deduplicate, DedupConfig, SourcedEvent};
+use diff::diff_snapshots_with;
+use feeds::{render_feed, SavedFeed};
+use fiche::{render_fiche, FicheOptions};
+use hetd::{compute_hetd, HetdConfig};
+use ics_writer::{write_ics, IcsExportOptions};
+use identity::{match_events, stable_fingerprint, IdentityConfig};
 use normalizer::normalize;
+use ordinals::compute_session_ordinals;
 use parser::{parse_ics_content, parse_ics_content_with_diagnostics, ParseDiagnostics, RawEvent};
+use query::Query;
+use search::{SearchIndex, SearchOptions};
+use service::{build_service_report, ServiceReport, ServiceReportOptions};
+use service_export::{service_report_csv, service_report_ods, SpreadsheetOptions};
+use sync::{Decision, FetchOutcome, FetchRequest, SourceState, SyncPolicy, SyncReport, Trigger};
+use timeseries::{aggregate_series, SeriesOptions};
+use wellbeing::{analyze_wellbeing, WellbeingConfig};
 
 #[derive(Serialize)]
 struct ParseAndNormalizeDetailedResult {
@@ -17,6 +59,52 @@ struct ParseOnlyDetailedResult {
     diagnostics: ParseDiagnostics,
 }
 
+#[derive(Serialize)]
+struct SyncBeginResult {
+    state: SourceState,
+    request: Option<FetchRequest>,
+    decision: Decision,
+}
+
+#[derive(Serialize)]
+struct SyncCompleteResult {
+    state: SourceState,
+    report: SyncReport,
+}
+
+fn sync_policy(policy: JsValue) -> Result<SyncPolicy, JsValue> {
+    if policy.is_undefined() || policy.is_null() {
+        return Ok(SyncPolicy::default());
+    }
+    serde_wasm_bindgen::from_value(policy)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize sync policy: {e}")))
+}
+
+fn export_options(options: JsValue) -> Result<IcsExportOptions, JsValue> {
+    if options.is_undefined() || options.is_null() {
+        return Ok(IcsExportOptions::default());
+    }
+    serde_wasm_bindgen::from_value(options)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize ICS export options: {e}")))
+}
+
+fn csv_import_options(options: JsValue) -> Result<csv_import::CsvImportOptions, JsValue> {
+    if options.is_undefined() || options.is_null() {
+        return Ok(csv_import::CsvImportOptions::default());
+    }
+    serde_wasm_bindgen::from_value(options)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize CSV import options: {e}")))
+}
+
+fn identity_config(config: JsValue) -> Result<IdentityConfig, JsValue> {
+    if config.is_undefined() || config.is_null() {
+        Ok(IdentityConfig::default())
+    } else {
+        serde_wasm_bindgen::from_value(config)
+            .map_err(|e| JsValue::from_str(&format!("Failed to deserialize identity config: {e}")))
+    }
+}
+
 #[wasm_bindgen]
 pub fn parse_and_normalize(content: &str) -> JsValue {
     let raw_events = parse_ics_content(content);
@@ -63,6 +151,431 @@ pub fn renormalize_raw_events(raw_events: JsValue) -> Result<JsValue, JsValue> {
         .map_err(|e| JsValue::from_str(&format!("Failed to serialize normalized events: {e}")))
 }
 
+#[wasm_bindgen]
+pub fn compute_accounting_totals(events: JsValue, policy: JsValue) -> Result<JsValue, JsValue> {
+    let events: Vec<normalizer::NormalizedEvent> = serde_wasm_bindgen::from_value(events)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize normalized events: {e}")))?;
+    let policy: AccountingPolicy = serde_wasm_bindgen::from_value(policy)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize accounting policy: {e}")))?;
+    serde_wasm_bindgen::to_value(&policy.totals(&events))
+        .map_err(|e| JsValue::from_str(&format!("Failed to serialize accounting totals: {e}")))
+}
+
+#[wasm_bindgen]
+pub fn compute_hetd_report(events: JsValue, config: JsValue) -> Result<JsValue, JsValue> {
+    let events: Vec<normalizer::NormalizedEvent> = serde_wasm_bindgen::from_value(events)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize normalized events: {e}")))?;
+    let config: HetdConfig = if config.is_undefined() || config.is_null() {
+        HetdConfig::default()
+    } else {
+        serde_wasm_bindgen::from_value(config)
+            .map_err(|e| JsValue::from_str(&format!("Failed to deserialize HETD config: {e}")))?
+    };
+    serde_wasm_bindgen::to_value(&compute_hetd(&events, &config))
+        .map_err(|e| JsValue::from_str(&format!("Failed to serialize HETD report: {e}")))
+}
+
+#[wasm_bindgen]
+pub fn compute_service_report(events: JsValue, options: JsValue) -> Result<JsValue, JsValue> {
+    let events: Vec<normalizer::NormalizedEvent> = serde_wasm_bindgen::from_value(events)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize normalized events: {e}")))?;
+    let options: ServiceReportOptions = if options.is_undefined() || options.is_null() {
+        ServiceReportOptions::default()
+    } else {
+        serde_wasm_bindgen::from_value(options).map_err(|e| {
+            JsValue::from_str(&format!(
+                "Failed to deserialize service report options: {e}"
+            ))
+        })?
+    };
+    let report = build_service_report(&events, &options).map_err(|e| JsValue::from_str(&e))?;
+    serde_wasm_bindgen::to_value(&report)
+        .map_err(|e| JsValue::from_str(&format!("Failed to serialize service report: {e}")))
+}
+
+fn spreadsheet_input(
+    report: JsValue,
+    options: JsValue,
+) -> Result<(ServiceReport, SpreadsheetOptions), JsValue> {
+    let report: ServiceReport = serde_wasm_bindgen::from_value(report)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize service report: {e}")))?;
+    let options: SpreadsheetOptions = if options.is_undefined() || options.is_null() {
+        SpreadsheetOptions::default()
+    } else {
+        serde_wasm_bindgen::from_value(options).map_err(|e| {
+            JsValue::from_str(&format!("Failed to deserialize spreadsheet options: {e}"))
+        })?
+    };
+    Ok((report, options))
+}
+
+/// Fiche de service rendered with `template`; errors carry the template line.
+#[wasm_bindgen]
+pub fn render_service_template(
+    events: JsValue,
+    template: &str,
+    options: JsValue,
+) -> Result<String, JsValue> {
+    let events: Vec<normalizer::NormalizedEvent> = serde_wasm_bindgen::from_value(events)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize normalized events: {e}")))?;
+    let options: FicheOptions = if options.is_undefined() || options.is_null() {
+        FicheOptions::default()
+    } else {
+        serde_wasm_bindgen::from_value(options).map_err(|e| {
+            JsValue::from_str(&format!("Failed to deserialize template options: {e}"))
+        })?
+    };
+    render_fiche(&events, template, &options).map_err(|e| {
+        serde_wasm_bindgen::to_value(&e).unwrap_or_else(|_| JsValue::from_str(&e.to_string()))
+    })
+}
+
+/// UTF-8 CSV bytes of a report returned by `compute_service_report`.
+#[wasm_bindgen]
+pub fn export_service_csv(report: JsValue, options: JsValue) -> Result<Vec<u8>, JsValue> {
+    let (report, options) = spreadsheet_input(report, options)?;
+    Ok(service_report_csv(&report, &options).into_bytes())
+}
+
+/// `.ods` bytes of a report returned by `compute_service_report`.
+#[wasm_bindgen]
+pub fn export_service_ods(report: JsValue, options: JsValue) -> Result<Vec<u8>, JsValue> {
+    let (report, options) = spreadsheet_input(report, options)?;
+    Ok(service_report_ods(&report, &options))
+}
+
+#[wasm_bindgen]
+pub fn deduplicate_events(events: JsValue, config: JsValue) -> Result<JsValue, JsValue> {
+    let events: Vec<SourcedEvent> = serde_wasm_bindgen::from_value(events)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize sourced events: {e}")))?;
+    let config: DedupConfig = if config.is_undefined() || config.is_null() {
+        DedupConfig::default()
+    } else {
+        serde_wasm_bindgen::from_value(config)
+            .map_err(|e| JsValue::from_str(&format!("Failed to deserialize dedup config: {e}")))?
+    };
+    serde_wasm_bindgen::to_value(&deduplicate(&events, &config))
+        .map_err(|e| JsValue::from_str(&format!("Failed to serialize dedup result: {e}")))
+}
+
+#[wasm_bindgen]
+pub fn detect_schedule_conflicts(events: JsValue, config: JsValue) -> Result<JsValue, JsValue> {
+    let events: Vec<SourcedEvent> = serde_wasm_bindgen::from_value(events)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize sourced events: {e}")))?;
+    let config: ConflictConfig = if config.is_undefined() || config.is_null() {
+        ConflictConfig::default()
+    } else {
+        serde_wasm_bindgen::from_value(config).map_err(|e| {
+            JsValue::from_str(&format!("Failed to deserialize conflict config: {e}"))
+        })?
+    };
+    serde_wasm_bindgen::to_value(&detect_conflicts(&events, &config))
+        .map_err(|e| JsValue::from_str(&format!("Failed to serialize conflict report: {e}")))
+}
+
+#[wasm_bindgen]
+pub fn find_available_slots(events: JsValue, request: JsValue) -> Result<JsValue, JsValue> {
+    let events: Vec<normalizer::NormalizedEvent> = serde_wasm_bindgen::from_value(events)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize normalized events: {e}")))?;
+    let request: SlotRequest = serde_wasm_bindgen::from_value(request)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize slot request: {e}")))?;
+    let report = find_free_slots(&events, &request).map_err(|e| JsValue::from_str(&e))?;
+    serde_wasm_bindgen::to_value(&report)
+        .map_err(|e| JsValue::from_str(&format!("Failed to serialize free slots: {e}")))
+}
+
+#[wasm_bindgen]
+pub fn compute_wellbeing_report(events: JsValue, config: JsValue) -> Result<JsValue, JsValue> {
+    let events: Vec<normalizer::NormalizedEvent> = serde_wasm_bindgen::from_value(events)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize normalized events: {e}")))?;
+    let config: WellbeingConfig = if config.is_undefined() || config.is_null() {
+        WellbeingConfig::default()
+    } else {
+        serde_wasm_bindgen::from_value(config).map_err(|e| {
+            JsValue::from_str(&format!("Failed to deserialize wellbeing config: {e}"))
+        })?
+    };
+    let report = analyze_wellbeing(&events, &config).map_err(|e| JsValue::from_str(&e))?;
+    serde_wasm_bindgen::to_value(&report)
+        .map_err(|e| JsValue::from_str(&format!("Failed to serialize wellbeing report: {e}")))
+}
+
+#[wasm_bindgen]
+pub fn aggregate_time_series(events: JsValue, options: JsValue) -> Result<JsValue, JsValue> {
+    let events: Vec<SourcedEvent> = serde_wasm_bindgen::from_value(events)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize sourced events: {e}")))?;
+    let options: SeriesOptions = if options.is_undefined() || options.is_null() {
+        SeriesOptions::default()
+    } else {
+        serde_wasm_bindgen::from_value(options)
+            .map_err(|e| JsValue::from_str(&format!("Failed to deserialize series options: {e}")))?
+    };
+    serde_wasm_bindgen::to_value(&aggregate_series(&events, &options))
+        .map_err(|e| JsValue::from_str(&format!("Failed to serialize time series: {e}")))
+}
+
+/// iCalendar text of `events`, ready to be wrapped in a `text/calendar` Blob.
+#[wasm_bindgen]
+pub fn export_ics(events: JsValue, options: JsValue) -> Result<String, JsValue> {
+    let events: Vec<normalizer::NormalizedEvent> = serde_wasm_bindgen::from_value(events)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize normalized events: {e}")))?;
+    write_ics(&events, &export_options(options)?).map_err(|e| {
+        serde_wasm_bindgen::to_value(&e).unwrap_or_else(|_| JsValue::from_str(&e.to_string()))
+    })
+}
+
+#[wasm_bindgen]
+pub fn render_saved_feed(events: JsValue, feed: JsValue) -> Result<JsValue, JsValue> {
+    let events: Vec<SourcedEvent> = serde_wasm_bindgen::from_value(events)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize sourced events: {e}")))?;
+    let feed: SavedFeed = serde_wasm_bindgen::from_value(feed)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize feed: {e}")))?;
+    let rendered = render_feed(&feed, &events, None).map_err(|e| {
+        serde_wasm_bindgen::to_value(&e).unwrap_or_else(|_| JsValue::from_str(&e.to_string()))
+    })?;
+    serde_wasm_bindgen::to_value(&rendered)
+        .map_err(|e| JsValue::from_str(&format!("Failed to serialize feed: {e}")))
+}
+
+/// First half of a refresh: returns the updated `state` and either the
+/// conditional `request` to send with `fetch`, or the `decision` to wait.
+#[wasm_bindgen]
+pub fn sync_begin(
+    state: JsValue,
+    trigger: JsValue,
+    policy: JsValue,
+    now_ms: f64,
+) -> Result<JsValue, JsValue> {
+    let mut state: SourceState = serde_wasm_bindgen::from_value(state)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize source state: {e}")))?;
+    let trigger: Trigger = serde_wasm_bindgen::from_value(trigger)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize sync trigger: {e}")))?;
+    let policy = sync_policy(policy)?;
+    let (request, decision) = match state.begin(trigger, &policy, now_ms as i64) {
+        Ok(request) => (Some(request), Decision::Fetch),
+        Err(decision) => (None, decision),
+    };
+    let payload = SyncBeginResult {
+        state,
+        request,
+        decision,
+    };
+    serde_wasm_bindgen::to_value(&payload)
+        .map_err(|e| JsValue::from_str(&format!("Failed to serialize sync request: {e}")))
+}
+
+/// Second half of a refresh: applies the `fetch` outcome and returns the
+/// updated `state` with a report holding the new events and their diff.
+#[wasm_bindgen]
+pub fn sync_complete(
+    state: JsValue,
+    outcome: JsValue,
+    previous_events: JsValue,
+    policy: JsValue,
+    now_ms: f64,
+) -> Result<JsValue, JsValue> {
+    let mut state: SourceState = serde_wasm_bindgen::from_value(state)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize source state: {e}")))?;
+    let outcome: FetchOutcome = serde_wasm_bindgen::from_value(outcome)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize fetch outcome: {e}")))?;
+    let previous: Vec<normalizer::NormalizedEvent> =
+        serde_wasm_bindgen::from_value(previous_events).map_err(|e| {
+            JsValue::from_str(&format!("Failed to deserialize normalized events: {e}"))
+        })?;
+    let policy = sync_policy(policy)?;
+    let report = state.complete(
+        outcome,
+        &previous,
+        &IdentityConfig::default(),
+        &policy,
+        now_ms as i64,
+    );
+    serde_wasm_bindgen::to_value(&SyncCompleteResult { state, report })
+        .map_err(|e| JsValue::from_str(&format!("Failed to serialize sync report: {e}")))
+}
+
+#[wasm_bindgen]
+pub fn parse_jcal_detailed(content: &str) -> Result<JsValue, JsValue> {
+    let parsed = jcal::parse_jcal_with_diagnostics(content);
+    let payload = ParseOnlyDetailedResult {
+        events: parsed.events,
+        diagnostics: parsed.diagnostics,
+    };
+    serde_wasm_bindgen::to_value(&payload)
+        .map_err(|e| JsValue::from_str(&format!("Failed to serialize raw parse result: {e}")))
+}
+
+#[wasm_bindgen]
+pub fn parse_xcal_detailed(content: &str) -> Result<JsValue, JsValue> {
+    let parsed = xcal::parse_xcal_with_diagnostics(content);
+    let payload = ParseOnlyDetailedResult {
+        events: parsed.events,
+        diagnostics: parsed.diagnostics,
+    };
+    serde_wasm_bindgen::to_value(&payload)
+        .map_err(|e| JsValue::from_str(&format!("Failed to serialize raw parse result: {e}")))
+}
+
+#[wasm_bindgen]
+pub fn export_jcal(events: JsValue, options: JsValue) -> Result<String, JsValue> {
+    let events: Vec<normalizer::NormalizedEvent> = serde_wasm_bindgen::from_value(events)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize normalized events: {e}")))?;
+    jcal::write_jcal(&events, &export_options(options)?).map_err(|e| {
+        serde_wasm_bindgen::to_value(&e).unwrap_or_else(|_| JsValue::from_str(&e.to_string()))
+    })
+}
+
+#[wasm_bindgen]
+pub fn export_xcal(events: JsValue, options: JsValue) -> Result<String, JsValue> {
+    let events: Vec<normalizer::NormalizedEvent> = serde_wasm_bindgen::from_value(events)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize normalized events: {e}")))?;
+    xcal::write_xcal(&events, &export_options(options)?).map_err(|e| {
+        serde_wasm_bindgen::to_value(&e).unwrap_or_else(|_| JsValue::from_str(&e.to_string()))
+    })
+}
+
+#[wasm_bindgen]
+pub fn import_csv_timetable(content: &str, options: JsValue) -> Result<JsValue, JsValue> {
+    let options = csv_import_options(options)?;
+    serde_wasm_bindgen::to_value(&csv_import::import_csv(content, &options))
+        .map_err(|e| JsValue::from_str(&format!("Failed to serialize CSV import: {e}")))
+}
+
+#[wasm_bindgen]
+pub fn import_csv_timetable_normalized(
+    content: &str,
+    options: JsValue,
+) -> Result<JsValue, JsValue> {
+    let options = csv_import_options(options)?;
+    serde_wasm_bindgen::to_value(&csv_import::import_csv_normalized(content, &options))
+        .map_err(|e| JsValue::from_str(&format!("Failed to serialize CSV import: {e}")))
+}
+
+#[wasm_bindgen]
+pub fn csv_mapping_preset(preset: JsValue) -> Result<JsValue, JsValue> {
+    let preset: csv_import::MappingPreset = serde_wasm_bindgen::from_value(preset)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize mapping preset: {e}")))?;
+    serde_wasm_bindgen::to_value(&preset.mapping())
+        .map_err(|e| JsValue::from_str(&format!("Failed to serialize column mapping: {e}")))
+}
+
+#[wasm_bindgen]
+pub fn session_ordinals(events: JsValue) -> Result<JsValue, JsValue> {
+    let events: Vec<normalizer::NormalizedEvent> = serde_wasm_bindgen::from_value(events)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize normalized events: {e}")))?;
+    serde_wasm_bindgen::to_value(&compute_session_ordinals(&events))
+        .map_err(|e| JsValue::from_str(&format!("Failed to serialize session ordinals: {e}")))
+}
+
+/// Indices of the events matching `query`; a syntax error is thrown as a
+/// `{ message, position, length }` object.
+#[wasm_bindgen]
+pub fn query_events(events: JsValue, query: &str) -> Result<JsValue, JsValue> {
+    let query = Query::parse(query).map_err(|e| {
+        serde_wasm_bindgen::to_value(&e).unwrap_or_else(|_| JsValue::from_str(&e.to_string()))
+    })?;
+    let events: Vec<SourcedEvent> = serde_wasm_bindgen::from_value(events)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize sourced events: {e}")))?;
+    let matches: Vec<usize> = (0..events.len())
+        .filter(|&i| query.matches_sourced(&events[i]))
+        .collect();
+    serde_wasm_bindgen::to_value(&matches)
+        .map_err(|e| JsValue::from_str(&format!("Failed to serialize query matches: {e}")))
+}
+
+/// Syntax error of `query` as `{ message, position, length }`, or `null` when valid.
+#[wasm_bindgen]
+pub fn check_query(query: &str) -> Result<JsValue, JsValue> {
+    match Query::parse(query) {
+        Ok(_) => Ok(JsValue::NULL),
+        Err(e) => serde_wasm_bindgen::to_value(&e)
+            .map_err(|e| JsValue::from_str(&format!("Failed to serialize query error: {e}"))),
+    }
+}
+
+#[wasm_bindgen]
+pub fn diff_calendar_snapshots(
+    old_events: JsValue,
+    new_events: JsValue,
+    config: JsValue,
+) -> Result<JsValue, JsValue> {
+    let old: Vec<normalizer::NormalizedEvent> = serde_wasm_bindgen::from_value(old_events)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize old snapshot: {e}")))?;
+    let new: Vec<normalizer::NormalizedEvent> = serde_wasm_bindgen::from_value(new_events)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize new snapshot: {e}")))?;
+    let config = identity_config(config)?;
+    serde_wasm_bindgen::to_value(&diff_snapshots_with(&old, &new, &config))
+        .map_err(|e| JsValue::from_str(&format!("Failed to serialize snapshot diff: {e}")))
+}
+
+#[wasm_bindgen]
+pub fn event_fingerprints(events: JsValue, config: JsValue) -> Result<JsValue, JsValue> {
+    let events: Vec<normalizer::NormalizedEvent> = serde_wasm_bindgen::from_value(events)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize normalized events: {e}")))?;
+    let config = identity_config(config)?;
+    let fingerprints: Vec<String> = events
+        .iter()
+        .map(|event| stable_fingerprint(event, &config))
+        .collect();
+    serde_wasm_bindgen::to_value(&fingerprints)
+        .map_err(|e| JsValue::from_str(&format!("Failed to serialize fingerprints: {e}")))
+}
+
+#[wasm_bindgen]
+pub fn match_imports(
+    old_events: JsValue,
+    new_events: JsValue,
+    config: JsValue,
+) -> Result<JsValue, JsValue> {
+    let old: Vec<normalizer::NormalizedEvent> = serde_wasm_bindgen::from_value(old_events)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize old events: {e}")))?;
+    let new: Vec<normalizer::NormalizedEvent> = serde_wasm_bindgen::from_value(new_events)
+        .map_err(|e| JsValue::from_str(&format!("Failed to deserialize new events: {e}")))?;
+    let config = identity_config(config)?;
+    serde_wasm_bindgen::to_value(&match_events(&old, &new, &config))
+        .map_err(|e| JsValue::from_str(&format!("Failed to serialize event matches: {e}")))
+}
+
+/// Full-text index kept alive on the JS side and refreshed per calendar.
+#[wasm_bindgen]
+#[derive(Default)]
+pub struct EventSearchIndex {
+    inner: SearchIndex,
+}
+
+#[wasm_bindgen]
+impl EventSearchIndex {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> EventSearchIndex {
+        EventSearchIndex::default()
+    }
+
+    pub fn set_calendar(&mut self, calendar_id: &str, events: JsValue) -> Result<(), JsValue> {
+        let events: Vec<normalizer::NormalizedEvent> = serde_wasm_bindgen::from_value(events)
+            .map_err(|e| {
+                JsValue::from_str(&format!("Failed to deserialize normalized events: {e}"))
+            })?;
+        self.inner.set_calendar(calendar_id, &events);
+        Ok(())
+    }
+
+    pub fn remove_calendar(&mut self, calendar_id: &str) {
+        self.inner.remove_calendar(calendar_id);
+    }
+
+    pub fn search(&self, query: &str, options: JsValue) -> Result<JsValue, JsValue> {
+        let options: SearchOptions = if options.is_undefined() || options.is_null() {
+            SearchOptions::default()
+        } else {
+            serde_wasm_bindgen::from_value(options).map_err(|e| {
+                JsValue::from_str(&format!("Failed to deserialize search options: {e}"))
+            })?
+        };
+        serde_wasm_bindgen::to_value(&self.inner.search(query, &options))
+            .map_err(|e| JsValue::from_str(&format!("Failed to serialize search hits: {e}")))
+    }
+}
+
 #[wasm_bindgen]
 pub fn greet(name: &str) -> String {
     format!("Hello, {}! Agendum Core is ready.", name)
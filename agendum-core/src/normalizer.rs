@@ -1,5 +1,8 @@
 use crate::parser::RawEvent;
-use chrono::{DateTime, Datelike, FixedOffset, NaiveDate, NaiveDateTime, TimeZone, Timelike, Weekday};
+use chrono::{
+    DateTime, Datelike, FixedOffset, NaiveDate, NaiveDateTime, SecondsFormat, TimeZone, Timelike,
+    Weekday,
+};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 use std::collections::{BTreeSet, HashSet};
@@ -8,6 +11,15 @@ use std::sync::OnceLock;
 const TYPE_TOKEN: &str = r"(?:CM|TD|TP|CT|DS|CC|EXAM|PROJET|RÉUNION|REUNION)";
 const TYPE_SUFFIX: &str = r"(?:\d+(?:[.-]\d+)*[A-G]?|[A-G])?";
 
+/// Placeholder teacher emitted when a description names nobody.
+pub const UNKNOWN_TEACHER: &str = "—";
+
+/// Placeholder for a missing promo, room or subject in derived reports.
+pub const UNKNOWN: &str = "—";
+
+/// Zone used to interpret floating datetimes and to express all local times.
+pub const DEFAULT_TIMEZONE: &str = "Europe/Paris";
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct NormalizedEvent {
     pub raw: RawEvent,
@@ -16,9 +28,70 @@ pub struct NormalizedEvent {
     pub start_iso: String,
     pub end_iso: String,
     pub duration_hours: f32, // Duration in hours, computed after TZ conversion
+    // Exact duration; `duration_hours` is only a display convenience derived from it.
+    #[serde(default)]
+    pub duration_minutes: i64,
     pub teachers: Vec<String>,
     pub promos: Vec<String>,
     pub cleaned_description: String,
+    // Absolute instants, so consumers never reinterpret `start_iso`/`end_iso`
+    // in their own timezone. Defaulted for events persisted before they existed.
+    #[serde(default)]
+    pub start_utc_ms: Option<i64>,
+    #[serde(default)]
+    pub end_utc_ms: Option<i64>,
+    #[serde(default)]
+    pub start_rfc3339: Option<String>,
+    #[serde(default)]
+    pub end_rfc3339: Option<String>,
+    #[serde(default = "default_timezone")]
+    pub timezone: String,
+}
+
+fn default_timezone() -> String {
+    DEFAULT_TIMEZONE.to_string()
+}
+
+pub fn is_unknown_teacher(name: &str) -> bool {
+    let trimmed = name.trim();
+    trimmed.is_empty()
+        || trimmed == UNKNOWN_TEACHER
+        || trimmed.eq_ignore_ascii_case("unknown teacher")
+}
+
+const LOCAL_ISO_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+
+impl NormalizedEvent {
+    /// Exact duration in minutes, falling back to the absolute instants and then
+    /// to `duration_hours` for events serialized before `duration_minutes` existed.
+    pub fn minutes(&self) -> i64 {
+        if self.duration_minutes != 0 {
+            return self.duration_minutes;
+        }
+        if let (Some(start), Some(end)) = (self.start_utc_ms, self.end_utc_ms) {
+            return (end - start) / 60_000;
+        }
+        (f64::from(self.duration_hours) * 60.0).round() as i64
+    }
+
+    /// Named teachers, without the unknown-teacher placeholder.
+    pub fn known_teachers(&self) -> Vec<&str> {
+        self.teachers
+            .iter()
+            .map(|name| name.trim())
+            .filter(|name| !is_unknown_teacher(name))
+            .collect()
+    }
+
+    /// Wall-clock start in [`DEFAULT_TIMEZONE`], as carried by `start_iso`.
+    pub fn local_start(&self) -> Option<NaiveDateTime> {
+        NaiveDateTime::parse_from_str(&self.start_iso, LOCAL_ISO_FORMAT).ok()
+    }
+
+    /// Wall-clock end in [`DEFAULT_TIMEZONE`], as carried by `end_iso`.
+    pub fn local_end(&self) -> Option<NaiveDateTime> {
+        NaiveDateTime::parse_from_str(&self.end_iso, LOCAL_ISO_FORMAT).ok()
+    }
 }
 
 fn re_type_subject() -> &'static Regex {
@@ -51,6 +124,11 @@ fn re_subject_dash_type() -> &'static Regex {
     })
 }
 
+fn re_type_only() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(&format!(r"(?i)^\s*({TYPE_TOKEN}){TYPE_SUFFIX}\s*$")).unwrap())
+}
+
 fn re_name_inline() -> &'static Regex {
     static RE: OnceLock<Regex> = OnceLock::new();
     RE.get_or_init(|| {
@@ -303,6 +381,11 @@ fn collapse_whitespace(value: &str) -> String {
     value.split_whitespace().collect::<Vec<_>>().join(" ")
 }
 
+/// Case- and spacing-insensitive form of a label, for comparisons.
+pub fn text_key(value: &str) -> String {
+    collapse_whitespace(value.trim()).to_lowercase()
+}
+
 fn split_word_parts(word: &str) -> Vec<String> {
     word.replace('’', "'")
         .split(['-', '\''])
@@ -507,10 +590,11 @@ fn score_promo(line: &str) -> i32 {
     score
 }
 
+#[allow(clippy::if_same_then_else)]
 fn extract_teachers_and_promos(description: &str) -> (Vec<String>, Vec<String>, String) {
     let raw_desc = normalize_description(description);
     if raw_desc.trim().is_empty() {
-        return (vec!["—".to_string()], Vec::new(), String::new());
+        return (vec![UNKNOWN_TEACHER.to_string()], Vec::new(), String::new());
     }
 
     let cleaned_description = strip_modified_noise(raw_desc.trim());
@@ -561,7 +645,7 @@ fn extract_teachers_and_promos(description: &str) -> (Vec<String>, Vec<String>,
     }
 
     if teacher_set.is_empty() {
-        teacher_set.insert("—".to_string());
+        teacher_set.insert(UNKNOWN_TEACHER.to_string());
     }
 
     let mut scored_promos: Vec<(String, i32)> = promo_candidates
@@ -687,6 +771,16 @@ fn strip_trailing_date_if_valid(value: &str) -> String {
     }
 }
 
+/// Type of a cell holding only a session type (`td`, `TP2`, `Réunion`), as
+/// [`normalize`] would write it, including its fallback for unknown types.
+pub fn detect_type(value: &str) -> String {
+    let type_ = match re_type_only().captures(value) {
+        Some(caps) => caps[1].to_string(),
+        None => "Autre".to_string(),
+    };
+    type_.to_uppercase()
+}
+
 pub fn normalize(events: Vec<RawEvent>) -> Vec<NormalizedEvent> {
     let re_type_subject = re_type_subject();
     let re_subject_dash_type = re_subject_dash_type();
@@ -725,17 +819,27 @@ pub fn normalize(events: Vec<RawEvent>) -> Vec<NormalizedEvent> {
             let start_dt = parse_ical_datetime(&raw.start);
             let end_dt = parse_ical_datetime(&raw.end);
 
-            let mut duration_hours = 0.0;
+            let mut duration_minutes = 0;
             let mut start_iso = raw.start.clone();
             let mut end_iso = raw.end.clone();
+            let mut start_utc_ms = None;
+            let mut end_utc_ms = None;
+            let mut start_rfc3339 = None;
+            let mut end_rfc3339 = None;
 
             if let (Some(s), Some(e)) = (start_dt, end_dt) {
                 let diff = e - s;
-                duration_hours = diff.num_minutes() as f32 / 60.0;
+                duration_minutes = diff.num_minutes();
 
                 // Local ISO without timezone suffix (browser treats as local time)
-                start_iso = s.format("%Y-%m-%dT%H:%M:%S").to_string();
-                end_iso = e.format("%Y-%m-%dT%H:%M:%S").to_string();
+                start_iso = s.format(LOCAL_ISO_FORMAT).to_string();
+                end_iso = e.format(LOCAL_ISO_FORMAT).to_string();
+
+                // Offset-qualified variants that are unambiguous in any timezone
+                start_utc_ms = Some(s.timestamp_millis());
+                end_utc_ms = Some(e.timestamp_millis());
+                start_rfc3339 = Some(s.to_rfc3339_opts(SecondsFormat::Secs, false));
+                end_rfc3339 = Some(e.to_rfc3339_opts(SecondsFormat::Secs, false));
             }
 
             NormalizedEvent {
@@ -743,16 +847,78 @@ pub fn normalize(events: Vec<RawEvent>) -> Vec<NormalizedEvent> {
                 type_: type_.to_uppercase(),
                 start_iso,
                 end_iso,
-                duration_hours,
+                duration_hours: duration_minutes as f32 / 60.0,
+                duration_minutes,
                 teachers,
                 promos,
                 cleaned_description,
+                start_utc_ms,
+                end_utc_ms,
+                start_rfc3339,
+                end_rfc3339,
+                timezone: default_timezone(),
                 raw,
             }
         })
         .collect()
 }
 
+/// Event factories for the unit tests of the modules built on [`normalize`].
+#[cfg(test)]
+pub(crate) mod test_support {
+    use super::{normalize, NormalizedEvent};
+    use crate::dedup::SourcedEvent;
+    use crate::parser::RawEvent;
+
+    pub(crate) fn event_with_uid(
+        uid: &str,
+        summary: &str,
+        description: &str,
+        location: &str,
+        start: &str,
+        end: &str,
+    ) -> NormalizedEvent {
+        normalize(vec![RawEvent {
+            uid: uid.to_string(),
+            summary: summary.to_string(),
+            description: description.to_string(),
+            location: location.to_string(),
+            start: start.to_string(),
+            end: end.to_string(),
+        }])
+        .remove(0)
+    }
+
+    /// An event in a room, with a UID made of its summary and start.
+    pub(crate) fn located(
+        summary: &str,
+        description: &str,
+        location: &str,
+        start: &str,
+        end: &str,
+    ) -> NormalizedEvent {
+        let uid = format!("{summary}-{start}");
+        event_with_uid(&uid, summary, description, location, start, end)
+    }
+
+    /// Like [`located`], without a room.
+    pub(crate) fn event(
+        summary: &str,
+        description: &str,
+        start: &str,
+        end: &str,
+    ) -> NormalizedEvent {
+        located(summary, description, "", start, end)
+    }
+
+    pub(crate) fn sourced(calendar_id: &str, event: NormalizedEvent) -> SourcedEvent {
+        SourcedEvent {
+            calendar_id: calendar_id.to_string(),
+            event,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -813,6 +979,11 @@ mod tests {
         assert_eq!(normalized[4].type_, "AUTRE");
         assert_eq!(normalized[4].subject, "Autre chose");
         assert_eq!(normalized[4].duration_hours, 0.0);
+
+        assert_eq!(normalized[0].duration_minutes, 120);
+        assert_eq!(normalized[1].duration_minutes, 90);
+        assert_eq!(normalized[3].duration_minutes, 45);
+        assert_eq!(normalized[4].duration_minutes, 0);
     }
 
     #[test]
@@ -968,4 +1139,39 @@ mod tests {
         assert_eq!(normalized[1].start_iso, "2026-03-31T08:00:00");
         assert_eq!(normalized[1].end_iso, "2026-03-31T09:30:00");
     }
+
+    #[test]
+    fn test_absolute_timestamps_and_offset_qualified_strings() {
+        let winter = make_event("IPD CM", "20260123T140000Z", "20260123T153000Z");
+        let summer = make_event("IPD TD", "20260601T080000", "20260601T100000");
+        let broken = make_event("IPD TP", "not-a-date", "20260601T100000");
+
+        let normalized = normalize(vec![winter, summer, broken]);
+
+        assert_eq!(
+            normalized[0].start_rfc3339.as_deref(),
+            Some("2026-01-23T15:00:00+01:00")
+        );
+        assert_eq!(
+            normalized[0].end_rfc3339.as_deref(),
+            Some("2026-01-23T16:30:00+01:00")
+        );
+        assert_eq!(normalized[0].start_utc_ms, Some(1_769_176_800_000));
+        assert_eq!(normalized[0].timezone, "Europe/Paris");
+
+        // Floating time in summer: 08:00 Paris is 06:00 UTC
+        assert_eq!(
+            normalized[1].start_rfc3339.as_deref(),
+            Some("2026-06-01T08:00:00+02:00")
+        );
+        assert_eq!(normalized[1].start_utc_ms, Some(1_780_293_600_000));
+        assert_eq!(
+            normalized[1].end_utc_ms.unwrap() - normalized[1].start_utc_ms.unwrap(),
+            2 * 3_600_000
+        );
+
+        assert_eq!(normalized[2].start_utc_ms, None);
+        assert_eq!(normalized[2].start_rfc3339, None);
+        assert_eq!(normalized[2].start_iso, "not-a-date");
+    }
 }
@@ -0,0 +1,418 @@
+use crate::normalizer::{text_key, NormalizedEvent};
+use crate::session_type::SessionType;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::OnceLock;
+
+fn re_explicit_number() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"(?i)\b(CM|TD|TP)\s*(\d{1,3})(?:[.-]\d+)*[A-G]?\b").unwrap())
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SessionOrdinal {
+    pub session_type: SessionType,
+    /// Explicit number when the summary carries one, computed number otherwise.
+    pub ordinal: u32,
+    pub computed: u32,
+    pub explicit: Option<u32>,
+    /// `"CM3"`, `"TP1"`…
+    pub label: String,
+    pub series: SeriesKey,
+}
+
+/// Sessions numbered together: one subject and type, and for TD and TP one
+/// set of groups.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SeriesKey {
+    pub subject: String,
+    pub session_type: SessionType,
+    /// Sorted promo keys; empty for lectures, which the whole promo shares.
+    pub groups: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum OrdinalIssue {
+    /// The summary number disagrees with the chronological position.
+    Mismatch {
+        index: usize,
+        explicit: u32,
+        computed: u32,
+    },
+    /// Distinct sessions of a series carry the same summary number.
+    DuplicateExplicit {
+        series: SeriesKey,
+        explicit: u32,
+        indices: Vec<usize>,
+    },
+    /// Summary numbers of a series skip some values.
+    Gap {
+        series: SeriesKey,
+        missing: Vec<u32>,
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+pub struct OrdinalReport {
+    /// One entry per input event; `None` for types outside CM/TD/TP.
+    pub ordinals: Vec<Option<SessionOrdinal>>,
+    pub issues: Vec<OrdinalIssue>,
+}
+
+/// Where one summary number of a series was seen: the computed numbers of the
+/// occurrences carrying it (more than one means distinct sessions) and their indices.
+#[derive(Default)]
+struct ExplicitUse {
+    computed: BTreeSet<u32>,
+    indices: Vec<usize>,
+}
+
+/// Session number written in the summary for the given type, e.g. 3 in "TP3 Réseaux".
+pub fn explicit_session_number(summary: &str, session_type: SessionType) -> Option<u32> {
+    re_explicit_number()
+        .captures_iter(summary)
+        .find(|caps| SessionType::from_type(&caps[1]) == session_type)
+        .and_then(|caps| caps[2].parse().ok())
+}
+
+fn group_key(event: &NormalizedEvent) -> Vec<String> {
+    let promos: BTreeSet<String> = event
+        .promos
+        .iter()
+        .map(|promo| text_key(promo))
+        .filter(|promo| !promo.is_empty())
+        .collect();
+    promos.into_iter().collect()
+}
+
+fn teacher_key(event: &NormalizedEvent) -> Vec<String> {
+    let teachers: BTreeSet<String> = event.known_teachers().into_iter().map(text_key).collect();
+    teachers.into_iter().collect()
+}
+
+fn subject_key(event: &NormalizedEvent) -> String {
+    if event.subject.trim().is_empty() {
+        text_key(&event.raw.summary)
+    } else {
+        text_key(&event.subject)
+    }
+}
+
+/// Lectures are shared by the whole promo; TD and TP are numbered per group.
+fn series_key(event: &NormalizedEvent, session_type: SessionType) -> SeriesKey {
+    SeriesKey {
+        subject: subject_key(event),
+        session_type,
+        groups: match session_type {
+            SessionType::Td | SessionType::Tp => group_key(event),
+            _ => Vec::new(),
+        },
+    }
+}
+
+/// Copies of one occurrence (same slot, teachers, room and summary) share a number.
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+struct OccurrenceKey {
+    start: String,
+    end: String,
+    teachers: Vec<String>,
+    room: String,
+    summary: String,
+}
+
+fn occurrence_key(event: &NormalizedEvent) -> OccurrenceKey {
+    OccurrenceKey {
+        start: event.start_iso.clone(),
+        end: event.end_iso.clone(),
+        teachers: teacher_key(event),
+        room: text_key(&event.raw.location),
+        summary: text_key(&event.raw.summary),
+    }
+}
+
+fn chronological(a: &NormalizedEvent, b: &NormalizedEvent) -> Ordering {
+    let instant = |value: Option<i64>| value.unwrap_or(i64::MAX);
+    instant(a.start_utc_ms)
+        .cmp(&instant(b.start_utc_ms))
+        .then_with(|| instant(a.end_utc_ms).cmp(&instant(b.end_utc_ms)))
+        .then_with(|| subject_key(a).cmp(&subject_key(b)))
+        .then_with(|| text_key(&a.type_).cmp(&text_key(&b.type_)))
+        .then_with(|| group_key(a).cmp(&group_key(b)))
+        .then_with(|| teacher_key(a).cmp(&teacher_key(b)))
+        .then_with(|| a.raw.uid.cmp(&b.raw.uid))
+}
+
+/// Numbers CM, TD and TP sessions chronologically within their series and
+/// checks the result against numbers already present in summaries.
+pub fn compute_session_ordinals(events: &[NormalizedEvent]) -> OrdinalReport {
+    let mut order: Vec<usize> = (0..events.len()).collect();
+    order.sort_by(|&a, &b| chronological(&events[a], &events[b]));
+
+    let mut ordinals: Vec<Option<SessionOrdinal>> = vec![None; events.len()];
+    let mut counters: BTreeMap<SeriesKey, u32> = BTreeMap::new();
+    let mut occurrences: BTreeMap<(SeriesKey, OccurrenceKey), u32> = BTreeMap::new();
+    let mut explicit_uses: BTreeMap<SeriesKey, BTreeMap<u32, ExplicitUse>> = BTreeMap::new();
+    let mut issues = Vec::new();
+
+    for index in order {
+        let event = &events[index];
+        let session_type = SessionType::from_type(&event.type_);
+        if !session_type.is_core() {
+            continue;
+        }
+
+        let series = series_key(event, session_type);
+        let occurrence = (series.clone(), occurrence_key(event));
+        let (computed, first_copy) = match occurrences.get(&occurrence) {
+            Some(&ordinal) => (ordinal, false),
+            None => {
+                let counter = counters.entry(series.clone()).or_default();
+                *counter += 1;
+                occurrences.insert(occurrence, *counter);
+                (*counter, true)
+            }
+        };
+
+        let explicit = explicit_session_number(&event.raw.summary, session_type);
+        if let Some(explicit) = explicit {
+            if explicit != computed {
+                issues.push(OrdinalIssue::Mismatch {
+                    index,
+                    explicit,
+                    computed,
+                });
+                // Count on from the written number so one skipped or repeated
+                // session is reported once, not on every later session.
+                if first_copy {
+                    counters.insert(series.clone(), explicit);
+                }
+            }
+            let entry = explicit_uses
+                .entry(series.clone())
+                .or_default()
+                .entry(explicit)
+                .or_default();
+            entry.computed.insert(computed);
+            entry.indices.push(index);
+        }
+
+        let ordinal = explicit.unwrap_or(computed);
+        ordinals[index] = Some(SessionOrdinal {
+            session_type,
+            ordinal,
+            computed,
+            explicit,
+            label: format!("{}{ordinal}", session_type.label()),
+            series,
+        });
+    }
+
+    for (series, numbers) in explicit_uses {
+        for (&explicit, uses) in &numbers {
+            if uses.computed.len() > 1 {
+                issues.push(OrdinalIssue::DuplicateExplicit {
+                    series: series.clone(),
+                    explicit,
+                    indices: uses.indices.clone(),
+                });
+            }
+        }
+        let highest = numbers.keys().next_back().copied().unwrap_or(0);
+        let missing: Vec<u32> = (1..highest).filter(|n| !numbers.contains_key(n)).collect();
+        if !missing.is_empty() {
+            issues.push(OrdinalIssue::Gap { series, missing });
+        }
+    }
+
+    OrdinalReport { ordinals, issues }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::normalizer::test_support::event;
+
+    #[test]
+    fn numbers_series_per_subject_type_and_group() {
+        let events = vec![
+            event(
+                "TD Algo",
+                "DUPONT Jean\nM1 Groupe A",
+                "20250113T080000",
+                "20250113T100000",
+            ),
+            event(
+                "CM Algo",
+                "DUPONT Jean\nM1",
+                "20250106T080000",
+                "20250106T100000",
+            ),
+            event(
+                "TD Algo",
+                "DUPONT Jean\nM1 Groupe A",
+                "20250106T100000",
+                "20250106T120000",
+            ),
+            event(
+                "TD Algo",
+                "MARTIN Paul\nM1 Groupe B",
+                "20250106T100000",
+                "20250106T120000",
+            ),
+            event(
+                "CM Algo",
+                "DUPONT Jean\nM1",
+                "20250113T140000",
+                "20250113T160000",
+            ),
+            event(
+                "Réunion pédagogique",
+                "",
+                "20250106T120000",
+                "20250106T130000",
+            ),
+        ];
+
+        let report = compute_session_ordinals(&events);
+        let labels: Vec<Option<&str>> = report
+            .ordinals
+            .iter()
+            .map(|ordinal| ordinal.as_ref().map(|o| o.label.as_str()))
+            .collect();
+
+        assert_eq!(
+            labels,
+            vec![
+                Some("TD2"),
+                Some("CM1"),
+                Some("TD1"),
+                Some("TD1"),
+                Some("CM2"),
+                None
+            ]
+        );
+        assert!(report.issues.is_empty());
+    }
+
+    #[test]
+    fn mutualized_copies_share_a_number() {
+        let events = vec![
+            event(
+                "CM Algo",
+                "DUPONT Jean",
+                "20250106T080000",
+                "20250106T100000",
+            ),
+            event(
+                "CM Algo",
+                "DUPONT Jean",
+                "20250106T080000",
+                "20250106T100000",
+            ),
+            event(
+                "CM Algo",
+                "DUPONT Jean",
+                "20250107T080000",
+                "20250107T100000",
+            ),
+        ];
+
+        let report = compute_session_ordinals(&events);
+        let computed: Vec<u32> = report
+            .ordinals
+            .iter()
+            .map(|o| o.as_ref().unwrap().computed)
+            .collect();
+
+        assert_eq!(computed, vec![1, 1, 2]);
+    }
+
+    #[test]
+    fn flags_mismatches_duplicates_and_gaps_in_explicit_numbers() {
+        let events = vec![
+            event(
+                "Réseaux TP1",
+                "DUPONT Jean",
+                "20250106T080000",
+                "20250106T100000",
+            ),
+            event(
+                "Réseaux TP2",
+                "DUPONT Jean",
+                "20250107T080000",
+                "20250107T100000",
+            ),
+            event(
+                "Réseaux TP2",
+                "DUPONT Jean",
+                "20250108T080000",
+                "20250108T100000",
+            ),
+            event(
+                "Réseaux TP5",
+                "DUPONT Jean",
+                "20250109T080000",
+                "20250109T100000",
+            ),
+            event(
+                "Réseaux TP6",
+                "DUPONT Jean",
+                "20250110T080000",
+                "20250110T100000",
+            ),
+        ];
+
+        let report = compute_session_ordinals(&events);
+
+        let fifth = report.ordinals[3].as_ref().unwrap();
+        assert_eq!(fifth.explicit, Some(5));
+        assert_eq!(fifth.computed, 3);
+        assert_eq!(fifth.label, "TP5");
+        assert_eq!(
+            fifth.series,
+            SeriesKey {
+                subject: "réseaux".to_string(),
+                session_type: SessionType::Tp,
+                groups: Vec::new(),
+            }
+        );
+        // The counter follows the written numbers after each mismatch.
+        assert_eq!(report.ordinals[4].as_ref().unwrap().computed, 6);
+        let mismatches = report
+            .issues
+            .iter()
+            .filter(|issue| matches!(issue, OrdinalIssue::Mismatch { .. }))
+            .count();
+        assert_eq!(mismatches, 2);
+        assert!(report.issues.contains(&OrdinalIssue::Mismatch {
+            index: 2,
+            explicit: 2,
+            computed: 3
+        }));
+        assert!(report.issues.iter().any(|issue| matches!(
+            issue,
+            OrdinalIssue::DuplicateExplicit { explicit: 2, indices, .. } if indices == &vec![1, 2]
+        )));
+        assert!(report.issues.iter().any(|issue| matches!(
+            issue,
+            OrdinalIssue::Gap { missing, .. } if missing == &vec![3, 4]
+        )));
+    }
+
+    #[test]
+    fn explicit_number_requires_matching_type_token() {
+        assert_eq!(
+            explicit_session_number("MODX TP3.1", SessionType::Tp),
+            Some(3)
+        );
+        assert_eq!(
+            explicit_session_number("TD 2 Algo", SessionType::Td),
+            Some(2)
+        );
+        assert_eq!(explicit_session_number("CM2 Algo", SessionType::Td), None);
+        assert_eq!(explicit_session_number("TDM4 Algo", SessionType::Td), None);
+    }
+}
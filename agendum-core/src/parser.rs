@@ -18,6 +18,9 @@ pub struct ParseDiagnostics {
     pub parser_errors: u32,
     pub skipped_events_without_uid: u32,
     pub parser_error_messages: Vec<String>,
+    /// Events with an RRULE or RDATE, imported as their first occurrence only.
+    #[serde(default)]
+    pub recurring_events_not_expanded: u32,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
@@ -55,6 +58,7 @@ pub fn parse_ics_content_with_diagnostics(content: &str) -> ParseOutput {
                     let mut location = String::new();
                     let mut start = String::new();
                     let mut end = String::new();
+                    let mut recurring = false;
 
                     for property in component.properties {
                         let val = property.value.unwrap_or_default();
@@ -65,10 +69,14 @@ pub fn parse_ics_content_with_diagnostics(content: &str) -> ParseOutput {
                             "LOCATION" => location = unescape_ical(&val),
                             "DTSTART" => start = val,
                             "DTEND" => end = val,
+                            "RRULE" | "RDATE" => recurring = true,
                             _ => {}
                         }
                     }
 
+                    if recurring {
+                        diagnostics.recurring_events_not_expanded += 1;
+                    }
                     if !uid.is_empty() {
                         events.push(RawEvent {
                             uid,
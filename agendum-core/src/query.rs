@@ -0,0 +1,662 @@
+//! Compact filter language over normalized events.
+
+use crate::dedup::SourcedEvent;
+use crate::normalizer::{text_key, NormalizedEvent};
+use crate::session_type::SessionType;
+use chrono::{Datelike, NaiveDate, Timelike, Weekday};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct QueryError {
+    pub message: String,
+    /// Character offset of the offending text in the query.
+    pub position: usize,
+    /// Length in characters of the offending text, at least 1.
+    pub length: usize,
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (at character {})", self.message, self.position + 1)
+    }
+}
+
+impl std::error::Error for QueryError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pattern {
+    text: String,
+    wildcard: bool,
+}
+
+impl Pattern {
+    fn new(value: &str) -> Self {
+        let text = text_key(value);
+        Pattern {
+            wildcard: text.contains(['*', '?']),
+            text,
+        }
+    }
+
+    pub fn matches(&self, value: &str) -> bool {
+        let value = text_key(value);
+        if self.wildcard {
+            glob_match(&self.text, &value)
+        } else {
+            self.text == value
+        }
+    }
+}
+
+fn glob_match(pattern: &str, value: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let value: Vec<char> = value.chars().collect();
+    let (mut p, mut v) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while v < value.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == value[v]) {
+            p += 1;
+            v += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, v));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            p = star + 1;
+            v = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&ch| ch == '*')
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Condition {
+    /// Bare word: substring of subject, type or teachers.
+    Text(String),
+    Teacher(Vec<Pattern>),
+    Promo(Vec<Pattern>),
+    Room(Vec<Pattern>),
+    Subject(Vec<Pattern>),
+    Type(Vec<Pattern>),
+    Uid(Vec<Pattern>),
+    Calendar(Vec<Pattern>),
+    After(NaiveDate),
+    Before(NaiveDate),
+    On(Vec<NaiveDate>),
+    Weekday(Vec<Weekday>),
+    /// Overlap with a time-of-day window, in minutes since midnight.
+    Time {
+        start: u32,
+        end: u32,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Clause {
+    pub negated: bool,
+    pub condition: Condition,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Query {
+    pub clauses: Vec<Clause>,
+}
+
+struct RawValue {
+    text: String,
+    position: usize,
+    length: usize,
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn error(&self, message: impl Into<String>, position: usize, length: usize) -> QueryError {
+        QueryError {
+            message: message.into(),
+            position,
+            length: length.max(1),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    fn quoted(&mut self) -> Result<RawValue, QueryError> {
+        let start = self.pos;
+        self.pos += 1;
+        let mut text = String::new();
+        loop {
+            match self.peek() {
+                None => {
+                    return Err(self.error("unterminated quoted value", start, self.pos - start))
+                }
+                Some('"') => {
+                    self.pos += 1;
+                    break;
+                }
+                Some('\\') if self.chars.get(self.pos + 1) == Some(&'"') => {
+                    text.push('"');
+                    self.pos += 2;
+                }
+                Some(ch) => {
+                    text.push(ch);
+                    self.pos += 1;
+                }
+            }
+        }
+        Ok(RawValue {
+            text,
+            position: start,
+            length: self.pos - start,
+        })
+    }
+
+    fn bare(&mut self, stop_at_comma: bool) -> RawValue {
+        let start = self.pos;
+        while let Some(ch) = self.peek() {
+            if ch.is_whitespace() || (stop_at_comma && ch == ',') {
+                break;
+            }
+            self.pos += 1;
+        }
+        RawValue {
+            text: self.chars[start..self.pos].iter().collect(),
+            position: start,
+            length: self.pos - start,
+        }
+    }
+
+    fn values(&mut self, field: &str, field_pos: usize) -> Result<Vec<RawValue>, QueryError> {
+        let mut values = Vec::new();
+        loop {
+            let value = match self.peek() {
+                Some('"') => self.quoted()?,
+                _ => self.bare(true),
+            };
+            if value.text.trim().is_empty() {
+                return Err(self.error(
+                    format!("missing value for `{field}:`"),
+                    if values.is_empty() {
+                        field_pos
+                    } else {
+                        value.position
+                    },
+                    field.chars().count() + 1,
+                ));
+            }
+            values.push(value);
+            if self.peek() == Some(',') {
+                self.pos += 1;
+                continue;
+            }
+            if self.peek().is_some_and(|ch| !ch.is_whitespace()) {
+                let rest = self.bare(false);
+                return Err(self.error(
+                    format!("unexpected `{}` after value", rest.text),
+                    rest.position,
+                    rest.length,
+                ));
+            }
+            return Ok(values);
+        }
+    }
+
+    fn term(&mut self) -> Result<Clause, QueryError> {
+        let mut negated = false;
+        if self.peek() == Some('-')
+            && self
+                .chars
+                .get(self.pos + 1)
+                .is_some_and(|ch| !ch.is_whitespace())
+        {
+            negated = true;
+            self.pos += 1;
+        }
+
+        if self.peek() == Some('"') {
+            let value = self.quoted()?;
+            return Ok(Clause {
+                negated,
+                condition: Condition::Text(text_key(&value.text)),
+            });
+        }
+
+        let field_pos = self.pos;
+        let mut end = self.pos;
+        while self
+            .chars
+            .get(end)
+            .is_some_and(|ch| ch.is_ascii_alphabetic() || *ch == '_')
+        {
+            end += 1;
+        }
+        if end > field_pos && self.chars.get(end) == Some(&':') {
+            let field: String = self.chars[field_pos..end]
+                .iter()
+                .collect::<String>()
+                .to_lowercase();
+            self.pos = end + 1;
+            let values = self.values(&field, field_pos)?;
+            let condition = self.condition(&field, field_pos, values)?;
+            return Ok(Clause { negated, condition });
+        }
+
+        let word = self.bare(false);
+        Ok(Clause {
+            negated,
+            condition: Condition::Text(text_key(&word.text)),
+        })
+    }
+
+    fn condition(
+        &self,
+        field: &str,
+        field_pos: usize,
+        values: Vec<RawValue>,
+    ) -> Result<Condition, QueryError> {
+        let patterns = || {
+            values
+                .iter()
+                .map(|value| Pattern::new(&value.text))
+                .collect()
+        };
+        let single = |what: &str| -> Result<&RawValue, QueryError> {
+            match values.as_slice() {
+                [value] => Ok(value),
+                [_, second, ..] => Err(self.error(
+                    format!("`{field}:` takes a single {what}"),
+                    second.position,
+                    second.length,
+                )),
+                [] => unreachable!("values() returns at least one value"),
+            }
+        };
+
+        Ok(match field {
+            "teacher" | "prof" | "enseignant" => Condition::Teacher(patterns()),
+            "promo" | "group" | "groupe" => Condition::Promo(patterns()),
+            "room" | "salle" | "location" => Condition::Room(patterns()),
+            "subject" | "matiere" => Condition::Subject(patterns()),
+            "type" => Condition::Type(patterns()),
+            "uid" => Condition::Uid(patterns()),
+            "calendar" | "source" => Condition::Calendar(patterns()),
+            "after" | "from" => Condition::After(self.date(single("date")?)?),
+            "before" | "to" | "until" => Condition::Before(self.date(single("date")?)?),
+            "on" | "date" => Condition::On(
+                values
+                    .iter()
+                    .map(|value| self.date(value))
+                    .collect::<Result<_, _>>()?,
+            ),
+            "weekday" | "day" | "jour" => {
+                let mut days = Vec::new();
+                for value in &values {
+                    days.extend(self.weekdays(value)?);
+                }
+                Condition::Weekday(days)
+            }
+            "time" | "heure" => {
+                let value = single("time range")?;
+                let (start, end) = self.time_range(value)?;
+                Condition::Time { start, end }
+            }
+            _ => {
+                return Err(self.error(
+                    format!("unknown field `{field}`"),
+                    field_pos,
+                    field.chars().count(),
+                ))
+            }
+        })
+    }
+
+    fn date(&self, value: &RawValue) -> Result<NaiveDate, QueryError> {
+        let text = value.text.trim();
+        ["%Y-%m-%d", "%d/%m/%Y", "%Y%m%d"]
+            .iter()
+            .find_map(|fmt| NaiveDate::parse_from_str(text, fmt).ok())
+            .ok_or_else(|| {
+                self.error(
+                    format!("invalid date `{text}`, expected YYYY-MM-DD or DD/MM/YYYY"),
+                    value.position,
+                    value.length,
+                )
+            })
+    }
+
+    fn weekdays(&self, value: &RawValue) -> Result<Vec<Weekday>, QueryError> {
+        let parse = |name: &str| -> Result<Weekday, QueryError> {
+            parse_weekday(name).ok_or_else(|| {
+                self.error(
+                    format!("unknown weekday `{name}`"),
+                    value.position,
+                    value.length,
+                )
+            })
+        };
+        let text = value.text.trim();
+        let Some((from, to)) = text.split_once('-') else {
+            return Ok(vec![parse(text)?]);
+        };
+        let (from, to) = (parse(from)?, parse(to)?);
+        let mut days = vec![from];
+        let mut day = from;
+        while day != to {
+            day = day.succ();
+            days.push(day);
+        }
+        Ok(days)
+    }
+
+    fn time_range(&self, value: &RawValue) -> Result<(u32, u32), QueryError> {
+        let invalid = || {
+            self.error(
+                format!("invalid time range `{}`, expected HH:MM-HH:MM", value.text),
+                value.position,
+                value.length,
+            )
+        };
+        let (start, end) = value.text.trim().split_once('-').ok_or_else(invalid)?;
+        let start = parse_clock(start).ok_or_else(invalid)?;
+        let end = parse_clock(end).ok_or_else(invalid)?;
+        if end < start {
+            return Err(self.error(
+                format!("time range `{}` ends before it starts", value.text),
+                value.position,
+                value.length,
+            ));
+        }
+        Ok((start, end))
+    }
+}
+
+fn parse_weekday(name: &str) -> Option<Weekday> {
+    let lower = name.trim().to_lowercase();
+    let day = match lower.as_str() {
+        "mon" | "monday" | "lun" | "lundi" => Weekday::Mon,
+        "tue" | "tuesday" | "mar" | "mardi" => Weekday::Tue,
+        "wed" | "wednesday" | "mer" | "mercredi" => Weekday::Wed,
+        "thu" | "thursday" | "jeu" | "jeudi" => Weekday::Thu,
+        "fri" | "friday" | "ven" | "vendredi" => Weekday::Fri,
+        "sat" | "saturday" | "sam" | "samedi" => Weekday::Sat,
+        "sun" | "sunday" | "dim" | "dimanche" => Weekday::Sun,
+        _ => return None,
+    };
+    Some(day)
+}
+
+/// Minutes since midnight of `HH:MM`, `HHhMM` or `HH`.
+pub(crate) fn parse_clock(value: &str) -> Option<u32> {
+    let value = value.trim().to_lowercase();
+    let (hours, minutes) = match value.split_once([':', 'h']) {
+        Some((hours, "")) => (hours, "0"),
+        Some((hours, minutes)) => (hours, minutes),
+        None => (value.as_str(), "0"),
+    };
+    let hours: u32 = hours.parse().ok()?;
+    let minutes: u32 = minutes.parse().ok()?;
+    (hours <= 24 && minutes < 60 && hours * 60 + minutes <= 24 * 60).then_some(hours * 60 + minutes)
+}
+
+fn minutes_of_day(time: chrono::NaiveDateTime) -> u32 {
+    time.hour() * 60 + time.minute()
+}
+
+impl Query {
+    /// Parses whitespace-separated terms that must all match, such as
+    /// `teacher:"DUPONT Jean" type:TD,TP after:2025-09-01 -room:Amphi* réseau`.
+    /// `field:a,b` matches any alternative, `*` and `?` are wildcards, `-`
+    /// negates and bare words search subject, type and teachers. Values match
+    /// whole and case-insensitively unless wildcarded; dates are inclusive.
+    pub fn parse(input: &str) -> Result<Query, QueryError> {
+        let mut parser = Parser {
+            chars: input.chars().collect(),
+            pos: 0,
+        };
+        let mut clauses = Vec::new();
+        loop {
+            parser.skip_whitespace();
+            if parser.peek().is_none() {
+                break;
+            }
+            clauses.push(parser.term()?);
+        }
+        Ok(Query { clauses })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.clauses.is_empty()
+    }
+
+    pub fn matches(&self, event: &NormalizedEvent) -> bool {
+        self.matches_in(event, None)
+    }
+
+    pub fn matches_sourced(&self, sourced: &SourcedEvent) -> bool {
+        self.matches_in(&sourced.event, Some(&sourced.calendar_id))
+    }
+
+    /// `calendar:` terms only match when the event's calendar is known.
+    pub fn matches_in(&self, event: &NormalizedEvent, calendar_id: Option<&str>) -> bool {
+        self.clauses
+            .iter()
+            .all(|clause| clause.condition.matches(event, calendar_id) != clause.negated)
+    }
+
+    /// Indices of the matching events.
+    pub fn filter(&self, events: &[NormalizedEvent]) -> Vec<usize> {
+        (0..events.len())
+            .filter(|&i| self.matches(&events[i]))
+            .collect()
+    }
+}
+
+impl Condition {
+    fn matches(&self, event: &NormalizedEvent, calendar_id: Option<&str>) -> bool {
+        let any = |patterns: &[Pattern], value: &str| patterns.iter().any(|p| p.matches(value));
+        let any_of =
+            |patterns: &[Pattern], values: &[&str]| values.iter().any(|v| any(patterns, v));
+        let start = event.local_start();
+
+        match self {
+            Condition::Text(needle) => {
+                text_key(&event.subject).contains(needle.as_str())
+                    || text_key(&event.type_).contains(needle.as_str())
+                    || event
+                        .teachers
+                        .iter()
+                        .any(|teacher| text_key(teacher).contains(needle.as_str()))
+            }
+            Condition::Teacher(patterns) => any_of(patterns, &event.known_teachers()),
+            Condition::Promo(patterns) => {
+                let promos: Vec<&str> = event.promos.iter().map(String::as_str).collect();
+                any_of(patterns, &promos)
+            }
+            Condition::Room(patterns) => any(patterns, &event.raw.location),
+            Condition::Subject(patterns) => any(patterns, &event.subject),
+            Condition::Type(patterns) => {
+                let canonical = SessionType::from_type(&event.type_);
+                patterns.iter().any(|pattern| {
+                    pattern.matches(&event.type_)
+                        || (!pattern.wildcard
+                            && canonical != SessionType::Other
+                            && SessionType::from_type(&pattern.text) == canonical)
+                })
+            }
+            Condition::Uid(patterns) => any(patterns, &event.raw.uid),
+            Condition::Calendar(patterns) => calendar_id.is_some_and(|id| any(patterns, id)),
+            Condition::After(date) => start.is_some_and(|s| s.date() >= *date),
+            Condition::Before(date) => start.is_some_and(|s| s.date() <= *date),
+            Condition::On(dates) => start.is_some_and(|s| dates.contains(&s.date())),
+            Condition::Weekday(days) => start.is_some_and(|s| days.contains(&s.weekday())),
+            Condition::Time {
+                start: from,
+                end: to,
+            } => match (start, event.local_end()) {
+                (Some(s), Some(e)) => minutes_of_day(s) <= *to && minutes_of_day(e) >= *from,
+                _ => false,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::normalizer::test_support::located;
+
+    fn sample() -> Vec<NormalizedEvent> {
+        vec![
+            // Monday morning TD in a regular room
+            located(
+                "TD Algo",
+                "DUPONT Jean\nM1 Informatique",
+                "B12",
+                "20250908T080000",
+                "20250908T100000",
+            ),
+            // Monday CM in an amphitheatre
+            located(
+                "CM Algo",
+                "DUPONT Jean\nM1 Informatique",
+                "Amphi A",
+                "20250908T100000",
+                "20250908T120000",
+            ),
+            // Saturday TP
+            located(
+                "TP Réseaux",
+                "DUPONT Jean\nM1 Informatique",
+                "B13",
+                "20250913T080000",
+                "20250913T100000",
+            ),
+            // Before the start date
+            located(
+                "TD Algo",
+                "DUPONT Jean\nM1 Informatique",
+                "B12",
+                "20250801T080000",
+                "20250801T100000",
+            ),
+            // Other teacher, afternoon
+            located(
+                "TP Réseaux",
+                "MARTIN Paul\nL3 MIAGE",
+                "B13",
+                "20250909T140000",
+                "20250909T160000",
+            ),
+        ]
+    }
+
+    #[test]
+    fn evaluates_the_documented_example() {
+        let query = Query::parse(
+            r#"teacher:"DUPONT Jean" type:TD,TP promo:M1* after:2025-09-01 weekday:mon-fri time:08:00-12:00 -room:Amphi*"#,
+        )
+        .unwrap();
+
+        assert_eq!(query.clauses.len(), 7);
+        assert_eq!(query.filter(&sample()), vec![0]);
+    }
+
+    #[test]
+    fn bare_words_lists_and_negation() {
+        let events = sample();
+
+        assert_eq!(Query::parse("réseaux").unwrap().filter(&events), vec![2, 4]);
+        assert_eq!(
+            Query::parse("\"dupont jean\"").unwrap().filter(&events),
+            vec![0, 1, 2, 3]
+        );
+        assert_eq!(
+            Query::parse("-teacher:dupont*").unwrap().filter(&events),
+            vec![4]
+        );
+        assert_eq!(
+            Query::parse("room:b12,b13 before:08/09/2025")
+                .unwrap()
+                .filter(&events),
+            vec![0, 3]
+        );
+        assert_eq!(
+            Query::parse("weekday:sat,sun").unwrap().filter(&events),
+            vec![2]
+        );
+        assert_eq!(
+            Query::parse("on:2025-09-09").unwrap().filter(&events),
+            vec![4]
+        );
+        assert_eq!(Query::parse("").unwrap().filter(&events).len(), 5);
+    }
+
+    #[test]
+    fn calendar_terms_need_a_source() {
+        let query = Query::parse("calendar:m1*").unwrap();
+        let sourced = SourcedEvent {
+            calendar_id: "M1-info".to_string(),
+            event: sample().remove(0),
+        };
+
+        assert!(query.matches_sourced(&sourced));
+        assert!(!query.matches(&sourced.event));
+    }
+
+    #[test]
+    fn reports_precise_syntax_errors() {
+        let err = Query::parse("type:TD colour:red").unwrap_err();
+        assert_eq!(err.message, "unknown field `colour`");
+        assert_eq!((err.position, err.length), (8, 6));
+
+        let err = Query::parse(r#"teacher:"DUPONT Jean"#).unwrap_err();
+        assert_eq!(err.message, "unterminated quoted value");
+        assert_eq!(err.position, 8);
+
+        let err = Query::parse("after:2025-13-01").unwrap_err();
+        assert!(err.message.starts_with("invalid date `2025-13-01`"));
+        assert_eq!((err.position, err.length), (6, 10));
+
+        let err = Query::parse("time:12:00-08:00").unwrap_err();
+        assert!(err.message.contains("ends before it starts"));
+
+        let err = Query::parse("weekday:mon-fry").unwrap_err();
+        assert_eq!(err.message, "unknown weekday `fry`");
+
+        let err = Query::parse("teacher: DUPONT").unwrap_err();
+        assert_eq!(err.message, "missing value for `teacher:`");
+        assert_eq!(err.position, 0);
+
+        let err = Query::parse(r#"teacher:"A"B"#).unwrap_err();
+        assert_eq!(err.message, "unexpected `B` after value");
+        assert_eq!(
+            err.to_string(),
+            "unexpected `B` after value (at character 12)"
+        );
+    }
+
+    #[test]
+    fn glob_matching() {
+        assert!(glob_match("m1*", "m1 informatique"));
+        assert!(glob_match("*info*", "m1 informatique"));
+        assert!(glob_match("b1?", "b12"));
+        assert!(!glob_match("b1?", "b123"));
+        assert!(glob_match("*", ""));
+        assert!(!glob_match("amphi*", "salle amphi"));
+    }
+}
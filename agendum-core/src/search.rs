@@ -0,0 +1,451 @@
+use crate::normalizer::{is_unknown_teacher, NormalizedEvent};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+/// Lowercases, strips diacritics and spells out ligatures, so "Réseaux" and
+/// "reseaux", or "Œuvre" and "oeuvre", fold alike.
+pub fn fold(text: &str) -> String {
+    text.nfd()
+        .filter(|ch| !is_combining_mark(*ch))
+        .flat_map(char::to_lowercase)
+        .fold(String::with_capacity(text.len()), |mut folded, ch| {
+            match ch {
+                'œ' => folded.push_str("oe"),
+                'æ' => folded.push_str("ae"),
+                '’' => folded.push('\''),
+                other => folded.push(other),
+            }
+            folded
+        })
+}
+
+pub fn tokenize(text: &str) -> Vec<String> {
+    fold(text)
+        .split(|ch: char| !ch.is_alphanumeric())
+        .filter(|token| !token.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchField {
+    Subject,
+    Type,
+    Teachers,
+    Promos,
+    Location,
+    Description,
+}
+
+impl SearchField {
+    const ALL: [SearchField; 6] = [
+        SearchField::Subject,
+        SearchField::Type,
+        SearchField::Teachers,
+        SearchField::Promos,
+        SearchField::Location,
+        SearchField::Description,
+    ];
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(default)]
+pub struct FieldBoosts {
+    pub subject: f64,
+    pub type_: f64,
+    pub teachers: f64,
+    pub promos: f64,
+    pub location: f64,
+    pub description: f64,
+}
+
+impl Default for FieldBoosts {
+    fn default() -> Self {
+        FieldBoosts {
+            subject: 3.0,
+            type_: 1.0,
+            teachers: 2.5,
+            promos: 1.5,
+            location: 1.5,
+            description: 0.5,
+        }
+    }
+}
+
+impl FieldBoosts {
+    fn get(&self, field: SearchField) -> f64 {
+        match field {
+            SearchField::Subject => self.subject,
+            SearchField::Type => self.type_,
+            SearchField::Teachers => self.teachers,
+            SearchField::Promos => self.promos,
+            SearchField::Location => self.location,
+            SearchField::Description => self.description,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct SearchOptions {
+    pub limit: usize,
+    /// Let a query term match the beginning of a longer indexed word.
+    pub prefix: bool,
+    /// Tolerate typos: one edit for terms of 4+ characters, two from 8.
+    pub fuzzy: bool,
+    pub boosts: FieldBoosts,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        SearchOptions {
+            limit: 50,
+            prefix: true,
+            fuzzy: true,
+            boosts: FieldBoosts::default(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SearchHit {
+    pub calendar_id: String,
+    /// Position of the event in the list given for its calendar.
+    pub index: usize,
+    pub uid: String,
+    pub score: f64,
+    pub fields: Vec<SearchField>,
+}
+
+struct Document {
+    calendar_id: String,
+    index: usize,
+    uid: String,
+    terms: BTreeSet<String>,
+}
+
+/// Weights of the ways an indexed word can match a query term.
+const EXACT_WEIGHT: f64 = 1.0;
+const PREFIX_WEIGHT: f64 = 0.7;
+const FUZZY_WEIGHT: f64 = 0.4;
+
+/// Inverted index over normalized events, updated one calendar at a time.
+#[derive(Default)]
+pub struct SearchIndex {
+    documents: Vec<Option<Document>>,
+    free_slots: Vec<usize>,
+    by_calendar: HashMap<String, Vec<usize>>,
+    /// term -> document slot -> field -> occurrences
+    postings: BTreeMap<String, HashMap<usize, BTreeMap<SearchField, u32>>>,
+}
+
+fn field_texts(event: &NormalizedEvent) -> Vec<(SearchField, String)> {
+    let teachers = event
+        .teachers
+        .iter()
+        .filter(|name| !is_unknown_teacher(name))
+        .cloned()
+        .collect::<Vec<_>>()
+        .join(" ");
+    vec![
+        (SearchField::Subject, event.subject.clone()),
+        (SearchField::Type, event.type_.clone()),
+        (SearchField::Teachers, teachers),
+        (SearchField::Promos, event.promos.join(" ")),
+        (SearchField::Location, event.raw.location.clone()),
+        (SearchField::Description, event.cleaned_description.clone()),
+    ]
+}
+
+fn max_edits(term: &str) -> usize {
+    match term.chars().count() {
+        0..=3 => 0,
+        4..=7 => 1,
+        _ => 2,
+    }
+}
+
+/// Levenshtein distance, giving up as soon as it exceeds `limit`.
+fn within_edits(a: &str, b: &str, limit: usize) -> bool {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    if a.len().abs_diff(b.len()) > limit {
+        return false;
+    }
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        if current.iter().min().is_some_and(|&best| best > limit) {
+            return false;
+        }
+        previous = current;
+    }
+    previous[b.len()] <= limit
+}
+
+impl SearchIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.documents.len() - self.free_slots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Replaces everything indexed for `calendar_id` with `events`.
+    pub fn set_calendar(&mut self, calendar_id: &str, events: &[NormalizedEvent]) {
+        self.remove_calendar(calendar_id);
+        let mut slots = Vec::with_capacity(events.len());
+        for (index, event) in events.iter().enumerate() {
+            slots.push(self.insert(calendar_id, index, event));
+        }
+        self.by_calendar.insert(calendar_id.to_string(), slots);
+    }
+
+    pub fn remove_calendar(&mut self, calendar_id: &str) {
+        let Some(slots) = self.by_calendar.remove(calendar_id) else {
+            return;
+        };
+        for slot in slots {
+            let Some(document) = self.documents[slot].take() else {
+                continue;
+            };
+            for term in &document.terms {
+                if let Some(docs) = self.postings.get_mut(term) {
+                    docs.remove(&slot);
+                    if docs.is_empty() {
+                        self.postings.remove(term);
+                    }
+                }
+            }
+            self.free_slots.push(slot);
+        }
+    }
+
+    fn insert(&mut self, calendar_id: &str, index: usize, event: &NormalizedEvent) -> usize {
+        let slot = self.free_slots.pop().unwrap_or_else(|| {
+            self.documents.push(None);
+            self.documents.len() - 1
+        });
+
+        let mut terms = BTreeSet::new();
+        for (field, text) in field_texts(event) {
+            for token in tokenize(&text) {
+                *self
+                    .postings
+                    .entry(token.clone())
+                    .or_default()
+                    .entry(slot)
+                    .or_default()
+                    .entry(field)
+                    .or_default() += 1;
+                terms.insert(token);
+            }
+        }
+
+        self.documents[slot] = Some(Document {
+            calendar_id: calendar_id.to_string(),
+            index,
+            uid: event.raw.uid.clone(),
+            terms,
+        });
+        slot
+    }
+
+    /// Indexed words a query term can stand for, with the weight of each match.
+    fn expand(&self, term: &str, options: &SearchOptions) -> Vec<(&str, f64)> {
+        let mut matches: BTreeMap<&str, f64> = BTreeMap::new();
+        if let Some((word, _)) = self.postings.get_key_value(term) {
+            matches.insert(word, EXACT_WEIGHT);
+        }
+        if options.prefix && term.chars().count() >= 2 {
+            for (word, _) in self
+                .postings
+                .range(term.to_string()..)
+                .take_while(|(word, _)| word.starts_with(term))
+            {
+                matches.entry(word).or_insert(PREFIX_WEIGHT);
+            }
+        }
+        let edits = max_edits(term);
+        if options.fuzzy && edits > 0 {
+            for word in self.postings.keys() {
+                if !matches.contains_key(word.as_str()) && within_edits(term, word, edits) {
+                    matches.insert(word, FUZZY_WEIGHT);
+                }
+            }
+        }
+        matches.into_iter().collect()
+    }
+
+    /// Ranked events matching every word of `query` in at least one field.
+    pub fn search(&self, query: &str, options: &SearchOptions) -> Vec<SearchHit> {
+        let terms = tokenize(query);
+        if terms.is_empty() {
+            return Vec::new();
+        }
+
+        let total = self.len().max(1) as f64;
+        let mut scores: Option<HashMap<usize, (f64, BTreeSet<SearchField>)>> = None;
+
+        for term in &terms {
+            let mut term_scores: HashMap<usize, (f64, BTreeSet<SearchField>)> = HashMap::new();
+            for (word, weight) in self.expand(term, options) {
+                let docs = &self.postings[word];
+                let idf = (1.0 + total / docs.len() as f64).ln();
+                for (&slot, fields) in docs {
+                    let best = fields
+                        .iter()
+                        .map(|(&field, &count)| {
+                            let tf = 1.0 + f64::from(count).ln();
+                            options.boosts.get(field) * tf
+                        })
+                        .fold(0.0, f64::max);
+                    let entry = term_scores.entry(slot).or_default();
+                    entry.0 = entry.0.max(best * weight * idf);
+                    entry.1.extend(fields.keys().copied());
+                }
+            }
+
+            scores = Some(match scores {
+                None => term_scores,
+                Some(previous) => previous
+                    .into_iter()
+                    .filter_map(|(slot, (score, fields))| {
+                        let (term_score, term_fields) = term_scores.remove(&slot)?;
+                        Some((slot, (score + term_score, &fields | &term_fields)))
+                    })
+                    .collect(),
+            });
+        }
+
+        let mut hits: Vec<SearchHit> = scores
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|(slot, (score, fields))| {
+                let document = self.documents[slot].as_ref()?;
+                Some(SearchHit {
+                    calendar_id: document.calendar_id.clone(),
+                    index: document.index,
+                    uid: document.uid.clone(),
+                    score,
+                    fields: SearchField::ALL
+                        .into_iter()
+                        .filter(|field| fields.contains(field))
+                        .collect(),
+                })
+            })
+            .collect();
+        hits.sort_by(|a, b| {
+            b.score
+                .total_cmp(&a.score)
+                .then_with(|| a.calendar_id.cmp(&b.calendar_id))
+                .then_with(|| a.index.cmp(&b.index))
+        });
+        hits.truncate(options.limit);
+        hits
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::normalizer::test_support::event_with_uid;
+
+    fn event(summary: &str, description: &str, location: &str) -> NormalizedEvent {
+        let (start, end) = ("20250106T080000", "20250106T100000");
+        event_with_uid(summary, summary, description, location, start, end)
+    }
+
+    fn index() -> SearchIndex {
+        let mut index = SearchIndex::new();
+        index.set_calendar(
+            "m1",
+            &[
+                event("CM Réseaux", "DUPONT Jean\nM1 Informatique", "Amphi A"),
+                event("TD Algorithmique", "MARTIN Paul\nM1 Informatique", "B12"),
+                event(
+                    "TP Systèmes",
+                    "DUPONT Jean\nM1 Informatique",
+                    "Salle réseau",
+                ),
+            ],
+        );
+        index
+    }
+
+    #[test]
+    fn folds_accents_and_case() {
+        assert_eq!(fold("Réseaux ÉLÈVES Œuvre"), "reseaux eleves oeuvre");
+        assert_ne!(fold("cœur"), fold("cour"));
+        assert_eq!(fold("Cæcum"), "caecum");
+        assert_eq!(
+            tokenize("M1 Informatique - Groupe A"),
+            vec!["m1", "informatique", "groupe", "a"]
+        );
+    }
+
+    #[test]
+    fn prefix_match_ranks_subject_above_location() {
+        let hits = index().search("reseau", &SearchOptions::default());
+
+        assert_eq!(hits.len(), 2);
+        assert_eq!(hits[0].uid, "CM Réseaux");
+        assert_eq!(hits[0].fields, vec![SearchField::Subject]);
+        assert_eq!(hits[1].uid, "TP Systèmes");
+        assert_eq!(hits[1].fields, vec![SearchField::Location]);
+        assert!(hits[0].score > hits[1].score);
+    }
+
+    #[test]
+    fn all_words_must_match_and_typos_are_tolerated() {
+        let index = index();
+
+        let hits = index.search("dupont systemes", &SearchOptions::default());
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].uid, "TP Systèmes");
+
+        let hits = index.search("algoritmique", &SearchOptions::default());
+        assert_eq!(hits.len(), 1);
+
+        let strict = SearchOptions {
+            fuzzy: false,
+            prefix: false,
+            ..SearchOptions::default()
+        };
+        assert!(index.search("algoritmique", &strict).is_empty());
+        assert_eq!(index.search("reseau", &strict).len(), 1);
+    }
+
+    #[test]
+    fn calendars_are_replaced_incrementally() {
+        let mut index = index();
+        index.set_calendar("l3", &[event("CM Réseaux", "BERNARD Luc\nL3 MIAGE", "")]);
+        assert_eq!(index.len(), 4);
+        // Both "Réseaux" subjects, plus "Salle réseau" one edit away
+        assert_eq!(index.search("reseaux", &SearchOptions::default()).len(), 3);
+
+        index.set_calendar("m1", &[event("TD Algorithmique", "MARTIN Paul", "B12")]);
+        assert_eq!(index.len(), 2);
+        let hits = index.search("reseaux", &SearchOptions::default());
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].calendar_id, "l3");
+
+        index.remove_calendar("l3");
+        assert_eq!(index.len(), 1);
+        assert!(index
+            .search("bernard", &SearchOptions::default())
+            .is_empty());
+    }
+}
@@ -0,0 +1,345 @@
+use crate::accounting::AccountingPolicy;
+use crate::hetd::credited_teachers;
+use crate::normalizer::{is_unknown_teacher, NormalizedEvent, UNKNOWN_TEACHER};
+use crate::session_type::SessionType;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ServiceScope {
+    #[default]
+    Total,
+    /// Sessions already finished at `as_of_ms`.
+    Done,
+    /// Sessions not finished yet at `as_of_ms`.
+    Upcoming,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct ServiceReportOptions {
+    /// Restrict the report to one teacher, matched case-insensitively.
+    pub teacher: Option<String>,
+    /// Reference instant (UTC epoch milliseconds) for the done/upcoming split;
+    /// required unless the scope is [`ServiceScope::Total`].
+    pub as_of_ms: Option<i64>,
+    pub scope: ServiceScope,
+    pub accounting: AccountingPolicy,
+}
+
+/// Minutes per session type, as shown in the service dashboard columns.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ServiceBreakdown {
+    pub cm: i64,
+    pub td: i64,
+    pub tp: i64,
+    pub project: i64,
+    pub reunion: i64,
+    pub exam: i64,
+    pub other: i64,
+    /// CM + TD + TP.
+    pub core: i64,
+    /// Core + projects.
+    pub teaching: i64,
+    pub total: i64,
+    pub sessions: u32,
+}
+
+impl ServiceBreakdown {
+    pub fn add(&mut self, session_type: SessionType, minutes: i64) {
+        let slot = match session_type {
+            SessionType::Cm => &mut self.cm,
+            SessionType::Td => &mut self.td,
+            SessionType::Tp => &mut self.tp,
+            SessionType::Project => &mut self.project,
+            SessionType::Reunion => &mut self.reunion,
+            SessionType::Exam => &mut self.exam,
+            SessionType::Other => &mut self.other,
+        };
+        *slot += minutes;
+        if session_type.is_core() {
+            self.core += minutes;
+        }
+        if session_type.is_core() || session_type == SessionType::Project {
+            self.teaching += minutes;
+        }
+        self.total += minutes;
+        self.sessions += 1;
+    }
+
+    pub fn get(&self, session_type: SessionType) -> i64 {
+        match session_type {
+            SessionType::Cm => self.cm,
+            SessionType::Td => self.td,
+            SessionType::Tp => self.tp,
+            SessionType::Project => self.project,
+            SessionType::Reunion => self.reunion,
+            SessionType::Exam => self.exam,
+            SessionType::Other => self.other,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SubjectService {
+    pub subject: String,
+    pub breakdown: ServiceBreakdown,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TeacherService {
+    pub teacher: String,
+    pub is_unknown: bool,
+    /// Sorted by total minutes, largest first.
+    pub subjects: Vec<SubjectService>,
+    pub totals: ServiceBreakdown,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ServiceReport {
+    pub scope: ServiceScope,
+    pub as_of_ms: Option<i64>,
+    pub teacher: Option<String>,
+    /// Every selected session counted once, whatever its number of teachers.
+    pub summary: ServiceBreakdown,
+    /// Selected sessions without timestamps, which belong to neither the done
+    /// nor the upcoming scope; only counted in the total scope's summary.
+    pub undated: ServiceBreakdown,
+    /// Sorted by total minutes, largest first, with the unknown bucket last.
+    pub teachers: Vec<TeacherService>,
+}
+
+fn in_scope(reference: Option<i64>, scope: ServiceScope, as_of: i64) -> bool {
+    match (scope, reference) {
+        (ServiceScope::Total, _) => true,
+        (_, None) => false,
+        (ServiceScope::Done, Some(reference)) => reference <= as_of,
+        (ServiceScope::Upcoming, Some(reference)) => reference > as_of,
+    }
+}
+
+/// Teachers the event is credited to under the report's teacher selection, or
+/// `None` when the event belongs to somebody else. Sessions without a known
+/// teacher stay in the unknown bucket whatever the selection.
+fn report_teachers<'a>(
+    event: &'a NormalizedEvent,
+    selected: Option<&'a str>,
+) -> Option<Vec<&'a str>> {
+    let Some(selected) = selected else {
+        return Some(credited_teachers(event));
+    };
+    let known = event.known_teachers();
+    if known.is_empty() {
+        return Some(vec![UNKNOWN_TEACHER]);
+    }
+    let target = selected.to_lowercase();
+    known
+        .into_iter()
+        .find(|name| name.to_lowercase() == target)
+        .map(|name| vec![name])
+}
+
+/// Service totals per teacher, subject and type.
+///
+/// Events are expected to be deduplicated already. The done and upcoming
+/// scopes need `as_of_ms`.
+pub fn build_service_report(
+    events: &[NormalizedEvent],
+    options: &ServiceReportOptions,
+) -> Result<ServiceReport, String> {
+    let as_of = match (options.scope, options.as_of_ms) {
+        (ServiceScope::Total, as_of) => as_of.unwrap_or_default(),
+        (_, Some(as_of)) => as_of,
+        (_, None) => {
+            return Err("as_of_ms is required for the done and upcoming scopes".to_string())
+        }
+    };
+    let selected = options
+        .teacher
+        .as_deref()
+        .map(str::trim)
+        .filter(|name| !name.is_empty());
+
+    let mut selected_events: Vec<&NormalizedEvent> = Vec::new();
+    let mut undated_events: Vec<&NormalizedEvent> = Vec::new();
+    let mut per_teacher: BTreeMap<&str, Vec<&NormalizedEvent>> = BTreeMap::new();
+    for event in events {
+        let Some(teachers) = report_teachers(event, selected) else {
+            continue;
+        };
+        let reference = event.end_utc_ms.or(event.start_utc_ms);
+        if reference.is_none() {
+            undated_events.push(event);
+        }
+        if !in_scope(reference, options.scope, as_of) {
+            continue;
+        }
+        selected_events.push(event);
+        for teacher in teachers {
+            per_teacher.entry(teacher).or_default().push(event);
+        }
+    }
+
+    let mut summary = ServiceBreakdown::default();
+    let allocated = options.accounting.allocate(&selected_events);
+    for (event, minutes) in selected_events.iter().zip(allocated) {
+        summary.add(SessionType::from_type(&event.type_), minutes);
+    }
+    let mut undated = ServiceBreakdown::default();
+    let allocated = options.accounting.allocate(&undated_events);
+    for (event, minutes) in undated_events.iter().zip(allocated) {
+        undated.add(SessionType::from_type(&event.type_), minutes);
+    }
+
+    let mut teachers: Vec<TeacherService> = per_teacher
+        .into_iter()
+        .map(|(teacher, events)| {
+            let allocated = options.accounting.allocate(&events);
+            let mut totals = ServiceBreakdown::default();
+            let mut subjects: BTreeMap<&str, ServiceBreakdown> = BTreeMap::new();
+            for (event, minutes) in events.iter().zip(allocated) {
+                let session_type = SessionType::from_type(&event.type_);
+                totals.add(session_type, minutes);
+                subjects
+                    .entry(event.subject.trim())
+                    .or_default()
+                    .add(session_type, minutes);
+            }
+
+            let mut subjects: Vec<SubjectService> = subjects
+                .into_iter()
+                .map(|(subject, breakdown)| SubjectService {
+                    subject: subject.to_string(),
+                    breakdown,
+                })
+                .collect();
+            subjects.sort_by_key(|subject| std::cmp::Reverse(subject.breakdown.total));
+
+            TeacherService {
+                teacher: teacher.to_string(),
+                is_unknown: is_unknown_teacher(teacher),
+                subjects,
+                totals,
+            }
+        })
+        .collect();
+    teachers.sort_by(|a, b| {
+        a.is_unknown
+            .cmp(&b.is_unknown)
+            .then_with(|| b.totals.total.cmp(&a.totals.total))
+            .then_with(|| a.teacher.cmp(&b.teacher))
+    });
+
+    Ok(ServiceReport {
+        scope: options.scope,
+        as_of_ms: options.as_of_ms,
+        teacher: selected.map(str::to_string),
+        summary,
+        undated,
+        teachers,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::normalizer::test_support::event;
+
+    fn sample() -> Vec<NormalizedEvent> {
+        vec![
+            event(
+                "CM Algo",
+                "DUPONT Jean\nMARTIN Paul",
+                "20250106T080000",
+                "20250106T100000",
+            ),
+            event(
+                "TD Algo",
+                "DUPONT Jean",
+                "20250106T100000",
+                "20250106T120000",
+            ),
+            event(
+                "TP Réseaux",
+                "MARTIN Paul",
+                "20250107T080000",
+                "20250107T110000",
+            ),
+            event("CC Algo", "", "20250108T080000", "20250108T090000"),
+        ]
+    }
+
+    #[test]
+    fn credits_every_teacher_and_counts_summary_once() {
+        let report = build_service_report(&sample(), &ServiceReportOptions::default()).unwrap();
+
+        assert_eq!(report.summary.total, 8 * 60);
+        assert_eq!(report.summary.core, 7 * 60);
+        assert_eq!(report.summary.exam, 60);
+        assert_eq!(report.summary.sessions, 4);
+
+        let names: Vec<&str> = report.teachers.iter().map(|t| t.teacher.as_str()).collect();
+        assert_eq!(names, vec!["MARTIN Paul", "DUPONT Jean", UNKNOWN_TEACHER]);
+        assert_eq!(report.teachers[0].totals.total, 5 * 60);
+        assert_eq!(report.teachers[0].subjects[0].subject, "Réseaux");
+        assert_eq!(report.teachers[1].totals.cm, 120);
+        assert_eq!(report.teachers[1].totals.td, 120);
+        assert!(report.teachers[2].is_unknown);
+    }
+
+    #[test]
+    fn selection_keeps_the_teachers_and_unassigned_sessions() {
+        let options = ServiceReportOptions {
+            teacher: Some("dupont jean".to_string()),
+            ..ServiceReportOptions::default()
+        };
+        let report = build_service_report(&sample(), &options).unwrap();
+
+        // MARTIN Paul's TP is left out, the unassigned CC is kept.
+        assert_eq!(report.summary.sessions, 3);
+        assert_eq!(report.summary.total, 5 * 60);
+        assert_eq!(report.summary.exam, 60);
+        let names: Vec<&str> = report.teachers.iter().map(|t| t.teacher.as_str()).collect();
+        assert_eq!(names, vec!["DUPONT Jean", UNKNOWN_TEACHER]);
+        assert_eq!(report.teachers[0].totals.total, 4 * 60);
+        assert!(report.teachers[1].is_unknown);
+        assert_eq!(report.teachers[1].totals.exam, 60);
+        assert_eq!(report.teachers[1].totals.sessions, 1);
+    }
+
+    #[test]
+    fn scope_splits_on_session_end_and_reports_undated_sessions() {
+        let mut events = sample();
+        events.push(event("TD Algo", "DUPONT Jean", "", ""));
+        events.last_mut().unwrap().duration_minutes = 90;
+        // 2025-01-06T11:00:00+01:00, in the middle of the TD
+        let report = |scope, as_of_ms| {
+            build_service_report(
+                &events,
+                &ServiceReportOptions {
+                    as_of_ms,
+                    scope,
+                    ..ServiceReportOptions::default()
+                },
+            )
+        };
+        let as_of_ms = Some(1_736_157_600_000);
+        let total = report(ServiceScope::Total, None).unwrap();
+        let done = report(ServiceScope::Done, as_of_ms).unwrap();
+        let upcoming = report(ServiceScope::Upcoming, as_of_ms).unwrap();
+
+        assert_eq!(done.summary.sessions, 1);
+        assert_eq!(done.summary.cm, 120);
+        assert_eq!(upcoming.summary.sessions, 3);
+        assert_eq!(total.undated.sessions, 1);
+        assert_eq!(done.undated, total.undated);
+        assert_eq!(
+            done.summary.total + upcoming.summary.total + total.undated.total,
+            total.summary.total
+        );
+
+        assert!(report(ServiceScope::Done, None).is_err());
+        assert!(report(ServiceScope::Upcoming, None).is_err());
+    }
+}
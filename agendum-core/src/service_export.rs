@@ -0,0 +1,493 @@
+use crate::service::{ServiceBreakdown, ServiceReport, TeacherService};
+use crate::session_type::SessionType;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct ServiceColumnLabels {
+    pub teacher: String,
+    pub subject: String,
+    pub cm: String,
+    pub td: String,
+    pub tp: String,
+    pub project: String,
+    pub reunion: String,
+    pub exam: String,
+    pub other: String,
+    pub total: String,
+    pub sessions: String,
+    /// Label of the per-teacher and whole-report total rows.
+    pub total_row: String,
+    /// Name of the first ODS sheet, listing every teacher.
+    pub summary_sheet: String,
+}
+
+impl Default for ServiceColumnLabels {
+    fn default() -> Self {
+        ServiceColumnLabels {
+            teacher: "Teacher".to_string(),
+            subject: "Subject".to_string(),
+            cm: "CM".to_string(),
+            td: "TD".to_string(),
+            tp: "TP".to_string(),
+            project: "Project".to_string(),
+            reunion: "Meeting".to_string(),
+            exam: "Exam".to_string(),
+            other: "Other".to_string(),
+            total: "Total".to_string(),
+            sessions: "Sessions".to_string(),
+            total_row: "Total".to_string(),
+            summary_sheet: "Summary".to_string(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct SpreadsheetOptions {
+    /// CSV field separator; French spreadsheets expect `;`.
+    pub delimiter: char,
+    /// Write `1,5` instead of `1.5` in CSV and in ODS display text.
+    pub decimal_comma: bool,
+    pub decimals: usize,
+    /// Prefix the CSV with a UTF-8 byte order mark so Excel detects accents.
+    pub byte_order_mark: bool,
+    pub labels: ServiceColumnLabels,
+}
+
+impl Default for SpreadsheetOptions {
+    fn default() -> Self {
+        SpreadsheetOptions {
+            delimiter: ',',
+            decimal_comma: false,
+            decimals: 2,
+            byte_order_mark: true,
+            labels: ServiceColumnLabels::default(),
+        }
+    }
+}
+
+const TYPE_COLUMNS: [SessionType; 7] = [
+    SessionType::Cm,
+    SessionType::Td,
+    SessionType::Tp,
+    SessionType::Project,
+    SessionType::Reunion,
+    SessionType::Exam,
+    SessionType::Other,
+];
+
+enum Cell {
+    Text(String),
+    Hours(i64),
+    Count(u32),
+}
+
+fn header(labels: &ServiceColumnLabels, with_teacher: bool) -> Vec<Cell> {
+    let mut cells = Vec::new();
+    if with_teacher {
+        cells.push(Cell::Text(labels.teacher.clone()));
+    }
+    cells.push(Cell::Text(labels.subject.clone()));
+    for label in [
+        &labels.cm,
+        &labels.td,
+        &labels.tp,
+        &labels.project,
+        &labels.reunion,
+        &labels.exam,
+        &labels.other,
+        &labels.total,
+        &labels.sessions,
+    ] {
+        cells.push(Cell::Text(label.clone()));
+    }
+    cells
+}
+
+fn breakdown_row(teacher: Option<&str>, label: &str, breakdown: &ServiceBreakdown) -> Vec<Cell> {
+    let mut cells = Vec::new();
+    if let Some(teacher) = teacher {
+        cells.push(Cell::Text(teacher.to_string()));
+    }
+    cells.push(Cell::Text(label.to_string()));
+    cells.extend(
+        TYPE_COLUMNS
+            .iter()
+            .map(|&session_type| Cell::Hours(breakdown.get(session_type))),
+    );
+    cells.push(Cell::Hours(breakdown.total));
+    cells.push(Cell::Count(breakdown.sessions));
+    cells
+}
+
+fn teacher_rows(
+    teacher: &TeacherService,
+    labels: &ServiceColumnLabels,
+    with_teacher: bool,
+) -> Vec<Vec<Cell>> {
+    let name = with_teacher.then_some(teacher.teacher.as_str());
+    let mut rows: Vec<Vec<Cell>> = teacher
+        .subjects
+        .iter()
+        .map(|subject| breakdown_row(name, &subject.subject, &subject.breakdown))
+        .collect();
+    rows.push(breakdown_row(name, &labels.total_row, &teacher.totals));
+    rows
+}
+
+fn format_hours(minutes: i64, options: &SpreadsheetOptions) -> String {
+    let text = format!("{:.*}", options.decimals, minutes as f64 / 60.0);
+    if options.decimal_comma {
+        text.replace('.', ",")
+    } else {
+        text
+    }
+}
+
+/// Quotes a CSV field when it holds the delimiter, a quote or a line break.
+///
+/// Fields a spreadsheet would run as a formula (`=`, `+`, `-`, `@` first) are
+/// prefixed with `'`: subjects and names come from external calendars.
+pub fn csv_field(value: &str, delimiter: char) -> String {
+    let value = if value.starts_with(['=', '+', '-', '@']) {
+        format!("'{value}")
+    } else {
+        value.to_string()
+    };
+    if value.contains([delimiter, '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
+
+/// One line per teacher and subject, a total line per teacher and a final
+/// line for the whole report. Hours are decimal hours.
+pub fn service_report_csv(report: &ServiceReport, options: &SpreadsheetOptions) -> String {
+    let labels = &options.labels;
+    let mut rows = vec![header(labels, true)];
+    for teacher in &report.teachers {
+        rows.extend(teacher_rows(teacher, labels, true));
+    }
+    rows.push(breakdown_row(Some(&labels.total_row), "", &report.summary));
+
+    let mut out = String::new();
+    if options.byte_order_mark {
+        out.push('\u{feff}');
+    }
+    for row in rows {
+        let fields: Vec<String> = row
+            .iter()
+            .map(|cell| match cell {
+                Cell::Text(text) => csv_field(text, options.delimiter),
+                Cell::Hours(minutes) => {
+                    csv_field(&format_hours(*minutes, options), options.delimiter)
+                }
+                Cell::Count(count) => count.to_string(),
+            })
+            .collect();
+        out.push_str(&fields.join(&options.delimiter.to_string()));
+        out.push_str("\r\n");
+    }
+    out
+}
+
+fn xml_escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for ch in value.chars() {
+        match ch {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            other => escaped.push(other),
+        }
+    }
+    escaped
+}
+
+/// Sheet names may not contain `[]*?:/\` and are kept to 31 characters so
+/// the file also opens in Excel.
+fn sheet_name(name: &str, taken: &mut Vec<String>) -> String {
+    let cleaned: String = name
+        .chars()
+        .map(|ch| if "[]*?:/\\'".contains(ch) { '_' } else { ch })
+        .take(31)
+        .collect();
+    let base = if cleaned.trim().is_empty() {
+        "Sheet".to_string()
+    } else {
+        cleaned.trim().to_string()
+    };
+    let mut candidate = base.clone();
+    let mut suffix = 2;
+    while taken.iter().any(|t| t.eq_ignore_ascii_case(&candidate)) {
+        let tail = format!(" ({suffix})");
+        let head: String = base.chars().take(31 - tail.chars().count()).collect();
+        candidate = format!("{head}{tail}");
+        suffix += 1;
+    }
+    taken.push(candidate.clone());
+    candidate
+}
+
+fn ods_table(name: &str, rows: &[Vec<Cell>], options: &SpreadsheetOptions, out: &mut String) {
+    out.push_str(&format!(
+        "<table:table table:name=\"{}\">",
+        xml_escape(name)
+    ));
+    for row in rows {
+        out.push_str("<table:table-row>");
+        for cell in row {
+            match cell {
+                Cell::Text(text) => out.push_str(&format!(
+                    "<table:table-cell office:value-type=\"string\"><text:p>{}</text:p></table:table-cell>",
+                    xml_escape(text)
+                )),
+                Cell::Hours(minutes) => out.push_str(&format!(
+                    "<table:table-cell office:value-type=\"float\" office:value=\"{}\"><text:p>{}</text:p></table:table-cell>",
+                    *minutes as f64 / 60.0,
+                    format_hours(*minutes, options)
+                )),
+                Cell::Count(count) => out.push_str(&format!(
+                    "<table:table-cell office:value-type=\"float\" office:value=\"{count}\"><text:p>{count}</text:p></table:table-cell>"
+                )),
+            }
+        }
+        out.push_str("</table:table-row>");
+    }
+    out.push_str("</table:table>");
+}
+
+fn ods_content(report: &ServiceReport, options: &SpreadsheetOptions) -> String {
+    let labels = &options.labels;
+    let mut out = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+         <office:document-content \
+         xmlns:office=\"urn:oasis:names:tc:opendocument:xmlns:office:1.0\" \
+         xmlns:table=\"urn:oasis:names:tc:opendocument:xmlns:table:1.0\" \
+         xmlns:text=\"urn:oasis:names:tc:opendocument:xmlns:text:1.0\" \
+         office:version=\"1.2\"><office:body><office:spreadsheet>",
+    );
+    let mut taken = Vec::new();
+
+    let mut summary = vec![header(labels, true)];
+    for teacher in &report.teachers {
+        summary.push(breakdown_row(
+            Some(&teacher.teacher),
+            &labels.total_row,
+            &teacher.totals,
+        ));
+    }
+    summary.push(breakdown_row(Some(&labels.total_row), "", &report.summary));
+    let name = sheet_name(&labels.summary_sheet, &mut taken);
+    ods_table(&name, &summary, options, &mut out);
+
+    for teacher in &report.teachers {
+        let mut rows = vec![header(labels, false)];
+        rows.extend(teacher_rows(teacher, labels, false));
+        let name = sheet_name(&teacher.teacher, &mut taken);
+        ods_table(&name, &rows, options, &mut out);
+    }
+
+    out.push_str("</office:spreadsheet></office:body></office:document-content>");
+    out
+}
+
+const ODS_MIMETYPE: &str = "application/vnd.oasis.opendocument.spreadsheet";
+
+const ODS_MANIFEST: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+<manifest:manifest xmlns:manifest=\"urn:oasis:names:tc:opendocument:xmlns:manifest:1.0\" manifest:version=\"1.2\">\
+<manifest:file-entry manifest:full-path=\"/\" manifest:version=\"1.2\" manifest:media-type=\"application/vnd.oasis.opendocument.spreadsheet\"/>\
+<manifest:file-entry manifest:full-path=\"content.xml\" manifest:media-type=\"text/xml\"/>\
+</manifest:manifest>";
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+/// Uncompressed ("stored") zip archive. ODS requires `mimetype` to be the
+/// first entry and stored, which this writer does for every entry.
+fn zip_store(entries: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut central = Vec::new();
+    for (name, data) in entries {
+        let offset = out.len() as u32;
+        let crc = crc32(data);
+        let size = data.len() as u32;
+        let name_len = name.len() as u16;
+
+        out.extend_from_slice(&0x0403_4b50u32.to_le_bytes());
+        out.extend_from_slice(&20u16.to_le_bytes()); // version needed
+        out.extend_from_slice(&0x0800u16.to_le_bytes()); // UTF-8 names
+        out.extend_from_slice(&0u16.to_le_bytes()); // stored
+        out.extend_from_slice(&0u16.to_le_bytes()); // time
+        out.extend_from_slice(&0x0021u16.to_le_bytes()); // 1980-01-01
+        out.extend_from_slice(&crc.to_le_bytes());
+        out.extend_from_slice(&size.to_le_bytes());
+        out.extend_from_slice(&size.to_le_bytes());
+        out.extend_from_slice(&name_len.to_le_bytes());
+        out.extend_from_slice(&0u16.to_le_bytes());
+        out.extend_from_slice(name.as_bytes());
+        out.extend_from_slice(data);
+
+        central.extend_from_slice(&0x0201_4b50u32.to_le_bytes());
+        central.extend_from_slice(&20u16.to_le_bytes()); // version made by
+        central.extend_from_slice(&20u16.to_le_bytes());
+        central.extend_from_slice(&0x0800u16.to_le_bytes());
+        central.extend_from_slice(&0u16.to_le_bytes());
+        central.extend_from_slice(&0u16.to_le_bytes());
+        central.extend_from_slice(&0x0021u16.to_le_bytes());
+        central.extend_from_slice(&crc.to_le_bytes());
+        central.extend_from_slice(&size.to_le_bytes());
+        central.extend_from_slice(&size.to_le_bytes());
+        central.extend_from_slice(&name_len.to_le_bytes());
+        central.extend_from_slice(&[0; 12]); // extra, comment, disk, attributes
+        central.extend_from_slice(&offset.to_le_bytes());
+        central.extend_from_slice(name.as_bytes());
+    }
+
+    let central_offset = out.len() as u32;
+    let central_size = central.len() as u32;
+    let count = entries.len() as u16;
+    out.extend_from_slice(&central);
+    out.extend_from_slice(&0x0605_4b50u32.to_le_bytes());
+    out.extend_from_slice(&[0; 4]); // disk numbers
+    out.extend_from_slice(&count.to_le_bytes());
+    out.extend_from_slice(&count.to_le_bytes());
+    out.extend_from_slice(&central_size.to_le_bytes());
+    out.extend_from_slice(&central_offset.to_le_bytes());
+    out.extend_from_slice(&0u16.to_le_bytes());
+    out
+}
+
+/// OpenDocument spreadsheet with a summary sheet followed by one sheet per
+/// teacher. Totals are plain values, not formulas.
+pub fn service_report_ods(report: &ServiceReport, options: &SpreadsheetOptions) -> Vec<u8> {
+    let content = ods_content(report, options);
+    zip_store(&[
+        ("mimetype", ODS_MIMETYPE.as_bytes()),
+        ("META-INF/manifest.xml", ODS_MANIFEST.as_bytes()),
+        ("content.xml", content.as_bytes()),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::normalizer::test_support::event;
+    use crate::service::{build_service_report, ServiceReportOptions};
+
+    fn report() -> ServiceReport {
+        let events = vec![
+            event(
+                "CM Algo",
+                "DUPONT Jean",
+                "20250106T080000",
+                "20250106T100000",
+            ),
+            event(
+                "TD Algo",
+                "DUPONT Jean",
+                "20250107T080000",
+                "20250107T093000",
+            ),
+            event(
+                "TP Réseaux; Wi-Fi",
+                "MARTIN Paul",
+                "20250108T080000",
+                "20250108T120000",
+            ),
+        ];
+        build_service_report(&events, &ServiceReportOptions::default()).unwrap()
+    }
+
+    #[test]
+    fn csv_uses_delimiter_and_decimal_comma() {
+        let options = SpreadsheetOptions {
+            delimiter: ';',
+            decimal_comma: true,
+            byte_order_mark: false,
+            ..SpreadsheetOptions::default()
+        };
+        let csv = service_report_csv(&report(), &options);
+        let lines: Vec<&str> = csv.split("\r\n").collect();
+
+        assert_eq!(
+            lines[0],
+            "Teacher;Subject;CM;TD;TP;Project;Meeting;Exam;Other;Total;Sessions"
+        );
+        assert!(lines.contains(&"DUPONT Jean;Algo;2,00;1,50;0,00;0,00;0,00;0,00;0,00;3,50;2"));
+        assert!(lines.contains(&"DUPONT Jean;Total;2,00;1,50;0,00;0,00;0,00;0,00;0,00;3,50;2"));
+        assert!(lines
+            .iter()
+            .any(|line| line.starts_with("MARTIN Paul;\"Réseaux; Wi-Fi\";")));
+        assert!(lines.contains(&"Total;;2,00;1,50;4,00;0,00;0,00;0,00;0,00;7,50;3"));
+
+        let with_bom = service_report_csv(&report(), &SpreadsheetOptions::default());
+        assert!(with_bom.starts_with('\u{feff}'));
+        assert!(with_bom.contains("DUPONT Jean,Algo,2.00,1.50,"));
+    }
+
+    #[test]
+    fn csv_fields_never_start_a_formula() {
+        assert_eq!(
+            csv_field("=HYPERLINK(\"x\")", ','),
+            "\"'=HYPERLINK(\"\"x\"\")\""
+        );
+        assert_eq!(csv_field("+33 6", ','), "'+33 6");
+        assert_eq!(csv_field("-1,5", ';'), "'-1,5");
+        assert_eq!(csv_field("@SUM(A1)", ';'), "'@SUM(A1)");
+        assert_eq!(csv_field("Wi-Fi", ','), "Wi-Fi");
+
+        let mut report = report();
+        report.teachers[0].teacher = "=cmd|' /C calc'!A0".to_string();
+        let csv = service_report_csv(&report, &SpreadsheetOptions::default());
+        assert!(csv.contains("\r\n'=cmd|' /C calc'!A0,"));
+    }
+
+    #[test]
+    fn ods_is_a_stored_zip_with_one_sheet_per_teacher() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+
+        let options = SpreadsheetOptions {
+            decimal_comma: true,
+            ..SpreadsheetOptions::default()
+        };
+        let bytes = service_report_ods(&report(), &options);
+
+        assert_eq!(&bytes[..4], b"PK\x03\x04");
+        assert_eq!(&bytes[30..38], b"mimetype");
+        assert_eq!(&bytes[38..38 + ODS_MIMETYPE.len()], ODS_MIMETYPE.as_bytes());
+        assert_eq!(&bytes[bytes.len() - 22..bytes.len() - 18], b"PK\x05\x06");
+
+        let content = ods_content(&report(), &options);
+        let sheets: Vec<&str> = content
+            .split("<table:table table:name=\"")
+            .skip(1)
+            .map(|chunk| chunk.split('"').next().unwrap())
+            .collect();
+        assert_eq!(sheets, vec!["Summary", "MARTIN Paul", "DUPONT Jean"]);
+        assert!(content
+            .contains("office:value-type=\"float\" office:value=\"3.5\"><text:p>3,50</text:p>"));
+        assert!(!content.contains("table:formula"));
+    }
+
+    #[test]
+    fn sheet_names_are_sanitized_and_unique() {
+        let mut taken = Vec::new();
+        assert_eq!(sheet_name("A/B: test", &mut taken), "A_B_ test");
+        assert_eq!(sheet_name("a/b: TEST", &mut taken), "a_b_ TEST (2)");
+        assert_eq!(sheet_name(&"x".repeat(40), &mut taken).chars().count(), 31);
+    }
+}
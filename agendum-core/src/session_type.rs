@@ -0,0 +1,107 @@
+use serde::{Deserialize, Serialize};
+
+/// Service category of a session, derived from the normalizer's `type_`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SessionType {
+    #[serde(rename = "CM")]
+    Cm,
+    #[serde(rename = "TD")]
+    Td,
+    #[serde(rename = "TP")]
+    Tp,
+    #[serde(rename = "PROJET")]
+    Project,
+    #[serde(rename = "REUNION")]
+    Reunion,
+    #[serde(rename = "EXAM")]
+    Exam,
+    #[serde(rename = "AUTRE")]
+    Other,
+}
+
+impl SessionType {
+    pub const ALL: [SessionType; 7] = [
+        SessionType::Cm,
+        SessionType::Td,
+        SessionType::Tp,
+        SessionType::Project,
+        SessionType::Reunion,
+        SessionType::Exam,
+        SessionType::Other,
+    ];
+
+    /// Classifies a type token such as `"TD"`, `"tp3"` or `"Réunion"`.
+    ///
+    /// Matching is done on the whole token minus a group suffix as the
+    /// normalizer accepts it (`TP3`, `TP3.1`, `TP3A`, `TDB`), so a subject that
+    /// merely contains "CM" is not mistaken for a lecture.
+    pub fn from_type(type_: &str) -> Self {
+        fn strip_number(token: &str) -> &str {
+            token.trim_end_matches(|ch: char| {
+                ch.is_ascii_digit() || ch == '.' || ch == '-' || ch.is_whitespace()
+            })
+        }
+        let upper = type_.trim().to_uppercase();
+        let token = strip_number(&upper);
+        match Self::from_token(token) {
+            SessionType::Other => token
+                .strip_suffix(|ch: char| ('A'..='G').contains(&ch))
+                .map_or(SessionType::Other, |rest| {
+                    Self::from_token(strip_number(rest))
+                }),
+            session_type => session_type,
+        }
+    }
+
+    fn from_token(token: &str) -> Self {
+        match token {
+            "CM" => SessionType::Cm,
+            "TD" => SessionType::Td,
+            "TP" => SessionType::Tp,
+            "PROJET" | "PROJECT" => SessionType::Project,
+            "RÉUNION" | "REUNION" => SessionType::Reunion,
+            "EXAM" | "EXAMEN" | "DS" | "CT" | "CC" => SessionType::Exam,
+            _ => SessionType::Other,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SessionType::Cm => "CM",
+            SessionType::Td => "TD",
+            SessionType::Tp => "TP",
+            SessionType::Project => "PROJET",
+            SessionType::Reunion => "REUNION",
+            SessionType::Exam => "EXAM",
+            SessionType::Other => "AUTRE",
+        }
+    }
+
+    /// CM, TD and TP: the types that make up the teaching core of a service.
+    pub fn is_core(self) -> bool {
+        matches!(self, SessionType::Cm | SessionType::Td | SessionType::Tp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_normalizer_types_by_whole_token() {
+        assert_eq!(SessionType::from_type("CM"), SessionType::Cm);
+        assert_eq!(SessionType::from_type("td"), SessionType::Td);
+        assert_eq!(SessionType::from_type("TP3"), SessionType::Tp);
+        assert_eq!(SessionType::from_type("TP 3.1"), SessionType::Tp);
+        assert_eq!(SessionType::from_type("TP3A"), SessionType::Tp);
+        assert_eq!(SessionType::from_type("td-2b"), SessionType::Td);
+        assert_eq!(SessionType::from_type("TDB"), SessionType::Td);
+        assert_eq!(SessionType::from_type("RÉUNION"), SessionType::Reunion);
+        assert_eq!(SessionType::from_type("CC"), SessionType::Exam);
+        assert_eq!(SessionType::from_type("PROJET"), SessionType::Project);
+        assert_eq!(SessionType::from_type("AUTRE"), SessionType::Other);
+        assert_eq!(SessionType::from_type("ACME"), SessionType::Other);
+        assert_eq!(SessionType::from_type("TDM"), SessionType::Other);
+        assert_eq!(SessionType::from_type("TP3AB"), SessionType::Other);
+    }
+}
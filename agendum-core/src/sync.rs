@@ -0,0 +1,532 @@
+//! Refresh of remote calendars as an I/O-free state machine, see
+//! [`SourceState::begin`] and [`SourceState::complete`].
+
+use crate::diff::{diff_snapshots_with, SnapshotDiff};
+use crate::identity::{fnv1a, IdentityConfig};
+use crate::normalizer::{normalize, NormalizedEvent};
+use crate::parser::{parse_ics_content_with_diagnostics, ParseDiagnostics};
+use serde::{Deserialize, Serialize};
+
+const MINUTE_MS: i64 = 60 * 1000;
+const HOUR_MS: i64 = 60 * MINUTE_MS;
+
+/// Refresh limits; the defaults are those of the web app.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct SyncPolicy {
+    /// Automatic refreshes happen this long after the last attempt.
+    pub auto_refresh_ms: i64,
+    /// Minimum delay between two manual refreshes.
+    pub manual_cooldown_ms: i64,
+    pub manual_window_ms: i64,
+    /// Manual refreshes allowed within `manual_window_ms`.
+    pub manual_max_per_window: usize,
+    /// Delay before the first automatic retry after a failure, doubled for
+    /// each further failure and capped at `auto_refresh_ms`.
+    pub backoff_initial_ms: i64,
+}
+
+impl Default for SyncPolicy {
+    fn default() -> Self {
+        SyncPolicy {
+            auto_refresh_ms: 24 * HOUR_MS,
+            manual_cooldown_ms: MINUTE_MS,
+            manual_window_ms: HOUR_MS,
+            manual_max_per_window: 20,
+            backoff_initial_ms: 5 * MINUTE_MS,
+        }
+    }
+}
+
+impl SyncPolicy {
+    /// Automatic retry delay after `failures` consecutive failures.
+    pub fn backoff_ms(&self, failures: u32) -> i64 {
+        let doublings = failures.saturating_sub(1).min(30);
+        self.backoff_initial_ms
+            .saturating_mul(1 << doublings)
+            .min(self.auto_refresh_ms)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Trigger {
+    Auto,
+    Manual,
+}
+
+/// Persisted state of one remote calendar. Times are epoch milliseconds.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+#[serde(default)]
+pub struct SourceState {
+    pub url: String,
+    pub last_success_ms: Option<i64>,
+    pub last_attempt_ms: Option<i64>,
+    pub last_manual_ms: Option<i64>,
+    /// Manual refreshes within the policy window, oldest first.
+    pub manual_history: Vec<i64>,
+    pub consecutive_failures: u32,
+    pub last_error: Option<String>,
+    /// Hash of the last body that parsed, to skip parsing identical copies.
+    pub content_hash: Option<String>,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum WaitReason {
+    /// The last copy is recent enough.
+    Fresh,
+    /// Retrying too soon after a failure.
+    Backoff,
+    Cooldown,
+    /// Too many manual refreshes within the window.
+    ManualLimit,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum Decision {
+    Fetch,
+    Wait { reason: WaitReason, until_ms: i64 },
+}
+
+/// Conditional GET for the source, to be sent by the transport.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct FetchRequest {
+    pub url: String,
+    pub if_none_match: Option<String>,
+    pub if_modified_since: Option<String>,
+}
+
+/// What the transport got back.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum FetchOutcome {
+    NotModified,
+    Body {
+        text: String,
+        #[serde(default)]
+        etag: Option<String>,
+        #[serde(default)]
+        last_modified: Option<String>,
+    },
+    Failed {
+        message: String,
+    },
+}
+
+/// A way of performing [`FetchRequest`]s synchronously.
+pub trait Transport {
+    fn fetch(&mut self, request: &FetchRequest) -> FetchOutcome;
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncStatus {
+    /// The server answered 304.
+    NotModified,
+    /// Same body as the last successful copy; nothing was parsed.
+    Unchanged,
+    Updated,
+    Failed,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SyncReport {
+    pub status: SyncStatus,
+    /// New events, only when `status` is `updated`.
+    pub events: Option<Vec<NormalizedEvent>>,
+    pub diagnostics: Option<ParseDiagnostics>,
+    /// Changes from the previous events, only when `status` is `updated`.
+    pub diff: Option<SnapshotDiff>,
+    pub error: Option<String>,
+    /// When the next automatic refresh is due.
+    pub next_refresh_ms: i64,
+}
+
+impl SourceState {
+    pub fn new(url: &str) -> SourceState {
+        SourceState {
+            url: url.to_string(),
+            ..SourceState::default()
+        }
+    }
+
+    pub fn decide(&self, trigger: Trigger, policy: &SyncPolicy, now_ms: i64) -> Decision {
+        let wait = |reason, until_ms: i64| {
+            if until_ms > now_ms {
+                Decision::Wait { reason, until_ms }
+            } else {
+                Decision::Fetch
+            }
+        };
+        match trigger {
+            Trigger::Auto => {
+                if self.consecutive_failures > 0 {
+                    let last = self.last_attempt_ms.unwrap_or(now_ms);
+                    return wait(
+                        WaitReason::Backoff,
+                        last + policy.backoff_ms(self.consecutive_failures),
+                    );
+                }
+                match self.last_attempt_ms.or(self.last_success_ms) {
+                    Some(last) => wait(WaitReason::Fresh, last + policy.auto_refresh_ms),
+                    None => Decision::Fetch,
+                }
+            }
+            Trigger::Manual => {
+                let cooldown = self
+                    .last_manual_ms
+                    .map_or(now_ms, |last| last + policy.manual_cooldown_ms);
+                if cooldown > now_ms {
+                    return wait(WaitReason::Cooldown, cooldown);
+                }
+                let recent: Vec<i64> = self.recent_manual(policy, now_ms).collect();
+                if policy.manual_max_per_window > 0 && recent.len() >= policy.manual_max_per_window
+                {
+                    let oldest = recent[recent.len() - policy.manual_max_per_window];
+                    return wait(WaitReason::ManualLimit, oldest + policy.manual_window_ms);
+                }
+                Decision::Fetch
+            }
+        }
+    }
+
+    fn recent_manual<'a>(
+        &'a self,
+        policy: &SyncPolicy,
+        now_ms: i64,
+    ) -> impl Iterator<Item = i64> + 'a {
+        let since = now_ms - policy.manual_window_ms;
+        self.manual_history
+            .iter()
+            .copied()
+            .filter(move |at| *at > since && *at <= now_ms)
+    }
+
+    /// Records the attempt and returns the request to send, or the decision
+    /// to wait.
+    pub fn begin(
+        &mut self,
+        trigger: Trigger,
+        policy: &SyncPolicy,
+        now_ms: i64,
+    ) -> Result<FetchRequest, Decision> {
+        let decision = self.decide(trigger, policy, now_ms);
+        if decision != Decision::Fetch {
+            return Err(decision);
+        }
+        self.last_attempt_ms = Some(now_ms);
+        if trigger == Trigger::Manual {
+            self.manual_history = self.recent_manual(policy, now_ms).collect();
+            self.manual_history.push(now_ms);
+            self.last_manual_ms = Some(now_ms);
+        }
+        Ok(FetchRequest {
+            url: self.url.clone(),
+            if_none_match: self.etag.clone(),
+            if_modified_since: self.last_modified.clone(),
+        })
+    }
+
+    /// Applies the outcome of the request returned by [`SourceState::begin`].
+    ///
+    /// `previous` are the events of the last successful copy; a body that
+    /// yields no event and parser errors counts as a failure, so a broken
+    /// download never replaces them.
+    pub fn complete(
+        &mut self,
+        outcome: FetchOutcome,
+        previous: &[NormalizedEvent],
+        identity: &IdentityConfig,
+        policy: &SyncPolicy,
+        now_ms: i64,
+    ) -> SyncReport {
+        let mut report = SyncReport {
+            status: SyncStatus::NotModified,
+            events: None,
+            diagnostics: None,
+            diff: None,
+            error: None,
+            next_refresh_ms: 0,
+        };
+        match outcome {
+            FetchOutcome::NotModified => {}
+            FetchOutcome::Failed { message } => report.error = Some(message),
+            FetchOutcome::Body {
+                text,
+                etag,
+                last_modified,
+            } => {
+                let hash = format!("{:016x}", fnv1a(&text));
+                if self.content_hash.as_deref() == Some(hash.as_str()) {
+                    report.status = SyncStatus::Unchanged;
+                } else {
+                    let parsed = parse_ics_content_with_diagnostics(&text);
+                    if parsed.events.is_empty() && parsed.diagnostics.parser_errors > 0 {
+                        let detail = parsed
+                            .diagnostics
+                            .parser_error_messages
+                            .first()
+                            .cloned()
+                            .unwrap_or_default();
+                        report.error = Some(format!("Calendar could not be parsed: {detail}"));
+                    } else {
+                        let events = normalize(parsed.events);
+                        report.status = SyncStatus::Updated;
+                        report.diff = Some(diff_snapshots_with(previous, &events, identity));
+                        report.events = Some(events);
+                        self.content_hash = Some(hash);
+                    }
+                    report.diagnostics = Some(parsed.diagnostics);
+                }
+                if report.error.is_none() {
+                    self.etag = etag;
+                    self.last_modified = last_modified;
+                }
+            }
+        }
+
+        if report.error.is_some() {
+            report.status = SyncStatus::Failed;
+            self.consecutive_failures = self.consecutive_failures.saturating_add(1);
+            self.last_error = report.error.clone();
+        } else {
+            self.last_success_ms = Some(now_ms);
+            self.consecutive_failures = 0;
+            self.last_error = None;
+        }
+        report.next_refresh_ms = match self.decide(Trigger::Auto, policy, now_ms) {
+            Decision::Fetch => now_ms,
+            Decision::Wait { until_ms, .. } => until_ms,
+        };
+        report
+    }
+}
+
+/// Runs one refresh of `state` over a synchronous transport. Returns the
+/// decision to wait when the policy does not allow fetching yet.
+pub fn sync_source<T: Transport + ?Sized>(
+    state: &mut SourceState,
+    trigger: Trigger,
+    previous: &[NormalizedEvent],
+    transport: &mut T,
+    identity: &IdentityConfig,
+    policy: &SyncPolicy,
+    now_ms: i64,
+) -> Result<SyncReport, Decision> {
+    let request = state.begin(trigger, policy, now_ms)?;
+    let outcome = transport.fetch(&request);
+    Ok(state.complete(outcome, previous, identity, policy, now_ms))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const URL: &str = "https://planning.example/cal.ics";
+
+    fn calendar(summary: &str) -> String {
+        format!(
+            "BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nUID:a\r\nSUMMARY:{summary}\r\n\
+DESCRIPTION:M1 INFO\\nDUPONT Jean\r\nDTSTART:20250106T080000\r\n\
+DTEND:20250106T100000\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n"
+        )
+    }
+
+    /// Replies with queued outcomes and records the requests.
+    struct Scripted {
+        outcomes: Vec<FetchOutcome>,
+        requests: Vec<FetchRequest>,
+    }
+
+    impl Transport for Scripted {
+        fn fetch(&mut self, request: &FetchRequest) -> FetchOutcome {
+            self.requests.push(request.clone());
+            self.outcomes.remove(0)
+        }
+    }
+
+    fn body(text: String, etag: &str) -> FetchOutcome {
+        FetchOutcome::Body {
+            text,
+            etag: Some(etag.to_string()),
+            last_modified: None,
+        }
+    }
+
+    #[test]
+    fn refreshes_daily_and_backs_off_after_failures() {
+        let policy = SyncPolicy::default();
+        let mut state = SourceState::new(URL);
+        assert_eq!(state.decide(Trigger::Auto, &policy, 0), Decision::Fetch);
+
+        let identity = IdentityConfig::default();
+        let mut failing = Scripted {
+            outcomes: vec![
+                FetchOutcome::Failed {
+                    message: "timeout".to_string(),
+                },
+                FetchOutcome::Failed {
+                    message: "timeout".to_string(),
+                },
+            ],
+            requests: Vec::new(),
+        };
+        let first = sync_source(
+            &mut state,
+            Trigger::Auto,
+            &[],
+            &mut failing,
+            &identity,
+            &policy,
+            0,
+        )
+        .unwrap();
+        assert_eq!(first.status, SyncStatus::Failed);
+        assert_eq!(first.next_refresh_ms, 5 * MINUTE_MS);
+        assert_eq!(
+            state.decide(Trigger::Auto, &policy, MINUTE_MS),
+            Decision::Wait {
+                reason: WaitReason::Backoff,
+                until_ms: 5 * MINUTE_MS
+            }
+        );
+        let second = sync_source(
+            &mut state,
+            Trigger::Auto,
+            &[],
+            &mut failing,
+            &identity,
+            &policy,
+            5 * MINUTE_MS,
+        )
+        .unwrap();
+        assert_eq!(second.next_refresh_ms, 15 * MINUTE_MS);
+        assert_eq!(state.last_error.as_deref(), Some("timeout"));
+        assert_eq!(policy.backoff_ms(20), policy.auto_refresh_ms);
+
+        let mut ok = Scripted {
+            outcomes: vec![body(calendar("CM Algo"), "\"v1\"")],
+            requests: Vec::new(),
+        };
+        let report = sync_source(
+            &mut state,
+            Trigger::Auto,
+            &[],
+            &mut ok,
+            &identity,
+            &policy,
+            15 * MINUTE_MS,
+        )
+        .unwrap();
+        assert_eq!(report.status, SyncStatus::Updated);
+        assert_eq!(state.consecutive_failures, 0);
+        assert_eq!(state.last_error, None);
+        assert_eq!(
+            report.next_refresh_ms,
+            15 * MINUTE_MS + policy.auto_refresh_ms
+        );
+        assert!(matches!(
+            state.decide(Trigger::Auto, &policy, HOUR_MS),
+            Decision::Wait {
+                reason: WaitReason::Fresh,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn limits_manual_refreshes() {
+        let policy = SyncPolicy {
+            manual_max_per_window: 3,
+            ..SyncPolicy::default()
+        };
+        let mut state = SourceState::new(URL);
+        for minute in 0..3 {
+            state
+                .begin(Trigger::Manual, &policy, minute * MINUTE_MS)
+                .unwrap();
+        }
+        assert_eq!(
+            state.begin(Trigger::Manual, &policy, 3 * MINUTE_MS + 1_000),
+            Err(Decision::Wait {
+                reason: WaitReason::ManualLimit,
+                until_ms: HOUR_MS
+            })
+        );
+        assert_eq!(
+            state.decide(Trigger::Manual, &policy, HOUR_MS),
+            Decision::Fetch
+        );
+
+        state.begin(Trigger::Manual, &policy, HOUR_MS).unwrap();
+        assert_eq!(
+            state.decide(Trigger::Manual, &policy, HOUR_MS + 10_000),
+            Decision::Wait {
+                reason: WaitReason::Cooldown,
+                until_ms: HOUR_MS + MINUTE_MS
+            }
+        );
+        assert_eq!(
+            state.manual_history,
+            vec![MINUTE_MS, 2 * MINUTE_MS, HOUR_MS]
+        );
+    }
+
+    #[test]
+    fn skips_identical_bodies_and_diffs_changes() {
+        let policy = SyncPolicy::default();
+        let identity = IdentityConfig::default();
+        let mut state = SourceState::new(URL);
+        let mut transport = Scripted {
+            outcomes: vec![
+                body(calendar("CM Algo"), "\"v1\""),
+                FetchOutcome::NotModified,
+                body(calendar("CM Algo"), "\"v2\""),
+                body(calendar("TD Algo"), "\"v3\""),
+                body("BEGIN:VCALENDAR\r\nBROKEN".to_string(), "\"v4\""),
+            ],
+            requests: Vec::new(),
+        };
+        let mut run = |state: &mut SourceState, previous: &[NormalizedEvent], day: i64| {
+            sync_source(
+                state,
+                Trigger::Auto,
+                previous,
+                &mut transport,
+                &identity,
+                &policy,
+                day * policy.auto_refresh_ms,
+            )
+            .unwrap()
+        };
+
+        let first = run(&mut state, &[], 0);
+        let events = first.events.unwrap();
+        assert_eq!(first.diff.unwrap().summary.added, 1);
+        assert_eq!(run(&mut state, &events, 1).status, SyncStatus::NotModified);
+        let same = run(&mut state, &events, 2);
+        assert_eq!(same.status, SyncStatus::Unchanged);
+        assert!(same.diagnostics.is_none());
+        assert_eq!(state.etag.as_deref(), Some("\"v2\""));
+
+        let changed = run(&mut state, &events, 3);
+        assert_eq!(changed.status, SyncStatus::Updated);
+        let diff = changed.diff.unwrap();
+        assert_eq!(diff.summary.added + diff.summary.removed, 0);
+        assert_eq!(diff.changed.len(), 1);
+
+        let hash = state.content_hash.clone();
+        let broken = run(&mut state, &events, 4);
+        assert_eq!(broken.status, SyncStatus::Failed);
+        assert_eq!(state.content_hash, hash);
+        assert_eq!(state.etag.as_deref(), Some("\"v3\""));
+        assert_eq!(
+            transport.requests[1].if_none_match.as_deref(),
+            Some("\"v1\"")
+        );
+    }
+}
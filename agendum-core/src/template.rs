@@ -0,0 +1,656 @@
+//! Small text template engine for institution-specific documents.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    Text(String),
+    List(Vec<Value>),
+    Map(BTreeMap<String, Value>),
+}
+
+impl Value {
+    fn truthy(&self) -> bool {
+        match self {
+            Value::Null => false,
+            Value::Bool(value) => *value,
+            Value::Number(value) => *value != 0.0,
+            Value::Text(value) => !value.is_empty(),
+            Value::List(items) => !items.is_empty(),
+            Value::Map(entries) => !entries.is_empty(),
+        }
+    }
+
+    fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Map(entries) => entries.get(key),
+            Value::List(items) => key.parse::<usize>().ok().and_then(|i| items.get(i)),
+            _ => None,
+        }
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::Text(value.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Value::Text(value)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Self {
+        Value::Number(value)
+    }
+}
+
+impl From<i64> for Value {
+    fn from(value: i64) -> Self {
+        Value::Number(value as f64)
+    }
+}
+
+impl From<u32> for Value {
+    fn from(value: u32) -> Self {
+        Value::Number(f64::from(value))
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Value::Bool(value)
+    }
+}
+
+impl<T: Into<Value>> From<Vec<T>> for Value {
+    fn from(items: Vec<T>) -> Self {
+        Value::List(items.into_iter().map(Into::into).collect())
+    }
+}
+
+/// Builds a [`Value::Map`] from `key => value` pairs.
+#[macro_export]
+macro_rules! template_map {
+    ($($key:expr => $value:expr),* $(,)?) => {{
+        let mut entries = ::std::collections::BTreeMap::new();
+        $(entries.insert(($key).to_string(), $crate::template::Value::from($value));)*
+        $crate::template::Value::Map(entries)
+    }};
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputFormat {
+    #[default]
+    Html,
+    Markdown,
+    /// No escaping.
+    Text,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct NumberFormat {
+    pub decimals: usize,
+    pub decimal_separator: String,
+    /// Inserted between groups of three digits, e.g. `" "` for `1 234,50`.
+    pub thousands_separator: String,
+}
+
+impl Default for NumberFormat {
+    fn default() -> Self {
+        NumberFormat {
+            decimals: 2,
+            decimal_separator: ".".to_string(),
+            thousands_separator: String::new(),
+        }
+    }
+}
+
+impl NumberFormat {
+    pub fn format(&self, value: f64, decimals: usize) -> String {
+        let text = format!("{:.*}", decimals, value.abs());
+        let (integer, fraction) = text.split_once('.').unwrap_or((&text, ""));
+        let mut grouped = String::new();
+        for (position, digit) in integer.chars().enumerate() {
+            if position > 0 && (integer.len() - position) % 3 == 0 {
+                grouped.push_str(&self.thousands_separator);
+            }
+            grouped.push(digit);
+        }
+        // Avoid "-0.00" for tiny negative values.
+        let negative = value < 0.0 && text.chars().any(|ch| ch.is_ascii_digit() && ch != '0');
+        let sign = if negative { "-" } else { "" };
+        if fraction.is_empty() {
+            format!("{sign}{grouped}")
+        } else {
+            format!("{sign}{grouped}{}{fraction}", self.decimal_separator)
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct TemplateError {
+    pub message: String,
+    /// 1-based line of the offending tag.
+    pub line: usize,
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (line {})", self.message, self.line)
+    }
+}
+
+impl std::error::Error for TemplateError {}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Filter {
+    name: String,
+    arg: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Expression {
+    path: Vec<String>,
+    filters: Vec<Filter>,
+    line: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Text(String),
+    Print(Expression),
+    For {
+        name: String,
+        list: Expression,
+        body: Vec<Node>,
+    },
+    If {
+        negated: bool,
+        condition: Expression,
+        then: Vec<Node>,
+        otherwise: Vec<Node>,
+    },
+}
+
+enum Token {
+    Text(String),
+    Print(String, usize),
+    Tag(String, usize),
+}
+
+const FILTERS: [&str; 8] = [
+    "hours", "number", "int", "upper", "lower", "join", "default", "raw",
+];
+
+fn tokenize(source: &str) -> Result<Vec<Token>, TemplateError> {
+    let mut tokens = Vec::new();
+    let mut rest = source;
+    let mut line = 1;
+    // Whether the next token starts a fresh output line.
+    let mut at_line_start = true;
+    loop {
+        let next = [rest.find("{{"), rest.find("{%")]
+            .into_iter()
+            .flatten()
+            .min();
+        let Some(open) = next else {
+            if !rest.is_empty() {
+                tokens.push(Token::Text(rest.to_string()));
+            }
+            return Ok(tokens);
+        };
+        let text = &rest[..open];
+        let is_tag = rest[open..].starts_with("{%");
+        let close_marker = if is_tag { "%}" } else { "}}" };
+        let Some(close) = rest[open + 2..].find(close_marker) else {
+            return Err(TemplateError {
+                message: format!("unclosed `{}`", &rest[open..open + 2]),
+                line: line + text.matches('\n').count(),
+            });
+        };
+
+        // A tag alone on its line swallows that line's indentation and newline.
+        let mut text = text.to_string();
+        let mut after = open + 2 + close + 2;
+        if is_tag {
+            let line_start = text.rfind('\n').map_or(0, |i| i + 1);
+            let before_is_blank =
+                text[line_start..].trim().is_empty() && (line_start > 0 || at_line_start);
+            let trailing = &rest[after..];
+            let line_end = trailing.find('\n');
+            let after_is_blank = trailing[..line_end.unwrap_or(trailing.len())]
+                .trim()
+                .is_empty();
+            if before_is_blank && after_is_blank {
+                text.truncate(line_start);
+                after += line_end.map_or(trailing.len(), |i| i + 1);
+                at_line_start = true;
+            } else {
+                at_line_start = false;
+            }
+        } else {
+            at_line_start = false;
+        }
+
+        line += rest[..open].matches('\n').count();
+        if !text.is_empty() {
+            tokens.push(Token::Text(text));
+        }
+        let inner = rest[open + 2..open + 2 + close].trim().to_string();
+        let tag_line = line;
+        line += rest[open..after].matches('\n').count();
+        tokens.push(if is_tag {
+            Token::Tag(inner, tag_line)
+        } else {
+            Token::Print(inner, tag_line)
+        });
+        rest = &rest[after..];
+    }
+}
+
+fn parse_expression(source: &str, line: usize) -> Result<Expression, TemplateError> {
+    let error = |message: String| TemplateError { message, line };
+    let mut parts = source.split('|').map(str::trim);
+    let path_text = parts.next().unwrap_or_default();
+    if path_text.is_empty() {
+        return Err(error("empty expression".to_string()));
+    }
+    let path: Vec<String> = path_text.split('.').map(str::to_string).collect();
+    if path
+        .iter()
+        .any(|part| part.is_empty() || !part.chars().all(|ch| ch.is_alphanumeric() || ch == '_'))
+    {
+        return Err(error(format!("invalid variable `{path_text}`")));
+    }
+    let filters = parts
+        .map(|filter| {
+            let (name, arg) = match filter.split_once(':') {
+                Some((name, arg)) => (name.trim(), Some(arg.trim().trim_matches('"').to_string())),
+                None => (filter, None),
+            };
+            if FILTERS.contains(&name) {
+                Ok(Filter {
+                    name: name.to_string(),
+                    arg,
+                })
+            } else {
+                Err(error(format!("unknown filter `{name}`")))
+            }
+        })
+        .collect::<Result<_, _>>()?;
+    Ok(Expression {
+        path,
+        filters,
+        line,
+    })
+}
+
+/// What closed a block: the closing tag, or `else` for an `if`.
+enum Closing {
+    End,
+    Else,
+    Eof,
+}
+
+fn parse_nodes(
+    tokens: &mut std::iter::Peekable<std::vec::IntoIter<Token>>,
+    block: Option<(&str, usize)>,
+) -> Result<(Vec<Node>, Closing), TemplateError> {
+    let mut nodes = Vec::new();
+    while let Some(token) = tokens.next() {
+        match token {
+            Token::Text(text) => nodes.push(Node::Text(text)),
+            Token::Print(source, line) => nodes.push(Node::Print(parse_expression(&source, line)?)),
+            Token::Tag(source, line) => {
+                let words: Vec<&str> = source.split_whitespace().collect();
+                match words.as_slice() {
+                    ["for", name, "in", rest @ ..] if !rest.is_empty() => {
+                        let list = parse_expression(&rest.join(" "), line)?;
+                        let (body, closing) = parse_nodes(tokens, Some(("for", line)))?;
+                        if matches!(closing, Closing::Else) {
+                            return Err(TemplateError {
+                                message: "`else` inside `for`".to_string(),
+                                line,
+                            });
+                        }
+                        nodes.push(Node::For {
+                            name: name.to_string(),
+                            list,
+                            body,
+                        });
+                    }
+                    ["if", rest @ ..] if !rest.is_empty() => {
+                        let (negated, rest) = match rest {
+                            ["not", rest @ ..] => (true, rest),
+                            _ => (false, rest),
+                        };
+                        let condition = parse_expression(&rest.join(" "), line)?;
+                        let (then, closing) = parse_nodes(tokens, Some(("if", line)))?;
+                        let otherwise = match closing {
+                            Closing::Else => parse_nodes(tokens, Some(("else", line)))?.0,
+                            _ => Vec::new(),
+                        };
+                        nodes.push(Node::If {
+                            negated,
+                            condition,
+                            then,
+                            otherwise,
+                        });
+                    }
+                    ["endfor"] if block.is_some_and(|(name, _)| name == "for") => {
+                        return Ok((nodes, Closing::End));
+                    }
+                    ["endif"] if block.is_some_and(|(name, _)| name == "if" || name == "else") => {
+                        return Ok((nodes, Closing::End));
+                    }
+                    ["else"] if block.is_some_and(|(name, _)| name == "if") => {
+                        return Ok((nodes, Closing::Else));
+                    }
+                    _ => {
+                        return Err(TemplateError {
+                            message: format!("unexpected tag `{source}`"),
+                            line,
+                        })
+                    }
+                }
+            }
+        }
+    }
+    match block {
+        Some((name, line)) => Err(TemplateError {
+            message: format!(
+                "`{}` is never closed",
+                if name == "else" { "if" } else { name }
+            ),
+            line,
+        }),
+        None => Ok((nodes, Closing::Eof)),
+    }
+}
+
+/// A parsed template, reusable across contexts.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    nodes: Vec<Node>,
+}
+
+struct Renderer<'a> {
+    format: OutputFormat,
+    numbers: &'a NumberFormat,
+    scopes: Vec<(String, Value)>,
+    root: &'a Value,
+}
+
+impl Renderer<'_> {
+    fn lookup(&self, expression: &Expression) -> Result<Value, TemplateError> {
+        let first = &expression.path[0];
+        let start = self
+            .scopes
+            .iter()
+            .rev()
+            .find(|(name, _)| name == first)
+            .map(|(_, value)| value)
+            .or_else(|| self.root.get(first));
+        let mut current = start;
+        for part in &expression.path[1..] {
+            current = current.and_then(|value| value.get(part));
+        }
+        Ok(current.cloned().unwrap_or(Value::Null))
+    }
+
+    fn escape(&self, text: &str) -> String {
+        match self.format {
+            OutputFormat::Text => text.to_string(),
+            OutputFormat::Html => text
+                .replace('&', "&amp;")
+                .replace('<', "&lt;")
+                .replace('>', "&gt;")
+                .replace('"', "&quot;")
+                .replace('\'', "&#39;"),
+            OutputFormat::Markdown => {
+                let mut escaped = String::with_capacity(text.len());
+                for ch in text.chars() {
+                    if "\\`*_[]|<>#".contains(ch) {
+                        escaped.push('\\');
+                    }
+                    escaped.push(ch);
+                }
+                escaped
+            }
+        }
+    }
+
+    fn print(&self, expression: &Expression) -> Result<String, TemplateError> {
+        let mut value = self.lookup(expression)?;
+        let mut text: Option<String> = None;
+        let mut raw = false;
+        for filter in &expression.filters {
+            let decimals = || {
+                filter
+                    .arg
+                    .as_deref()
+                    .and_then(|arg| arg.parse().ok())
+                    .unwrap_or(self.numbers.decimals)
+            };
+            let number = |value: &Value| match value {
+                Value::Number(number) => Some(*number),
+                Value::Text(text) => text.trim().parse().ok(),
+                Value::Bool(flag) => Some(f64::from(u8::from(*flag))),
+                _ => None,
+            };
+            let current = text.take().map(Value::Text).unwrap_or(value.clone());
+            let output = match filter.name.as_str() {
+                "hours" => number(&current)
+                    .map(|minutes| self.numbers.format(minutes / 60.0, decimals()))
+                    .unwrap_or_default(),
+                "number" => number(&current)
+                    .map(|n| self.numbers.format(n, decimals()))
+                    .unwrap_or_default(),
+                "int" => number(&current)
+                    .map(|n| self.numbers.format(n, 0))
+                    .unwrap_or_default(),
+                "upper" => plain(&current, self.numbers).to_uppercase(),
+                "lower" => plain(&current, self.numbers).to_lowercase(),
+                "join" => match &current {
+                    Value::List(items) => items
+                        .iter()
+                        .map(|item| plain(item, self.numbers))
+                        .collect::<Vec<_>>()
+                        .join(filter.arg.as_deref().unwrap_or(", ")),
+                    other => plain(other, self.numbers),
+                },
+                "default" if !current.truthy() => filter.arg.clone().unwrap_or_default(),
+                "default" => plain(&current, self.numbers),
+                _ => {
+                    raw = true;
+                    value = current;
+                    continue;
+                }
+            };
+            text = Some(output);
+        }
+        let text = text.unwrap_or_else(|| plain(&value, self.numbers));
+        Ok(if raw { text } else { self.escape(&text) })
+    }
+
+    fn render(&mut self, nodes: &[Node], out: &mut String) -> Result<(), TemplateError> {
+        for node in nodes {
+            match node {
+                Node::Text(text) => out.push_str(text),
+                Node::Print(expression) => out.push_str(&self.print(expression)?),
+                Node::If {
+                    negated,
+                    condition,
+                    then,
+                    otherwise,
+                } => {
+                    let truthy = self.lookup(condition)?.truthy() != *negated;
+                    self.render(if truthy { then } else { otherwise }, out)?;
+                }
+                Node::For { name, list, body } => {
+                    let items = match self.lookup(list)? {
+                        Value::List(items) => items,
+                        Value::Null => Vec::new(),
+                        _ => {
+                            return Err(TemplateError {
+                                message: format!("`{}` is not a list", list.path.join(".")),
+                                line: list.line,
+                            })
+                        }
+                    };
+                    let count = items.len();
+                    for (index, item) in items.into_iter().enumerate() {
+                        self.scopes.push((name.clone(), item));
+                        self.scopes.push((
+                            "loop".to_string(),
+                            crate::template_map! {
+                                "index" => index as i64 + 1,
+                                "first" => index == 0,
+                                "last" => index + 1 == count,
+                            },
+                        ));
+                        let result = self.render(body, out);
+                        self.scopes.truncate(self.scopes.len() - 2);
+                        result?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Display text of a value: numbers use the configured format, lists are
+/// comma-separated.
+fn plain(value: &Value, numbers: &NumberFormat) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::Bool(flag) => flag.to_string(),
+        Value::Number(number) if number.fract() == 0.0 => numbers.format(*number, 0),
+        Value::Number(number) => numbers.format(*number, numbers.decimals),
+        Value::Text(text) => text.clone(),
+        Value::List(items) => items
+            .iter()
+            .map(|item| plain(item, numbers))
+            .collect::<Vec<_>>()
+            .join(", "),
+        Value::Map(_) => String::new(),
+    }
+}
+
+impl Template {
+    /// `{{ path | filter | filter:arg }}` prints a value, escaped unless `raw`
+    /// is used; `{% for item in path %}` loops with `loop.index`, `loop.first`
+    /// and `loop.last`; `{% if path %}`, `{% if not path %}` and `{% else %}`
+    /// test truthiness. A tag alone on its line leaves no empty line.
+    pub fn parse(source: &str) -> Result<Template, TemplateError> {
+        let mut tokens = tokenize(source)?.into_iter().peekable();
+        let (nodes, _) = parse_nodes(&mut tokens, None)?;
+        Ok(Template { nodes })
+    }
+
+    pub fn render(
+        &self,
+        context: &Value,
+        format: OutputFormat,
+        numbers: &NumberFormat,
+    ) -> Result<String, TemplateError> {
+        let mut renderer = Renderer {
+            format,
+            numbers,
+            scopes: Vec::new(),
+            root: context,
+        };
+        let mut out = String::new();
+        renderer.render(&self.nodes, &mut out)?;
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(source: &str, context: &Value, format: OutputFormat) -> String {
+        Template::parse(source)
+            .unwrap()
+            .render(context, format, &NumberFormat::default())
+            .unwrap()
+    }
+
+    #[test]
+    fn loops_conditions_and_standalone_tags() {
+        let context = template_map! {
+            "title" => "Fiche <2025>",
+            "items" => vec![
+                template_map! { "name" => "Algo", "minutes" => 90i64 },
+                template_map! { "name" => "Web", "minutes" => 0i64 },
+            ],
+        };
+        let source = "<h1>{{ title }}</h1>\n<ul>\n{% for item in items %}\n  <li>{{ loop.index }}. {{ item.name | upper }}{% if item.minutes %} {{ item.minutes | hours:1 }}h{% else %} —{% endif %}</li>\n{% endfor %}\n</ul>\n";
+
+        assert_eq!(
+            render(source, &context, OutputFormat::Html),
+            "<h1>Fiche &lt;2025&gt;</h1>\n<ul>\n  <li>1. ALGO 1.5h</li>\n  <li>2. WEB —</li>\n</ul>\n"
+        );
+    }
+
+    #[test]
+    fn formats_numbers_and_escapes_markdown() {
+        let numbers = NumberFormat {
+            decimals: 2,
+            decimal_separator: ",".to_string(),
+            thousands_separator: " ".to_string(),
+        };
+        assert_eq!(numbers.format(1234.5, 2), "1 234,50");
+        assert_eq!(numbers.format(-0.001, 2), "0,00");
+        assert_eq!(numbers.format(-12.0, 0), "-12");
+
+        let context = template_map! {
+            "name" => "a|b_c",
+            "promos" => vec!["M1", "M2"],
+            "empty" => "",
+        };
+        let template = Template::parse(
+            "| {{ name }} | {{ promos | join:\" / \" }} | {{ empty | default:\"n/a\" }} | {{ name | raw }} |",
+        )
+        .unwrap();
+        assert_eq!(
+            template
+                .render(&context, OutputFormat::Markdown, &numbers)
+                .unwrap(),
+            "| a\\|b\\_c | M1 / M2 | n/a | a|b_c |"
+        );
+    }
+
+    #[test]
+    fn reports_errors_with_lines() {
+        let error = Template::parse("ok\n{% for x in items %}\n{{ x }}").unwrap_err();
+        assert_eq!(error.line, 2);
+        assert_eq!(error.message, "`for` is never closed");
+
+        let error = Template::parse("{{ x | bold }}").unwrap_err();
+        assert_eq!(error.message, "unknown filter `bold`");
+
+        let error = Template::parse("a\nb\n{% endif %}").unwrap_err();
+        assert_eq!(error.line, 3);
+
+        let context = template_map! { "x" => 1i64 };
+        let error = Template::parse("{% for y in x %}{% endfor %}")
+            .unwrap()
+            .render(&context, OutputFormat::Text, &NumberFormat::default())
+            .unwrap_err();
+        assert_eq!(error.message, "`x` is not a list");
+    }
+}
@@ -0,0 +1,496 @@
+use crate::accounting::minutes_to_hours;
+use crate::dedup::{deduplicate, DedupConfig, SourcedEvent};
+use crate::hetd::credited_teachers;
+use crate::normalizer::{NormalizedEvent, UNKNOWN};
+use crate::session_type::SessionType;
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, Timelike};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Granularity {
+    Day,
+    #[default]
+    IsoWeek,
+    /// Weeks counted from the week containing the start of the academic year.
+    AcademicWeek,
+    Month,
+    /// 7 × 24 cells, Monday 00h first; sessions are spread over the hours they cover.
+    WeekdayHour,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Dimension {
+    #[default]
+    Total,
+    Teacher,
+    Promo,
+    Subject,
+    Type,
+    Room,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct SeriesOptions {
+    pub granularity: Granularity,
+    pub dimension: Dimension,
+    /// Copies of one session are counted once; `None` keeps every event.
+    pub dedup: Option<DedupConfig>,
+    /// Month and day the academic year starts on, e.g. 9 and 1.
+    pub academic_year_start_month: u32,
+    pub academic_year_start_day: u32,
+    pub cumulative: bool,
+}
+
+impl Default for SeriesOptions {
+    fn default() -> Self {
+        SeriesOptions {
+            granularity: Granularity::IsoWeek,
+            dimension: Dimension::Total,
+            dedup: Some(DedupConfig::default()),
+            academic_year_start_month: 9,
+            academic_year_start_day: 1,
+            cumulative: false,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Series {
+    /// Teacher, promo, subject, type or room; `"total"` without a dimension.
+    pub key: String,
+    /// One value per bucket.
+    pub hours: Vec<f64>,
+    pub sessions: Vec<u32>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cumulative_hours: Vec<f64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct TimeSeries {
+    pub granularity: Granularity,
+    pub dimension: Dimension,
+    /// Bucket labels: `"2025-01-06"`, `"2025-W02"`, `"2024-2025 S19"`,
+    /// `"2025-01"` or `"mon 08"`. Calendar buckets are contiguous, empty ones
+    /// included.
+    pub buckets: Vec<String>,
+    /// Largest total first.
+    pub series: Vec<Series>,
+}
+
+/// Bucket identifiers sort chronologically and can be enumerated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Bucket {
+    Day(NaiveDate),
+    /// Monday of the week.
+    Week(NaiveDate),
+    /// Academic year start, Monday of the week.
+    AcademicWeek(NaiveDate, NaiveDate),
+    Month(i32, u32),
+    Cell(u32, u32),
+}
+
+fn monday(date: NaiveDate) -> NaiveDate {
+    date - Duration::days(i64::from(date.weekday().num_days_from_monday()))
+}
+
+fn academic_year_start(date: NaiveDate, options: &SeriesOptions) -> NaiveDate {
+    let start = |year| {
+        NaiveDate::from_ymd_opt(
+            year,
+            options.academic_year_start_month,
+            options.academic_year_start_day,
+        )
+        .or_else(|| NaiveDate::from_ymd_opt(year, 9, 1))
+        .unwrap_or_default()
+    };
+    let this_year = start(date.year());
+    if date >= this_year {
+        this_year
+    } else {
+        start(date.year() - 1)
+    }
+}
+
+fn bucket_of(date: NaiveDate, options: &SeriesOptions) -> Bucket {
+    match options.granularity {
+        Granularity::Day => Bucket::Day(date),
+        Granularity::IsoWeek => Bucket::Week(monday(date)),
+        Granularity::AcademicWeek => {
+            Bucket::AcademicWeek(academic_year_start(date, options), monday(date))
+        }
+        Granularity::Month => Bucket::Month(date.year(), date.month()),
+        Granularity::WeekdayHour => Bucket::Cell(date.weekday().num_days_from_monday(), 0),
+    }
+}
+
+fn next(bucket: Bucket, options: &SeriesOptions) -> Bucket {
+    match bucket {
+        Bucket::Day(date) => Bucket::Day(date + Duration::days(1)),
+        Bucket::Week(date) => Bucket::Week(date + Duration::days(7)),
+        Bucket::AcademicWeek(year_start, date) => {
+            // A year starting mid-week splits that week into two buckets.
+            let following = academic_year_start(date + Duration::days(6), options);
+            if following != year_start {
+                Bucket::AcademicWeek(following, date)
+            } else {
+                bucket_of(date + Duration::days(7), options)
+            }
+        }
+        Bucket::Month(year, 12) => Bucket::Month(year + 1, 1),
+        Bucket::Month(year, month) => Bucket::Month(year, month + 1),
+        Bucket::Cell(day, 23) => Bucket::Cell(day + 1, 0),
+        Bucket::Cell(day, hour) => Bucket::Cell(day, hour + 1),
+    }
+}
+
+fn label(bucket: Bucket) -> String {
+    const DAYS: [&str; 7] = ["mon", "tue", "wed", "thu", "fri", "sat", "sun"];
+    match bucket {
+        Bucket::Day(date) => date.format("%Y-%m-%d").to_string(),
+        Bucket::Week(date) => {
+            let week = date.iso_week();
+            format!("{}-W{:02}", week.year(), week.week())
+        }
+        Bucket::AcademicWeek(year_start, date) => {
+            let number = (date - monday(year_start)).num_days() / 7 + 1;
+            format!(
+                "{}-{} S{number:02}",
+                year_start.year(),
+                year_start.year() + 1
+            )
+        }
+        Bucket::Month(year, month) => format!("{year}-{month:02}"),
+        Bucket::Cell(day, hour) => format!("{} {hour:02}", DAYS[day as usize % 7]),
+    }
+}
+
+fn dimension_keys(event: &NormalizedEvent, promos: &[String], dimension: Dimension) -> Vec<String> {
+    let or_unknown = |value: &str| {
+        let value = value.trim();
+        if value.is_empty() {
+            UNKNOWN.to_string()
+        } else {
+            value.to_string()
+        }
+    };
+    match dimension {
+        Dimension::Total => vec!["total".to_string()],
+        Dimension::Teacher => credited_teachers(event)
+            .into_iter()
+            .map(str::to_string)
+            .collect(),
+        Dimension::Promo if promos.is_empty() => vec![UNKNOWN.to_string()],
+        Dimension::Promo => promos.iter().map(|p| or_unknown(p)).collect(),
+        Dimension::Subject => vec![or_unknown(if event.subject.trim().is_empty() {
+            &event.raw.summary
+        } else {
+            &event.subject
+        })],
+        Dimension::Type => vec![SessionType::from_type(&event.type_).label().to_string()],
+        Dimension::Room => vec![or_unknown(&event.raw.location)],
+    }
+}
+
+/// Minutes of a session falling in each hour cell of the week grid.
+fn hour_cells(start: NaiveDateTime, end: NaiveDateTime) -> Vec<(Bucket, i64)> {
+    let mut cells = Vec::new();
+    let mut cursor = start;
+    while cursor < end {
+        let hour_end = cursor
+            .with_minute(0)
+            .and_then(|t| t.with_second(0))
+            .unwrap_or(cursor)
+            + Duration::hours(1);
+        let slice_end = hour_end.min(end);
+        cells.push((
+            Bucket::Cell(cursor.weekday().num_days_from_monday(), cursor.hour()),
+            (slice_end - cursor).num_minutes(),
+        ));
+        cursor = slice_end;
+    }
+    cells
+}
+
+#[derive(Default, Clone, Copy)]
+struct Cell {
+    minutes: i64,
+    sessions: u32,
+}
+
+/// Buckets event hours by time and dimension, ready for charts.
+pub fn aggregate_series(events: &[SourcedEvent], options: &SeriesOptions) -> TimeSeries {
+    // A merged session counts for the promos of all its copies.
+    let mut merged_promos: BTreeMap<usize, Vec<String>> = BTreeMap::new();
+    let kept: Vec<usize> = match &options.dedup {
+        Some(config) => {
+            let dedup = deduplicate(events, config);
+            for cluster in &dedup.clusters {
+                merged_promos.insert(cluster.kept, cluster.promos.clone());
+            }
+            dedup.kept_indices().collect()
+        }
+        None => (0..events.len()).collect(),
+    };
+
+    let mut cells: BTreeMap<String, BTreeMap<Bucket, Cell>> = BTreeMap::new();
+    let mut range: Option<(Bucket, Bucket)> = None;
+    for index in kept {
+        let event = &events[index].event;
+        let Some(start) = event.local_start() else {
+            continue;
+        };
+        let minutes = event.minutes();
+        let slices = if options.granularity == Granularity::WeekdayHour {
+            hour_cells(start, start + Duration::minutes(minutes))
+        } else {
+            vec![(bucket_of(start.date(), options), minutes)]
+        };
+        if let Some(&(first, _)) = slices.first() {
+            range = Some(match range {
+                Some((low, high)) => (low.min(first), high.max(first)),
+                None => (first, first),
+            });
+        }
+        let promos = merged_promos.get(&index).unwrap_or(&event.promos);
+        for key in dimension_keys(event, promos, options.dimension) {
+            let series = cells.entry(key).or_default();
+            for (position, &(bucket, minutes)) in slices.iter().enumerate() {
+                let cell = series.entry(bucket).or_default();
+                cell.minutes += minutes;
+                cell.sessions += u32::from(position == 0);
+            }
+        }
+    }
+
+    let buckets: Vec<Bucket> = match (options.granularity, range) {
+        (Granularity::WeekdayHour, _) => (0..7)
+            .flat_map(|day| (0..24).map(move |hour| Bucket::Cell(day, hour)))
+            .collect(),
+        (_, Some((low, high))) => {
+            let mut buckets = vec![low];
+            while let Some(&last) = buckets.last().filter(|&&last| last < high) {
+                buckets.push(next(last, options));
+            }
+            buckets
+        }
+        (_, None) => Vec::new(),
+    };
+
+    let mut series: Vec<Series> = cells
+        .into_iter()
+        .map(|(key, values)| {
+            let row: Vec<Cell> = buckets
+                .iter()
+                .map(|bucket| values.get(bucket).copied().unwrap_or_default())
+                .collect();
+            let cumulative_hours = if options.cumulative {
+                row.iter()
+                    .scan(0, |total, cell| {
+                        *total += cell.minutes;
+                        Some(minutes_to_hours(*total))
+                    })
+                    .collect()
+            } else {
+                Vec::new()
+            };
+            Series {
+                key,
+                hours: row.iter().map(|c| minutes_to_hours(c.minutes)).collect(),
+                sessions: row.iter().map(|c| c.sessions).collect(),
+                cumulative_hours,
+            }
+        })
+        .collect();
+    series.sort_by(|a, b| {
+        let total = |s: &Series| s.hours.iter().sum::<f64>();
+        total(b)
+            .total_cmp(&total(a))
+            .then_with(|| a.key.cmp(&b.key))
+    });
+
+    TimeSeries {
+        granularity: options.granularity,
+        dimension: options.dimension,
+        buckets: buckets.into_iter().map(label).collect(),
+        series,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::normalizer::test_support::{self, event_with_uid};
+
+    fn sourced(
+        calendar_id: &str,
+        summary: &str,
+        description: &str,
+        start: &str,
+        end: &str,
+    ) -> SourcedEvent {
+        let uid = format!("{calendar_id}-{summary}-{start}");
+        let event = event_with_uid(&uid, summary, description, "", start, end);
+        test_support::sourced(calendar_id, event)
+    }
+
+    fn events() -> Vec<SourcedEvent> {
+        vec![
+            sourced(
+                "m1",
+                "CM Algo",
+                "DUPONT Jean\nM1",
+                "20250106T080000",
+                "20250106T100000",
+            ),
+            // Same lecture seen through the M2 calendar
+            sourced(
+                "m2",
+                "CM Algo",
+                "DUPONT Jean\nM2",
+                "20250106T080000",
+                "20250106T100000",
+            ),
+            sourced(
+                "m1",
+                "TD Algo",
+                "MARTIN Paul\nM1",
+                "20250108T103000",
+                "20250108T120000",
+            ),
+            sourced(
+                "m1",
+                "TP Algo",
+                "DUPONT Jean\nM1",
+                "20250120T140000",
+                "20250120T170000",
+            ),
+        ]
+    }
+
+    #[test]
+    fn iso_weeks_are_contiguous_and_deduplicated() {
+        let series = aggregate_series(
+            &events(),
+            &SeriesOptions {
+                dimension: Dimension::Teacher,
+                cumulative: true,
+                ..SeriesOptions::default()
+            },
+        );
+
+        assert_eq!(series.buckets, vec!["2025-W02", "2025-W03", "2025-W04"]);
+        assert_eq!(series.series[0].key, "DUPONT Jean");
+        assert_eq!(series.series[0].hours, vec![2.0, 0.0, 3.0]);
+        assert_eq!(series.series[0].sessions, vec![1, 0, 1]);
+        assert_eq!(series.series[0].cumulative_hours, vec![2.0, 2.0, 5.0]);
+        assert_eq!(series.series[1].key, "MARTIN Paul");
+        assert_eq!(series.series[1].hours, vec![1.5, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn merged_sessions_count_for_every_promo() {
+        let series = aggregate_series(
+            &events(),
+            &SeriesOptions {
+                granularity: Granularity::Month,
+                dimension: Dimension::Promo,
+                ..SeriesOptions::default()
+            },
+        );
+
+        let keys: Vec<(&str, f64)> = series
+            .series
+            .iter()
+            .map(|s| (s.key.as_str(), s.hours[0]))
+            .collect();
+        assert_eq!(keys, vec![("M1", 6.5), ("M2", 2.0)]);
+    }
+
+    #[test]
+    fn academic_weeks_and_months() {
+        let options = SeriesOptions {
+            granularity: Granularity::AcademicWeek,
+            ..SeriesOptions::default()
+        };
+        let series = aggregate_series(&events(), &options);
+        // 1 September 2024 is a Sunday: week 1 starts on Monday 26 August.
+        assert_eq!(
+            series.buckets,
+            vec!["2024-2025 S20", "2024-2025 S21", "2024-2025 S22"]
+        );
+
+        let series = aggregate_series(
+            &events(),
+            &SeriesOptions {
+                granularity: Granularity::Month,
+                dimension: Dimension::Type,
+                dedup: None,
+                ..SeriesOptions::default()
+            },
+        );
+        assert_eq!(series.buckets, vec!["2025-01"]);
+        let keys: Vec<(&str, f64)> = series
+            .series
+            .iter()
+            .map(|s| (s.key.as_str(), s.hours[0]))
+            .collect();
+        assert_eq!(keys, vec![("CM", 4.0), ("TP", 3.0), ("TD", 1.5)]);
+    }
+
+    #[test]
+    fn academic_year_starting_mid_week_keeps_both_halves() {
+        let events = vec![
+            sourced(
+                "m1",
+                "CM Algo",
+                "DUPONT Jean\nM1",
+                "20260831T080000",
+                "20260831T100000",
+            ),
+            sourced(
+                "m1",
+                "TD Algo",
+                "DUPONT Jean\nM1",
+                "20260902T080000",
+                "20260902T110000",
+            ),
+        ];
+        let series = aggregate_series(
+            &events,
+            &SeriesOptions {
+                granularity: Granularity::AcademicWeek,
+                ..SeriesOptions::default()
+            },
+        );
+
+        assert_eq!(series.buckets, vec!["2025-2026 S53", "2026-2027 S01"]);
+        assert_eq!(series.series[0].hours, vec![2.0, 3.0]);
+    }
+
+    #[test]
+    fn weekday_hour_heatmap_spreads_sessions_over_cells() {
+        let series = aggregate_series(
+            &events(),
+            &SeriesOptions {
+                granularity: Granularity::WeekdayHour,
+                ..SeriesOptions::default()
+            },
+        );
+
+        assert_eq!(series.buckets.len(), 7 * 24);
+        assert_eq!(series.buckets[8], "mon 08");
+        let total = &series.series[0];
+        // Monday 08-10 lecture and 14-17 TP
+        assert_eq!(total.hours[8], 1.0);
+        assert_eq!(total.hours[14], 1.0);
+        assert_eq!(total.sessions[8], 1);
+        assert_eq!(total.sessions[9], 0);
+        // Wednesday 10:30-12:00
+        assert_eq!(total.hours[2 * 24 + 10], 0.5);
+        assert_eq!(total.hours[2 * 24 + 11], 1.0);
+    }
+}
@@ -0,0 +1,513 @@
+use crate::normalizer::{text_key, NormalizedEvent};
+use crate::query::parse_clock;
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, Weekday};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct WellbeingConfig {
+    pub max_daily_minutes: i64,
+    /// Longest stretch of back-to-back sessions before a break is due.
+    pub max_continuous_minutes: i64,
+    /// Gaps shorter than this do not interrupt a continuous run.
+    pub min_break_minutes: i64,
+    /// Sessions running past this time are evening sessions.
+    pub evening_after: String,
+    pub lunch_window_start: String,
+    pub lunch_window_end: String,
+    pub min_lunch_minutes: i64,
+    /// Gaps between two sessions of a group from this length are idle gaps.
+    pub max_idle_gap_minutes: i64,
+    pub weekend_days: Vec<Weekday>,
+}
+
+impl Default for WellbeingConfig {
+    fn default() -> Self {
+        WellbeingConfig {
+            max_daily_minutes: 6 * 60,
+            max_continuous_minutes: 4 * 60,
+            min_break_minutes: 15,
+            evening_after: "18:00".to_string(),
+            lunch_window_start: "12:00".to_string(),
+            lunch_window_end: "14:00".to_string(),
+            min_lunch_minutes: 45,
+            max_idle_gap_minutes: 120,
+            weekend_days: vec![Weekday::Sat, Weekday::Sun],
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ProfileKind {
+    Teacher,
+    Group,
+}
+
+/// Times are local `"HH:MM"`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum WellbeingIssue {
+    LongDay {
+        date: NaiveDate,
+        minutes: i64,
+    },
+    LongRun {
+        date: NaiveDate,
+        start: String,
+        end: String,
+        minutes: i64,
+    },
+    Evening {
+        date: NaiveDate,
+        start: String,
+        end: String,
+    },
+    Weekend {
+        date: NaiveDate,
+    },
+    NoLunchBreak {
+        date: NaiveDate,
+        longest_break_minutes: i64,
+    },
+    /// Only reported for groups: teachers choose what they do between sessions.
+    IdleGap {
+        date: NaiveDate,
+        start: String,
+        end: String,
+        minutes: i64,
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct DayMetrics {
+    pub date: NaiveDate,
+    pub sessions: usize,
+    /// Time spent in sessions; overlapping copies count once.
+    pub minutes: i64,
+    pub first_start: String,
+    pub last_end: String,
+    pub longest_run_minutes: i64,
+    /// Longest free stretch inside the lunch window.
+    pub lunch_break_minutes: i64,
+    /// Time between the first and last session not spent in sessions.
+    pub idle_minutes: i64,
+    pub evening_minutes: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+pub struct WellbeingTotals {
+    pub days: usize,
+    pub minutes: i64,
+    pub max_daily_minutes: i64,
+    pub long_days: usize,
+    pub long_runs: usize,
+    pub evening_sessions: usize,
+    pub weekend_days: usize,
+    pub missing_lunch_days: usize,
+    pub idle_gaps: usize,
+    pub idle_minutes: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct WellbeingProfile {
+    pub name: String,
+    pub kind: ProfileKind,
+    pub days: Vec<DayMetrics>,
+    pub issues: Vec<WellbeingIssue>,
+    pub totals: WellbeingTotals,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+pub struct WellbeingReport {
+    pub teachers: Vec<WellbeingProfile>,
+    pub groups: Vec<WellbeingProfile>,
+}
+
+struct Thresholds {
+    evening: u32,
+    lunch_start: u32,
+    lunch_end: u32,
+}
+
+fn clock(value: &str, field: &str) -> Result<u32, String> {
+    parse_clock(value).ok_or_else(|| format!("invalid {field} `{value}`"))
+}
+
+fn hhmm(time: NaiveDateTime) -> String {
+    time.format("%H:%M").to_string()
+}
+
+fn at(date: NaiveDate, minutes: u32) -> NaiveDateTime {
+    date.and_hms_opt(0, 0, 0).unwrap_or_default() + Duration::minutes(i64::from(minutes))
+}
+
+/// A group attends its own sessions and those of every enclosing promo:
+/// "M1 Groupe A" attends "M1" lectures.
+fn attends(group: &str, promo: &str) -> bool {
+    group == promo
+        || group
+            .strip_prefix(promo)
+            .is_some_and(|rest| rest.starts_with(' '))
+}
+
+/// Sorted, overlapping intervals merged.
+fn merge(
+    mut intervals: Vec<(NaiveDateTime, NaiveDateTime)>,
+) -> Vec<(NaiveDateTime, NaiveDateTime)> {
+    intervals.sort();
+    let mut merged: Vec<(NaiveDateTime, NaiveDateTime)> = Vec::new();
+    for (start, end) in intervals {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    merged
+}
+
+fn analyze_day(
+    date: NaiveDate,
+    sessions: Vec<(NaiveDateTime, NaiveDateTime)>,
+    kind: ProfileKind,
+    config: &WellbeingConfig,
+    thresholds: &Thresholds,
+    issues: &mut Vec<WellbeingIssue>,
+) -> DayMetrics {
+    let count = sessions.len();
+    let blocks = merge(sessions);
+    let minutes: i64 = blocks.iter().map(|(s, e)| (*e - *s).num_minutes()).sum();
+
+    if minutes > config.max_daily_minutes {
+        issues.push(WellbeingIssue::LongDay { date, minutes });
+    }
+    if config.weekend_days.contains(&date.weekday()) {
+        issues.push(WellbeingIssue::Weekend { date });
+    }
+
+    // Continuous runs: blocks separated by less than a real break.
+    let mut runs: Vec<(NaiveDateTime, NaiveDateTime, i64)> = Vec::new();
+    for &(start, end) in &blocks {
+        let length = (end - start).num_minutes();
+        match runs.last_mut() {
+            Some(run) if (start - run.1).num_minutes() < config.min_break_minutes => {
+                run.1 = end;
+                run.2 += length;
+            }
+            _ => runs.push((start, end, length)),
+        }
+    }
+    let longest_run_minutes = runs.iter().map(|run| run.2).max().unwrap_or(0);
+    for &(start, end, length) in &runs {
+        if length > config.max_continuous_minutes {
+            issues.push(WellbeingIssue::LongRun {
+                date,
+                start: hhmm(start),
+                end: hhmm(end),
+                minutes: length,
+            });
+        }
+    }
+
+    let mut idle_minutes = 0;
+    for pair in blocks.windows(2) {
+        let (end, next) = (pair[0].1, pair[1].0);
+        let gap = (next - end).num_minutes();
+        idle_minutes += gap;
+        if kind == ProfileKind::Group && gap >= config.max_idle_gap_minutes {
+            issues.push(WellbeingIssue::IdleGap {
+                date,
+                start: hhmm(end),
+                end: hhmm(next),
+                minutes: gap,
+            });
+        }
+    }
+
+    let evening = at(date, thresholds.evening);
+    let mut evening_minutes = 0;
+    for &(start, end) in blocks.iter().filter(|(_, end)| *end > evening) {
+        evening_minutes += (end - start.max(evening)).num_minutes();
+        issues.push(WellbeingIssue::Evening {
+            date,
+            start: hhmm(start),
+            end: hhmm(end),
+        });
+    }
+
+    let (lunch_start, lunch_end) = (
+        at(date, thresholds.lunch_start),
+        at(date, thresholds.lunch_end),
+    );
+    let mut cursor = lunch_start;
+    let mut lunch_break_minutes = 0;
+    let mut busy_at_lunch = false;
+    for &(start, end) in blocks
+        .iter()
+        .filter(|(s, e)| *s < lunch_end && *e > lunch_start)
+    {
+        busy_at_lunch = true;
+        lunch_break_minutes = lunch_break_minutes.max((start.max(cursor) - cursor).num_minutes());
+        cursor = cursor.max(end);
+    }
+    if cursor < lunch_end {
+        lunch_break_minutes = lunch_break_minutes.max((lunch_end - cursor).num_minutes());
+    }
+    if busy_at_lunch && lunch_break_minutes < config.min_lunch_minutes {
+        issues.push(WellbeingIssue::NoLunchBreak {
+            date,
+            longest_break_minutes: lunch_break_minutes,
+        });
+    }
+
+    DayMetrics {
+        date,
+        sessions: count,
+        minutes,
+        first_start: blocks.first().map(|b| hhmm(b.0)).unwrap_or_default(),
+        last_end: blocks.last().map(|b| hhmm(b.1)).unwrap_or_default(),
+        longest_run_minutes,
+        lunch_break_minutes,
+        idle_minutes,
+        evening_minutes,
+    }
+}
+
+fn build_profile(
+    name: String,
+    kind: ProfileKind,
+    sessions: Vec<(NaiveDateTime, NaiveDateTime)>,
+    config: &WellbeingConfig,
+    thresholds: &Thresholds,
+) -> WellbeingProfile {
+    let mut by_day: BTreeMap<NaiveDate, Vec<(NaiveDateTime, NaiveDateTime)>> = BTreeMap::new();
+    for session in sessions {
+        by_day.entry(session.0.date()).or_default().push(session);
+    }
+
+    let mut issues = Vec::new();
+    let days: Vec<DayMetrics> = by_day
+        .into_iter()
+        .map(|(date, sessions)| analyze_day(date, sessions, kind, config, thresholds, &mut issues))
+        .collect();
+
+    let mut totals = WellbeingTotals {
+        days: days.len(),
+        minutes: days.iter().map(|d| d.minutes).sum(),
+        max_daily_minutes: days.iter().map(|d| d.minutes).max().unwrap_or(0),
+        idle_minutes: days.iter().map(|d| d.idle_minutes).sum(),
+        ..WellbeingTotals::default()
+    };
+    for issue in &issues {
+        match issue {
+            WellbeingIssue::LongDay { .. } => totals.long_days += 1,
+            WellbeingIssue::LongRun { .. } => totals.long_runs += 1,
+            WellbeingIssue::Evening { .. } => totals.evening_sessions += 1,
+            WellbeingIssue::Weekend { .. } => totals.weekend_days += 1,
+            WellbeingIssue::NoLunchBreak { .. } => totals.missing_lunch_days += 1,
+            WellbeingIssue::IdleGap { .. } => totals.idle_gaps += 1,
+        }
+    }
+
+    WellbeingProfile {
+        name,
+        kind,
+        days,
+        issues,
+        totals,
+    }
+}
+
+/// Daily workload metrics and threshold breaches for every named teacher
+/// and every promo found in `events`.
+pub fn analyze_wellbeing(
+    events: &[NormalizedEvent],
+    config: &WellbeingConfig,
+) -> Result<WellbeingReport, String> {
+    let thresholds = Thresholds {
+        evening: clock(&config.evening_after, "evening_after")?,
+        lunch_start: clock(&config.lunch_window_start, "lunch_window_start")?,
+        lunch_end: clock(&config.lunch_window_end, "lunch_window_end")?,
+    };
+
+    // key -> (display name, sessions)
+    type Sessions = (String, Vec<(NaiveDateTime, NaiveDateTime)>);
+    let mut teachers: BTreeMap<String, Sessions> = BTreeMap::new();
+    let mut promos: BTreeMap<String, Sessions> = BTreeMap::new();
+    let mut timed: Vec<(&NormalizedEvent, NaiveDateTime, NaiveDateTime)> = Vec::new();
+    for event in events {
+        let (Some(start), Some(end)) = (event.local_start(), event.local_end()) else {
+            continue;
+        };
+        timed.push((event, start, end));
+        for name in event.known_teachers() {
+            teachers
+                .entry(text_key(name))
+                .or_insert_with(|| (name.to_string(), Vec::new()))
+                .1
+                .push((start, end));
+        }
+        for promo in &event.promos {
+            let key = text_key(promo);
+            if !key.is_empty() {
+                promos
+                    .entry(key)
+                    .or_insert_with(|| (promo.clone(), Vec::new()));
+            }
+        }
+    }
+    for (group, (_, sessions)) in promos.iter_mut() {
+        for (event, start, end) in &timed {
+            if event
+                .promos
+                .iter()
+                .any(|promo| attends(group, &text_key(promo)))
+            {
+                sessions.push((*start, *end));
+            }
+        }
+    }
+
+    let profiles = |entries: BTreeMap<String, Sessions>, kind| {
+        entries
+            .into_values()
+            .map(|(name, sessions)| build_profile(name, kind, sessions, config, &thresholds))
+            .collect()
+    };
+    Ok(WellbeingReport {
+        teachers: profiles(teachers, ProfileKind::Teacher),
+        groups: profiles(promos, ProfileKind::Group),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::normalizer::test_support::event;
+
+    fn date(value: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn flags_long_days_runs_evenings_and_missing_lunch() {
+        let events = vec![
+            event(
+                "CM Algo",
+                "DUPONT Jean\nM1",
+                "20250106T080000",
+                "20250106T100000",
+            ),
+            event(
+                "TD Algo",
+                "DUPONT Jean\nM1 Groupe A",
+                "20250106T100000",
+                "20250106T120000",
+            ),
+            event(
+                "TD Algo",
+                "DUPONT Jean\nM1 Groupe B",
+                "20250106T121000",
+                "20250106T140000",
+            ),
+            event(
+                "TP Algo",
+                "DUPONT Jean\nM1 Groupe A",
+                "20250106T170000",
+                "20250106T190000",
+            ),
+            event(
+                "TD Web",
+                "MARTIN Paul\nM1 Groupe A",
+                "20250111T080000",
+                "20250111T100000",
+            ),
+        ];
+
+        let report = analyze_wellbeing(&events, &WellbeingConfig::default()).unwrap();
+        let dupont = &report.teachers[0];
+        assert_eq!(dupont.name, "DUPONT Jean");
+
+        let monday = &dupont.days[0];
+        assert_eq!(monday.minutes, 470);
+        assert_eq!(monday.longest_run_minutes, 350);
+        assert_eq!(monday.lunch_break_minutes, 10);
+        assert_eq!(monday.idle_minutes, 190);
+        assert_eq!(monday.evening_minutes, 60);
+        assert_eq!(
+            dupont.issues,
+            vec![
+                WellbeingIssue::LongDay {
+                    date: date("2025-01-06"),
+                    minutes: 470
+                },
+                WellbeingIssue::LongRun {
+                    date: date("2025-01-06"),
+                    start: "08:00".to_string(),
+                    end: "14:00".to_string(),
+                    minutes: 350
+                },
+                WellbeingIssue::Evening {
+                    date: date("2025-01-06"),
+                    start: "17:00".to_string(),
+                    end: "19:00".to_string()
+                },
+                WellbeingIssue::NoLunchBreak {
+                    date: date("2025-01-06"),
+                    longest_break_minutes: 10
+                },
+            ]
+        );
+
+        let martin = &report.teachers[1];
+        assert_eq!(martin.totals.weekend_days, 1);
+        assert!(martin
+            .issues
+            .iter()
+            .all(|i| !matches!(i, WellbeingIssue::IdleGap { .. })));
+    }
+
+    #[test]
+    fn groups_attend_promo_sessions_and_report_idle_gaps() {
+        let events = vec![
+            event(
+                "CM Algo",
+                "DUPONT Jean\nM1",
+                "20250106T080000",
+                "20250106T100000",
+            ),
+            event(
+                "TD Algo",
+                "DUPONT Jean\nM1 Groupe A",
+                "20250106T140000",
+                "20250106T160000",
+            ),
+            event(
+                "TD Algo",
+                "MARTIN Paul\nM1 Groupe B",
+                "20250106T100000",
+                "20250106T120000",
+            ),
+        ];
+
+        let report = analyze_wellbeing(&events, &WellbeingConfig::default()).unwrap();
+        let names: Vec<&str> = report.groups.iter().map(|g| g.name.as_str()).collect();
+        assert_eq!(names, vec!["M1", "M1 Groupe A", "M1 Groupe B"]);
+
+        // The promo itself only has the lecture.
+        assert_eq!(report.groups[0].totals.minutes, 120);
+        let group_a = &report.groups[1];
+        assert_eq!(group_a.totals.minutes, 240);
+        assert_eq!(
+            group_a.issues,
+            vec![WellbeingIssue::IdleGap {
+                date: date("2025-01-06"),
+                start: "10:00".to_string(),
+                end: "14:00".to_string(),
+                minutes: 240
+            }]
+        );
+        assert!(report.groups[2].issues.is_empty());
+    }
+}
@@ -0,0 +1,245 @@
+//! xCal (RFC 6321): iCalendar as XML, sharing the jCal model in
+//! [`crate::jcal`].
+
+use crate::ics_writer::{write_ics, IcsExportOptions};
+use crate::jcal::{ics_tree, raw_events, record_error, Component, Property, Value};
+use crate::normalizer::NormalizedEvent;
+use crate::parser::{ParseDiagnostics, ParseOutput};
+use crate::query::QueryError;
+
+pub const NAMESPACE: &str = "urn:ietf:params:xml:ns:icalendar-2.0";
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn write_component(component: &Component, out: &mut String) {
+    out.push_str(&format!("<{}>", component.name));
+    if !component.properties.is_empty() {
+        out.push_str("<properties>");
+        for property in &component.properties {
+            out.push_str(&format!("<{}>", property.name));
+            if !property.params.is_empty() {
+                out.push_str("<parameters>");
+                for (name, value) in &property.params {
+                    out.push_str(&format!(
+                        "<{name}><text>{}</text></{name}>",
+                        escape_xml(value)
+                    ));
+                }
+                out.push_str("</parameters>");
+            }
+            for value in &property.values {
+                match value {
+                    Value::Text(text) => out.push_str(&format!(
+                        "<{kind}>{}</{kind}>",
+                        escape_xml(text),
+                        kind = property.value_type
+                    )),
+                    Value::Recur(parts) => {
+                        out.push_str("<recur>");
+                        for (key, value) in parts {
+                            for item in value.split(',') {
+                                out.push_str(&format!("<{key}>{}</{key}>", escape_xml(item)));
+                            }
+                        }
+                        out.push_str("</recur>");
+                    }
+                }
+            }
+            out.push_str(&format!("</{}>", property.name));
+        }
+        out.push_str("</properties>");
+    }
+    if !component.components.is_empty() {
+        out.push_str("<components>");
+        for child in &component.components {
+            write_component(child, out);
+        }
+        out.push_str("</components>");
+    }
+    out.push_str(&format!("</{}>", component.name));
+}
+
+/// Serializes events to an xCal document, with the same content as
+/// [`write_ics`].
+pub fn write_xcal(
+    events: &[NormalizedEvent],
+    options: &IcsExportOptions,
+) -> Result<String, QueryError> {
+    let ics = write_ics(events, options)?;
+    let mut out =
+        format!("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<icalendar xmlns=\"{NAMESPACE}\">");
+    for calendar in ics_tree(&ics) {
+        write_component(&calendar, &mut out);
+    }
+    out.push_str("</icalendar>\n");
+    Ok(out)
+}
+
+fn children<'a, 'input>(
+    node: roxmltree::Node<'a, 'input>,
+) -> impl Iterator<Item = roxmltree::Node<'a, 'input>> {
+    node.children()
+        .filter(|child| child.is_element() && child.tag_name().namespace() == Some(NAMESPACE))
+}
+
+fn child<'a, 'input>(
+    node: roxmltree::Node<'a, 'input>,
+    name: &str,
+) -> Option<roxmltree::Node<'a, 'input>> {
+    children(node).find(|child| child.tag_name().name() == name)
+}
+
+fn read_property(node: roxmltree::Node) -> Property {
+    let params = child(node, "parameters")
+        .map(|parameters| {
+            children(parameters)
+                .map(|param| {
+                    let value = children(param)
+                        .map(|value| value.text().unwrap_or_default())
+                        .collect::<Vec<_>>()
+                        .join(",");
+                    (param.tag_name().name().to_string(), value)
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    let mut value_type = "unknown".to_string();
+    let mut values = Vec::new();
+    for value in children(node).filter(|value| value.tag_name().name() != "parameters") {
+        value_type = value.tag_name().name().to_string();
+        if value_type == "recur" {
+            let mut parts: Vec<(String, String)> = Vec::new();
+            for part in children(value) {
+                let (key, text) = (part.tag_name().name(), part.text().unwrap_or_default());
+                match parts.iter_mut().find(|(existing, _)| existing == key) {
+                    Some((_, joined)) => {
+                        joined.push(',');
+                        joined.push_str(text);
+                    }
+                    None => parts.push((key.to_string(), text.to_string())),
+                }
+            }
+            values.push(Value::Recur(parts));
+        } else {
+            values.push(Value::Text(value.text().unwrap_or_default().to_string()));
+        }
+    }
+    Property {
+        name: node.tag_name().name().to_string(),
+        params,
+        value_type,
+        values,
+    }
+}
+
+fn read_component(node: roxmltree::Node) -> Component {
+    Component {
+        name: node.tag_name().name().to_string(),
+        properties: child(node, "properties")
+            .map(|properties| children(properties).map(read_property).collect())
+            .unwrap_or_default(),
+        components: child(node, "components")
+            .map(|components| children(components).map(read_component).collect())
+            .unwrap_or_default(),
+    }
+}
+
+/// Parses an xCal document; like the ICS parser, problems are reported in
+/// the diagnostics rather than as an error.
+pub fn parse_xcal_with_diagnostics(content: &str) -> ParseOutput {
+    let mut diagnostics = ParseDiagnostics::default();
+    let calendars = match roxmltree::Document::parse(content) {
+        Ok(document) => {
+            let root = document.root_element();
+            if root.tag_name().namespace() != Some(NAMESPACE)
+                || root.tag_name().name() != "icalendar"
+            {
+                record_error(
+                    &mut diagnostics,
+                    format!("Invalid xCal: expected <icalendar xmlns=\"{NAMESPACE}\">"),
+                );
+                Vec::new()
+            } else {
+                children(root)
+                    .filter(|node| node.tag_name().name() == "vcalendar")
+                    .map(read_component)
+                    .collect()
+            }
+        }
+        Err(e) => {
+            record_error(&mut diagnostics, format!("Invalid xCal: {e}"));
+            Vec::new()
+        }
+    };
+    ParseOutput {
+        events: raw_events(&calendars, &mut diagnostics),
+        diagnostics,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jcal::write_jcal;
+    use crate::normalizer::normalize;
+    use crate::parser::parse_ics_content_with_diagnostics;
+
+    const ICS: &str = "BEGIN:VCALENDAR\r\nVERSION:2.0\r\n\
+BEGIN:VEVENT\r\nUID:evt-1\r\nSUMMARY:TD <Réseaux> & Systèmes\r\n\
+LOCATION:Salle\\, B12\r\nDTSTART:20250106T080000\r\nDTEND:20250106T100000\r\nEND:VEVENT\r\n\
+END:VCALENDAR\r\n";
+
+    #[test]
+    fn round_trips_the_ics_writer_output() {
+        let events = normalize(parse_ics_content_with_diagnostics(ICS).events);
+        let options = IcsExportOptions {
+            generated_at: Some("2025-01-01T00:00:00Z".to_string()),
+            ..IcsExportOptions::default()
+        };
+        let xcal = write_xcal(&events, &options).unwrap();
+        assert!(xcal.contains(
+            "<dtstart><parameters><tzid><text>Europe/Paris</text></tzid></parameters>\
+<date-time>2025-01-06T08:00:00</date-time></dtstart>"
+        ));
+        assert!(xcal
+            .contains("<recur><freq>YEARLY</freq><bymonth>3</bymonth><byday>-1SU</byday></recur>"));
+        assert!(xcal.contains("TD &lt;Réseaux&gt; &amp; Systèmes"));
+
+        let parsed = parse_xcal_with_diagnostics(&xcal);
+        assert_eq!(parsed.diagnostics.parser_errors, 0);
+        assert_eq!(parsed.events[0].summary, "TD <Réseaux> & Systèmes");
+        assert_eq!(parsed.events[0].location, "Salle, B12");
+        assert_eq!(parsed.events[0].start, "20250106T080000");
+        assert_eq!(
+            normalize(parsed.events)[0].start_utc_ms,
+            events[0].start_utc_ms
+        );
+    }
+
+    #[test]
+    fn agrees_with_jcal_and_reports_invalid_documents() {
+        let events = normalize(parse_ics_content_with_diagnostics(ICS).events);
+        let options = IcsExportOptions::default();
+        let from_xcal = parse_xcal_with_diagnostics(&write_xcal(&events, &options).unwrap());
+        let from_jcal =
+            crate::jcal::parse_jcal_with_diagnostics(&write_jcal(&events, &options).unwrap());
+        assert_eq!(
+            serde_json::to_value(&from_xcal).unwrap(),
+            serde_json::to_value(&from_jcal).unwrap()
+        );
+
+        let wrong_root = parse_xcal_with_diagnostics("<icalendar><vcalendar/></icalendar>");
+        assert_eq!(wrong_root.diagnostics.parser_errors, 1);
+        assert_eq!(
+            parse_xcal_with_diagnostics("<icalendar")
+                .diagnostics
+                .parser_errors,
+            1
+        );
+    }
+}
@@ -0,0 +1,110 @@
+use agendum_core::dedup::DedupConfig;
+use agendum_core::feeds::SavedFeed;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Rennes planning host the web app used to reach through an external proxy.
+pub const RENNES_ORIGIN: &str = "https://planning.univ-rennes1.fr";
+pub const RENNES_PATH_PREFIX: &str = "/jsp/custom/modules/plannings/";
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct SourceConfig {
+    /// First path segment of proxied URLs: `/p/jsp/custom/...` for `p`.
+    pub mount: String,
+    /// Scheme, host and port of the upstream server.
+    pub origin: String,
+    /// Only upstream paths starting with one of these prefixes are forwarded.
+    pub allowed_paths: Vec<String>,
+    /// Upstream requests allowed per minute for this source; cached copies are
+    /// served, even stale, once the budget is spent.
+    pub max_requests_per_minute: u32,
+    /// Calendars kept in memory for this source; beyond that the least
+    /// recently validated one is dropped, whatever query string clients send.
+    pub max_cached_calendars: usize,
+}
+
+impl Default for SourceConfig {
+    fn default() -> Self {
+        SourceConfig {
+            mount: "p".to_string(),
+            origin: RENNES_ORIGIN.to_string(),
+            allowed_paths: vec![RENNES_PATH_PREFIX.to_string()],
+            max_requests_per_minute: 30,
+            max_cached_calendars: 100,
+        }
+    }
+}
+
+/// A calendar fetched through a source and published in feeds.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct CalendarConfig {
+    /// Identifier matched by `calendar:` query terms.
+    pub id: String,
+    #[serde(default = "default_mount")]
+    pub mount: String,
+    /// Upstream path and query, e.g.
+    /// `/jsp/custom/modules/plannings/anonymous_cal.jsp?resources=1234`.
+    pub path: String,
+    /// Copies of a session within the calendar are published once over
+    /// CalDAV. `null` keeps every copy.
+    #[serde(default = "default_dedup")]
+    pub dedup: Option<DedupConfig>,
+}
+
+fn default_mount() -> String {
+    SourceConfig::default().mount
+}
+
+fn default_dedup() -> Option<DedupConfig> {
+    Some(DedupConfig::default())
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct ServerConfig {
+    pub listen: String,
+    /// `Access-Control-Allow-Origin` sent with every response; `null` for none.
+    pub allow_origin: Option<String>,
+    /// Cached calendars younger than this are served without asking upstream.
+    pub cache_seconds: u64,
+    pub timeout_seconds: u64,
+    pub sources: Vec<SourceConfig>,
+    pub calendars: Vec<CalendarConfig>,
+    /// Saved queries over `calendars`, served at `/feeds/{name}.ics`.
+    pub feeds: Vec<SavedFeed>,
+    /// Publish `calendars` and `feeds` as read-only CalDAV collections under
+    /// `/dav/`.
+    pub caldav: bool,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            // Where the Vite dev server forwards `/rennes-proxy`.
+            listen: "127.0.0.1:8787".to_string(),
+            allow_origin: Some("*".to_string()),
+            cache_seconds: 300,
+            timeout_seconds: 30,
+            sources: vec![SourceConfig::default()],
+            calendars: Vec::new(),
+            feeds: Vec::new(),
+            caldav: false,
+        }
+    }
+}
+
+impl ServerConfig {
+    pub fn load(path: Option<&Path>) -> Result<ServerConfig, String> {
+        let Some(path) = path else {
+            return Ok(ServerConfig::default());
+        };
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("Cannot read config {}: {e}", path.display()))?;
+        serde_json::from_str(&text).map_err(|e| format!("Invalid config {}: {e}", path.display()))
+    }
+
+    pub fn source(&self, mount: &str) -> Option<&SourceConfig> {
+        self.sources.iter().find(|source| source.mount == mount)
+    }
+}
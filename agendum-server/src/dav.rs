@@ -0,0 +1,819 @@
+//! Read-only CalDAV access to the configured calendars and saved feeds.
+
+use crate::feeds::load_calendars;
+use crate::http::{etag, etag_matches, Request, Response};
+use crate::proxy::Proxy;
+use agendum_core::dedup::{deduplicate, SourcedEvent};
+use agendum_core::feeds::{select_feed_events, FeedStore};
+use agendum_core::ics_writer::{export_uid, write_ics, IcsExportOptions};
+use agendum_core::identity::IdentityConfig;
+use agendum_core::normalizer::NormalizedEvent;
+use chrono::{DateTime, NaiveDateTime, SecondsFormat};
+use std::collections::HashSet;
+
+const DAV: &str = "DAV:";
+const CALDAV: &str = "urn:ietf:params:xml:ns:caldav";
+const CALENDARSERVER: &str = "http://calendarserver.org/ns/";
+
+pub const ROOT: &str = "/dav/";
+pub const ALLOW: &str = "OPTIONS, GET, HEAD, PROPFIND, REPORT";
+
+/// Properties returned for `allprop` and empty PROPFIND bodies.
+const ALLPROP: &[(&str, &str)] = &[
+    (DAV, "resourcetype"),
+    (DAV, "displayname"),
+    (DAV, "getetag"),
+    (DAV, "getcontenttype"),
+    (CALENDARSERVER, "getctag"),
+    (CALDAV, "supported-calendar-component-set"),
+];
+
+struct Object {
+    /// Decoded resource name, e.g. `ADE6012@planning.ics`.
+    name: String,
+    ics: String,
+    etag: String,
+    start_ms: i64,
+    end_ms: i64,
+}
+
+struct Collection {
+    /// Calendar id or feed name.
+    name: String,
+    display_name: String,
+    description: Option<String>,
+    /// Changes with any object, for clients that poll `getctag`.
+    ctag: String,
+    events: Vec<NormalizedEvent>,
+    options: IcsExportOptions,
+    objects: Vec<Object>,
+}
+
+enum Resource<'a> {
+    Home,
+    Collection(&'a Collection),
+    Object(&'a Collection, &'a Object),
+}
+
+fn encode(segment: &str) -> String {
+    let mut encoded = String::new();
+    for byte in segment.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-_.~@".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    encoded
+}
+
+pub(crate) fn decode(segment: &str) -> String {
+    let bytes = segment.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let hex = (bytes[index] == b'%')
+            .then(|| segment.get(index + 1..index + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match hex {
+            Some(byte) => {
+                decoded.push(byte);
+                index += 3;
+            }
+            None => {
+                decoded.push(bytes[index]);
+                index += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn escape_xml(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+impl Collection {
+    fn build(
+        name: &str,
+        display_name: &str,
+        description: Option<String>,
+        events: Vec<NormalizedEvent>,
+        options: IcsExportOptions,
+        changed_at_ms: u64,
+    ) -> Result<Collection, Response> {
+        // DTSTAMP follows the source, so ETags only change with the events.
+        let stamp = DateTime::from_timestamp_millis(changed_at_ms as i64)
+            .unwrap_or_default()
+            .to_rfc3339_opts(SecondsFormat::Secs, true);
+        let object_options = IcsExportOptions {
+            method: None,
+            generated_at: Some(stamp),
+            ..options.clone()
+        };
+        let identity = IdentityConfig::default();
+        let mut objects: Vec<Object> = Vec::new();
+        let mut names: HashSet<String> = HashSet::new();
+        for event in &events {
+            let (Some(start_ms), Some(end_ms)) = (event.start_utc_ms, event.end_utc_ms) else {
+                continue;
+            };
+            let uid = export_uid(event, options.uid_mode, &identity);
+            let mut object_name = format!("{uid}.ics");
+            let mut copy = 1;
+            while names.contains(&object_name) {
+                copy += 1;
+                object_name = format!("{uid}-{copy}.ics");
+            }
+            names.insert(object_name.clone());
+            let ics = write_ics(std::slice::from_ref(event), &object_options)
+                .map_err(|e| Response::text(500, &e.to_string()))?;
+            objects.push(Object {
+                name: object_name,
+                etag: etag("", ics.as_bytes()),
+                ics,
+                start_ms,
+                end_ms,
+            });
+        }
+        let etags: String = objects.iter().map(|object| object.etag.as_str()).collect();
+        Ok(Collection {
+            name: name.to_string(),
+            display_name: display_name.to_string(),
+            description,
+            ctag: etag("c-", etags.as_bytes()),
+            events,
+            options,
+            objects,
+        })
+    }
+
+    fn href(&self) -> String {
+        format!("{ROOT}{}/", encode(&self.name))
+    }
+
+    fn object(&self, name: &str) -> Option<&Object> {
+        self.objects.iter().find(|object| object.name == name)
+    }
+}
+
+impl Resource<'_> {
+    fn href(&self) -> String {
+        match self {
+            Resource::Home => ROOT.to_string(),
+            Resource::Collection(collection) => collection.href(),
+            Resource::Object(collection, object) => {
+                format!("{}{}", collection.href(), encode(&object.name))
+            }
+        }
+    }
+
+    /// Inner XML of a property, `None` when the resource does not have it.
+    fn property(&self, namespace: &str, name: &str) -> Option<String> {
+        let home = format!("<d:href>{ROOT}</d:href>");
+        match (namespace, name, self) {
+            (DAV, "resourcetype", Resource::Home) => {
+                Some("<d:collection/><d:principal/>".to_string())
+            }
+            (DAV, "resourcetype", Resource::Collection(_)) => {
+                Some("<d:collection/><c:calendar/>".to_string())
+            }
+            (DAV, "resourcetype", Resource::Object(..)) => Some(String::new()),
+            (DAV, "displayname", Resource::Home) => Some("agendum".to_string()),
+            (DAV, "displayname", Resource::Collection(collection)) => {
+                Some(escape_xml(&collection.display_name))
+            }
+            (DAV, "current-user-principal" | "principal-URL" | "owner", _)
+            | (CALDAV, "calendar-home-set", _) => Some(home),
+            (DAV, "current-user-privilege-set", _) => {
+                Some("<d:privilege><d:read/></d:privilege>".to_string())
+            }
+            (DAV, "supported-report-set", Resource::Collection(_)) => Some(
+                "<d:supported-report><d:report><c:calendar-query/></d:report></d:supported-report>\
+<d:supported-report><d:report><c:calendar-multiget/></d:report></d:supported-report>"
+                    .to_string(),
+            ),
+            (DAV, "getetag", Resource::Collection(collection))
+            | (CALENDARSERVER, "getctag", Resource::Collection(collection)) => {
+                Some(escape_xml(&collection.ctag))
+            }
+            (DAV, "getetag", Resource::Object(_, object)) => Some(escape_xml(&object.etag)),
+            (DAV, "getcontenttype", Resource::Collection(_)) => {
+                Some("text/calendar; charset=utf-8".to_string())
+            }
+            (DAV, "getcontenttype", Resource::Object(..)) => {
+                Some("text/calendar; charset=utf-8; component=VEVENT".to_string())
+            }
+            (DAV, "getcontentlength", Resource::Object(_, object)) => {
+                Some(object.ics.len().to_string())
+            }
+            (CALDAV, "supported-calendar-component-set", Resource::Collection(_)) => {
+                Some("<c:comp name=\"VEVENT\"/>".to_string())
+            }
+            (CALDAV, "calendar-description", Resource::Collection(collection)) => {
+                collection.description.as_deref().map(escape_xml)
+            }
+            (CALDAV, "calendar-data", Resource::Object(_, object)) => Some(escape_xml(&object.ics)),
+            _ => None,
+        }
+    }
+
+    fn response(&self, properties: &[(String, String)]) -> String {
+        let mut found = String::new();
+        let mut missing = String::new();
+        for (namespace, name) in properties {
+            match self.property(namespace, name) {
+                Some(value) => found.push_str(&element(namespace, name, &value)),
+                None => missing.push_str(&element(namespace, name, "")),
+            }
+        }
+        let mut xml = format!("<d:response><d:href>{}</d:href>", self.href());
+        for (props, status) in [(found, "200 OK"), (missing, "404 Not Found")] {
+            if !props.is_empty() {
+                xml.push_str(&format!(
+                    "<d:propstat><d:prop>{props}</d:prop>\
+<d:status>HTTP/1.1 {status}</d:status></d:propstat>"
+                ));
+            }
+        }
+        xml.push_str("</d:response>");
+        xml
+    }
+}
+
+fn element(namespace: &str, name: &str, value: &str) -> String {
+    let (prefix, declaration) = match namespace {
+        DAV => ("d", String::new()),
+        CALDAV => ("c", String::new()),
+        CALENDARSERVER => ("cs", String::new()),
+        other => ("x", format!(" xmlns:x=\"{}\"", escape_xml(other))),
+    };
+    if value.is_empty() {
+        format!("<{prefix}:{name}{declaration}/>")
+    } else {
+        format!("<{prefix}:{name}{declaration}>{value}</{prefix}:{name}>")
+    }
+}
+
+fn not_found(href: &str) -> String {
+    format!(
+        "<d:response><d:href>{}</d:href><d:status>HTTP/1.1 404 Not Found</d:status></d:response>",
+        escape_xml(href)
+    )
+}
+
+fn multistatus(responses: &[String]) -> Response {
+    let body = format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<d:multistatus xmlns:d=\"{DAV}\" \
+xmlns:c=\"{CALDAV}\" xmlns:cs=\"{CALENDARSERVER}\">{}</d:multistatus>",
+        responses.concat()
+    );
+    Response::new(207, "application/xml; charset=utf-8", body)
+}
+
+enum Target {
+    Home,
+    Collection(String),
+    Object(String, String),
+}
+
+/// Resource addressed by a path or an absolute URL under [`ROOT`].
+fn resolve(href: &str) -> Option<Target> {
+    let path = match href.find("://") {
+        Some(index) => href[index + 3..]
+            .find('/')
+            .map_or("/", |start| &href[index + 3 + start..]),
+        None => href,
+    };
+    let rest = path.strip_prefix("/dav")?;
+    let rest = rest.trim_start_matches('/');
+    if rest.is_empty() {
+        return Some(Target::Home);
+    }
+    match rest.trim_end_matches('/').split_once('/') {
+        None => Some(Target::Collection(decode(rest.trim_end_matches('/')))),
+        Some((collection, object)) if !object.contains('/') => {
+            Some(Target::Object(decode(collection), decode(object)))
+        }
+        Some(_) => None,
+    }
+}
+
+fn requested_default() -> Vec<(String, String)> {
+    ALLPROP
+        .iter()
+        .map(|(namespace, name)| (namespace.to_string(), name.to_string()))
+        .collect()
+}
+
+/// Requested properties of a PROPFIND or REPORT body.
+fn requested(root: roxmltree::Node) -> Vec<(String, String)> {
+    let prop = root
+        .children()
+        .find(|node| node.has_tag_name((DAV, "prop")));
+    match prop {
+        Some(prop) => prop
+            .children()
+            .filter(|node| node.is_element())
+            .map(|node| {
+                let tag = node.tag_name();
+                (
+                    tag.namespace().unwrap_or_default().to_string(),
+                    tag.name().to_string(),
+                )
+            })
+            .collect(),
+        None => requested_default(),
+    }
+}
+
+fn utc_ms(value: &str) -> Option<i64> {
+    NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%SZ")
+        .ok()
+        .map(|time| time.and_utc().timestamp_millis())
+}
+
+pub struct Dav<'a> {
+    pub proxy: &'a Proxy,
+    pub feeds: &'a FeedStore,
+    pub now_ms: u64,
+}
+
+impl Dav<'_> {
+    fn calendar(&self, name: &str) -> Result<Option<Collection>, Response> {
+        let Some(calendar) = self
+            .proxy
+            .config()
+            .calendars
+            .iter()
+            .find(|calendar| calendar.id == name)
+        else {
+            return Ok(None);
+        };
+        let cached = self
+            .proxy
+            .calendar(&calendar.mount, &calendar.path, self.now_ms)?;
+        let events = match &calendar.dedup {
+            Some(config) => {
+                let sourced: Vec<SourcedEvent> = cached
+                    .events()
+                    .iter()
+                    .map(|event| SourcedEvent {
+                        calendar_id: calendar.id.clone(),
+                        event: event.clone(),
+                    })
+                    .collect();
+                deduplicate(&sourced, config)
+                    .kept_indices()
+                    .map(|index| sourced[index].event.clone())
+                    .collect()
+            }
+            None => cached.events().to_vec(),
+        };
+        let options = IcsExportOptions {
+            calendar_name: Some(calendar.id.clone()),
+            ..IcsExportOptions::default()
+        };
+        Collection::build(
+            &calendar.id,
+            &calendar.id,
+            None,
+            events,
+            options,
+            cached.changed_at_ms(),
+        )
+        .map(Some)
+    }
+
+    fn feed(&self, name: &str) -> Result<Option<Collection>, Response> {
+        let Some(feed) = self.feeds.get(name) else {
+            return Ok(None);
+        };
+        let (events, changed_at_ms) = load_calendars(self.proxy, self.now_ms)?;
+        let selected = select_feed_events(feed, &events)
+            .map_err(|e| Response::text(500, &format!("Feed `{name}`: {e}")))?;
+        Collection::build(
+            &feed.name,
+            feed.title.as_deref().unwrap_or(&feed.name),
+            Some(feed.query.clone()),
+            selected,
+            feed.export_options(None),
+            changed_at_ms,
+        )
+        .map(Some)
+    }
+
+    /// Calendars shadow feeds of the same name.
+    fn collection(&self, name: &str) -> Result<Collection, Response> {
+        match self.calendar(name)? {
+            Some(collection) => Ok(collection),
+            None => self
+                .feed(name)?
+                .ok_or_else(|| Response::text(404, "Unknown calendar")),
+        }
+    }
+
+    fn collections(&self) -> Result<Vec<Collection>, Response> {
+        let config = self.proxy.config();
+        let mut collections = Vec::new();
+        for calendar in &config.calendars {
+            collections.extend(self.calendar(&calendar.id)?);
+        }
+        for feed in self.feeds.feeds() {
+            if !config
+                .calendars
+                .iter()
+                .any(|calendar| calendar.id == feed.name)
+            {
+                collections.extend(self.feed(&feed.name)?);
+            }
+        }
+        Ok(collections)
+    }
+
+    fn propfind(&self, target: Target, request: &Request) -> Result<Response, Response> {
+        let body = String::from_utf8_lossy(&request.body);
+        let properties = if body.trim().is_empty() {
+            requested_default()
+        } else {
+            let document = roxmltree::Document::parse(&body)
+                .map_err(|e| Response::text(400, &format!("Invalid PROPFIND body: {e}")))?;
+            requested(document.root_element())
+        };
+        let deep = request
+            .header("Depth")
+            .is_none_or(|depth| depth.trim() != "0");
+
+        let mut responses = Vec::new();
+        match target {
+            Target::Home => {
+                responses.push(Resource::Home.response(&properties));
+                if deep {
+                    for collection in self.collections()? {
+                        responses.push(Resource::Collection(&collection).response(&properties));
+                    }
+                }
+            }
+            Target::Collection(name) => {
+                let collection = self.collection(&name)?;
+                responses.push(Resource::Collection(&collection).response(&properties));
+                if deep {
+                    for object in &collection.objects {
+                        responses.push(Resource::Object(&collection, object).response(&properties));
+                    }
+                }
+            }
+            Target::Object(name, object) => {
+                let collection = self.collection(&name)?;
+                let object = collection
+                    .object(&object)
+                    .ok_or_else(|| Response::text(404, "Unknown event"))?;
+                responses.push(Resource::Object(&collection, object).response(&properties));
+            }
+        }
+        Ok(multistatus(&responses))
+    }
+
+    fn report(&self, target: Target, request: &Request) -> Result<Response, Response> {
+        let body = String::from_utf8_lossy(&request.body);
+        let document = roxmltree::Document::parse(&body)
+            .map_err(|e| Response::text(400, &format!("Invalid REPORT body: {e}")))?;
+        let root = document.root_element();
+        let properties = requested(root);
+        let mut responses = Vec::new();
+
+        if root.has_tag_name((CALDAV, "calendar-multiget")) {
+            let mut loaded: Vec<Collection> = Vec::new();
+            for href in root
+                .children()
+                .filter(|node| node.has_tag_name((DAV, "href")))
+                .filter_map(|node| node.text())
+                .map(str::trim)
+            {
+                let Some(Target::Object(name, object)) = resolve(href) else {
+                    responses.push(not_found(href));
+                    continue;
+                };
+                if !loaded.iter().any(|collection| collection.name == name) {
+                    match self.collection(&name) {
+                        Ok(collection) => loaded.push(collection),
+                        Err(response) if response.status == 404 => {}
+                        Err(response) => return Err(response),
+                    }
+                }
+                let found = loaded
+                    .iter()
+                    .find(|collection| collection.name == name)
+                    .and_then(|collection| Some((collection, collection.object(&object)?)));
+                match found {
+                    Some((collection, object)) => {
+                        responses.push(Resource::Object(collection, object).response(&properties))
+                    }
+                    None => responses.push(not_found(href)),
+                }
+            }
+            return Ok(multistatus(&responses));
+        }
+
+        if !root.has_tag_name((CALDAV, "calendar-query")) {
+            return Err(Response::text(403, "Unsupported report"));
+        }
+        let Target::Collection(name) = target else {
+            return Err(Response::text(
+                403,
+                "calendar-query needs a calendar collection",
+            ));
+        };
+        let collection = self.collection(&name)?;
+        let filters: Vec<_> = root
+            .descendants()
+            .filter(|node| node.has_tag_name((CALDAV, "comp-filter")))
+            .collect();
+        // Only events are published; a query for to-dos or journals is empty.
+        let events_wanted = filters
+            .iter()
+            .all(|filter| matches!(filter.attribute("name"), Some("VCALENDAR" | "VEVENT")));
+        let range = root
+            .descendants()
+            .find(|node| node.has_tag_name((CALDAV, "time-range")));
+        let start = range
+            .and_then(|range| range.attribute("start"))
+            .and_then(utc_ms)
+            .unwrap_or(i64::MIN);
+        let end = range
+            .and_then(|range| range.attribute("end"))
+            .and_then(utc_ms)
+            .unwrap_or(i64::MAX);
+        if events_wanted {
+            for object in &collection.objects {
+                if object.start_ms < end && object.end_ms > start {
+                    responses.push(Resource::Object(&collection, object).response(&properties));
+                }
+            }
+        }
+        Ok(multistatus(&responses))
+    }
+
+    fn get(&self, target: Target, request: &Request) -> Result<Response, Response> {
+        let (ics, etag) = match target {
+            Target::Home => return Ok(Response::text(200, "agendum CalDAV")),
+            Target::Collection(name) => {
+                let collection = self.collection(&name)?;
+                let ics = write_ics(&collection.events, &collection.options)
+                    .map_err(|e| Response::text(500, &e.to_string()))?;
+                (ics, collection.ctag)
+            }
+            Target::Object(name, object) => {
+                let collection = self.collection(&name)?;
+                let object = collection
+                    .object(&object)
+                    .ok_or_else(|| Response::text(404, "Unknown event"))?;
+                (object.ics.clone(), object.etag.clone())
+            }
+        };
+        let response = if etag_matches(request.header("If-None-Match"), &etag) {
+            Response::empty(304)
+        } else {
+            Response::new(200, "text/calendar; charset=utf-8", ics)
+        };
+        Ok(response.with_header("ETag", &etag))
+    }
+
+    pub fn handle(&self, request: &Request) -> Response {
+        let response = match resolve(request.path()) {
+            None => Response::text(404, "Not found"),
+            Some(target) => {
+                let result = match request.method.as_str() {
+                    "OPTIONS" => Ok(Response::empty(200).with_header("Allow", ALLOW)),
+                    "PROPFIND" => self.propfind(target, request),
+                    "REPORT" => self.report(target, request),
+                    "GET" | "HEAD" => self.get(target, request),
+                    _ => Err(Response::text(405, "Read-only calendar").with_header("Allow", ALLOW)),
+                };
+                result.unwrap_or_else(|response| response)
+            }
+        };
+        response.with_header("DAV", "1, calendar-access")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{CalendarConfig, ServerConfig, SourceConfig};
+    use crate::proxy::{Upstream, UpstreamReply, UpstreamRequest};
+    use crate::routes::App;
+    use agendum_core::caldav::{
+        CalDavClient, CollectionState, DavRequest, DavResponse, DavTransport,
+    };
+    use agendum_core::dedup::DedupConfig;
+    use agendum_core::feeds::SavedFeed;
+
+    /// Two calendars sharing a session, M1 also holding a later one twice.
+    struct Static;
+
+    impl Upstream for Static {
+        fn fetch(&self, request: &UpstreamRequest) -> Result<UpstreamReply, String> {
+            let events: &[(&str, &str, &str, &str)] = if request.url.ends_with("m1.ics") {
+                &[
+                    ("a 1", "CM Algo", "M1 INFO\\nDUPONT Jean", "20250106T080000"),
+                    ("c", "TD Web", "M1 INFO\\nMARTIN Paul", "20250113T080000"),
+                    ("c2", "TD Web", "M1 INFO\\nMARTIN Paul", "20250113T080000"),
+                ]
+            } else {
+                &[("b", "CM Algo", "M2 INFO\\nDUPONT Jean", "20250106T080000")]
+            };
+            let mut ics = String::from("BEGIN:VCALENDAR\r\n");
+            for (uid, summary, description, start) in events {
+                ics.push_str(&format!(
+                    "BEGIN:VEVENT\r\nUID:{uid}\r\nSUMMARY:{summary}\r\nDESCRIPTION:{description}\r\n\
+DTSTART:{start}\r\nDTEND:{}\r\nEND:VEVENT\r\n",
+                    start.replace("T08", "T10")
+                ));
+            }
+            ics.push_str("END:VCALENDAR\r\n");
+            Ok(UpstreamReply {
+                status: 200,
+                body: ics.into_bytes(),
+                etag: None,
+                last_modified: None,
+                content_type: None,
+            })
+        }
+    }
+
+    fn app() -> App {
+        let config = ServerConfig {
+            caldav: true,
+            sources: vec![SourceConfig {
+                origin: "http://upstream.test".to_string(),
+                allowed_paths: vec!["/".to_string()],
+                ..SourceConfig::default()
+            }],
+            calendars: ["m1", "m2"]
+                .iter()
+                .map(|id| CalendarConfig {
+                    id: id.to_string(),
+                    mount: "p".to_string(),
+                    path: format!("/{id}.ics"),
+                    dedup: Some(DedupConfig::default()),
+                })
+                .collect(),
+            feeds: vec![SavedFeed {
+                name: "dupont".to_string(),
+                title: Some("Service DUPONT".to_string()),
+                query: "teacher:\"DUPONT Jean\"".to_string(),
+                dedup: Some(DedupConfig {
+                    match_uid: false,
+                    ..Default::default()
+                }),
+                ..SavedFeed::default()
+            }],
+            ..ServerConfig::default()
+        };
+        App::new(Proxy::new(config, Box::new(Static))).unwrap()
+    }
+
+    fn dav(method: &str, url: &str, depth: &str, body: &str) -> Request {
+        Request {
+            method: method.to_string(),
+            url: url.to_string(),
+            headers: vec![("Depth".to_string(), depth.to_string())],
+            body: body.as_bytes().to_vec(),
+        }
+    }
+
+    fn text(response: &Response) -> String {
+        String::from_utf8(response.body.clone()).unwrap()
+    }
+
+    #[test]
+    fn lists_calendars_and_feeds_as_collections() {
+        let app = app();
+        let home = app.handle(&dav("PROPFIND", "/dav/", "1", ""), 1_000);
+        assert_eq!(home.status, 207);
+        assert_eq!(home.header("DAV"), Some("1, calendar-access"));
+        let body = text(&home);
+        for href in ["/dav/", "/dav/m1/", "/dav/m2/", "/dav/dupont/"] {
+            assert!(body.contains(&format!("<d:href>{href}</d:href>")), "{href}");
+        }
+        assert!(body.contains("<d:displayname>Service DUPONT</d:displayname>"));
+        assert_eq!(body.matches("<c:calendar/>").count(), 3);
+
+        let propfind = r#"<?xml version="1.0"?><d:propfind xmlns:d="DAV:" xmlns:cs="http://calendarserver.org/ns/">
+<d:prop><d:getetag/><cs:getctag/><d:quota-used-bytes/></d:prop></d:propfind>"#;
+        let listing = text(&app.handle(&dav("PROPFIND", "/dav/m1/", "1", propfind), 1_000));
+        assert!(listing.contains("<d:href>/dav/m1/a%201.ics</d:href>"));
+        assert!(listing.contains("<d:href>/dav/m1/c.ics</d:href>"));
+        // The second copy of the TD is deduplicated away.
+        assert!(!listing.contains("c2.ics"));
+        assert!(listing.contains("<cs:getctag>&quot;c-"));
+        assert!(listing.contains("<d:quota-used-bytes/></d:prop><d:status>HTTP/1.1 404"));
+
+        let put = app.handle(
+            &dav("PUT", "/dav/m1/new.ics", "0", "BEGIN:VCALENDAR"),
+            1_000,
+        );
+        assert_eq!(put.status, 405);
+        assert_eq!(
+            app.handle(&dav("PROPFIND", "/dav/nope/", "0", ""), 1_000)
+                .status,
+            404
+        );
+        assert_eq!(
+            app.handle(&Request::get("/.well-known/caldav"), 1_000)
+                .header("Location"),
+            Some("/dav/")
+        );
+    }
+
+    #[test]
+    fn answers_queries_and_multigets_with_etags() {
+        let app = app();
+        let query = r#"<c:calendar-query xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+<d:prop><d:getetag/><c:calendar-data/></d:prop>
+<c:filter><c:comp-filter name="VCALENDAR"><c:comp-filter name="VEVENT">
+<c:time-range start="20250106T000000Z" end="20250107T000000Z"/></c:comp-filter></c:comp-filter></c:filter>
+</c:calendar-query>"#;
+        let found = text(&app.handle(&dav("REPORT", "/dav/m1/", "1", query), 1_000));
+        assert_eq!(found.matches("<d:response>").count(), 1);
+        assert!(found.contains("<d:href>/dav/m1/a%201.ics</d:href>"));
+        assert!(found.contains("UID:a 1"));
+        assert!(!found.contains("METHOD:"));
+
+        let feed = text(&app.handle(&dav("REPORT", "/dav/dupont/", "1", query), 1_000));
+        assert_eq!(feed.matches("<d:response>").count(), 1);
+
+        let multiget = r#"<c:calendar-multiget xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+<d:prop><d:getetag/></d:prop>
+<d:href>http://localhost:8787/dav/m1/c.ics</d:href><d:href>/dav/m1/gone.ics</d:href>
+</c:calendar-multiget>"#;
+        let got = text(&app.handle(&dav("REPORT", "/dav/m1/", "1", multiget), 1_000));
+        assert!(got.contains("<d:href>/dav/m1/c.ics</d:href><d:propstat><d:prop><d:getetag>"));
+        assert!(got.contains(
+            "<d:href>/dav/m1/gone.ics</d:href><d:status>HTTP/1.1 404 Not Found</d:status>"
+        ));
+
+        let object = app.handle(&Request::get("/dav/m1/c.ics"), 1_000);
+        assert_eq!(object.status, 200);
+        let etag = object.header("ETag").unwrap().to_string();
+        assert!(got.contains(&escape_xml(&etag)));
+        let again = app.handle(
+            &Request::get("/dav/m1/c.ics").with_header("If-None-Match", &etag),
+            2_000,
+        );
+        assert_eq!(again.status, 304);
+
+        let sync = r#"<d:sync-collection xmlns:d="DAV:"><d:sync-token/></d:sync-collection>"#;
+        assert_eq!(
+            app.handle(&dav("REPORT", "/dav/m1/", "1", sync), 1_000)
+                .status,
+            403
+        );
+    }
+
+    /// Sends the client's requests straight to the routes.
+    struct Direct(App);
+
+    impl DavTransport for Direct {
+        fn send(&mut self, request: &DavRequest) -> Result<DavResponse, String> {
+            let response = self.0.handle(
+                &Request {
+                    method: request.method.clone(),
+                    url: request
+                        .url
+                        .trim_start_matches("http://localhost:8787")
+                        .to_string(),
+                    headers: request.headers.clone(),
+                    body: request.body.as_bytes().to_vec(),
+                },
+                1_000,
+            );
+            Ok(DavResponse {
+                status: response.status,
+                body: String::from_utf8(response.body).map_err(|e| e.to_string())?,
+            })
+        }
+    }
+
+    #[test]
+    fn serves_the_agendum_caldav_client() {
+        let mut client = CalDavClient::new("http://localhost:8787/dav/", Direct(app()));
+        let calendars = client.discover().unwrap();
+        let hrefs: Vec<&str> = calendars.iter().map(|c| c.href.as_str()).collect();
+        assert_eq!(hrefs, ["/dav/m1/", "/dav/m2/", "/dav/dupont/"]);
+
+        let mut state = CollectionState::new("/dav/dupont/");
+        let changes = client.sync(&mut state).unwrap();
+        assert!(changes.full_resync);
+        let parsed = state.parse();
+        assert_eq!(parsed.events.len(), 1);
+        assert_eq!(parsed.events[0].summary, "CM Algo");
+        assert!(client.sync(&mut state).unwrap().updated.is_empty());
+    }
+}
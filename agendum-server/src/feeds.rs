@@ -0,0 +1,229 @@
+//! `/feeds/{name}.ics`: saved queries over the configured calendars, for
+//! webcal subscriptions from phones and desktop clients.
+
+use crate::http::{etag_matches, Request, Response};
+use crate::proxy::Proxy;
+use agendum_core::dedup::SourcedEvent;
+use agendum_core::feeds::{render_feed, FeedStore};
+use serde_json::json;
+use std::time::{Duration, UNIX_EPOCH};
+
+fn http_date(ms: u64) -> String {
+    httpdate::fmt_http_date(UNIX_EPOCH + Duration::from_millis(ms))
+}
+
+/// Events of every configured calendar, and when the latest one changed.
+///
+/// A feed missing one of its calendars would make subscribers delete events,
+/// so any failure fails the whole feed and clients keep their copy.
+pub fn load_calendars(proxy: &Proxy, now_ms: u64) -> Result<(Vec<SourcedEvent>, u64), Response> {
+    let mut events = Vec::new();
+    let mut changed_at_ms = 0;
+    for calendar in &proxy.config().calendars {
+        let cached = proxy.calendar(&calendar.mount, &calendar.path, now_ms)?;
+        changed_at_ms = changed_at_ms.max(cached.changed_at_ms());
+        events.extend(cached.events().iter().map(|event| SourcedEvent {
+            calendar_id: calendar.id.clone(),
+            event: event.clone(),
+        }));
+    }
+    Ok((events, changed_at_ms))
+}
+
+/// Feed names and titles, so clients can offer a subscription list.
+pub fn list(feeds: &FeedStore) -> Response {
+    let items: Vec<_> = feeds
+        .feeds()
+        .iter()
+        .map(|feed| {
+            json!({
+                "name": feed.name,
+                "title": feed.title.as_deref().unwrap_or(&feed.name),
+                "query": feed.query,
+                "path": format!("/feeds/{}.ics", feed.name),
+            })
+        })
+        .collect();
+    Response::new(
+        200,
+        "application/json",
+        serde_json::to_vec(&items).unwrap_or_default(),
+    )
+}
+
+pub fn serve(
+    proxy: &Proxy,
+    feeds: &FeedStore,
+    name: &str,
+    request: &Request,
+    now_ms: u64,
+) -> Response {
+    let name = name.strip_suffix(".ics").unwrap_or(name);
+    let Some(feed) = feeds.get(name) else {
+        return Response::text(404, "Unknown feed");
+    };
+
+    let (events, changed_at_ms) = match load_calendars(proxy, now_ms) {
+        Ok(loaded) => loaded,
+        Err(response) => return response,
+    };
+    let rendered = match render_feed(feed, &events, None) {
+        Ok(rendered) => rendered,
+        Err(e) => return Response::text(500, &format!("Feed `{name}`: {e}")),
+    };
+    let last_modified = http_date(changed_at_ms);
+    let not_modified = match request.header("If-None-Match") {
+        Some(header) => etag_matches(Some(header), &rendered.etag),
+        None => request
+            .header("If-Modified-Since")
+            .and_then(|date| httpdate::parse_http_date(date).ok())
+            .is_some_and(|since| since >= UNIX_EPOCH + Duration::from_secs(changed_at_ms / 1000)),
+    };
+    let response = if not_modified {
+        Response::empty(304)
+    } else {
+        Response::new(200, "text/calendar; charset=utf-8", rendered.ics)
+    };
+    response
+        .with_header("ETag", &rendered.etag)
+        .with_header("Last-Modified", &last_modified)
+        .with_header(
+            "Cache-Control",
+            &format!("max-age={}", proxy.config().cache_seconds),
+        )
+        .with_header(
+            "Content-Disposition",
+            &format!("inline; filename=\"{name}.ics\""),
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{CalendarConfig, ServerConfig, SourceConfig};
+    use crate::proxy::{Upstream, UpstreamReply, UpstreamRequest};
+    use agendum_core::feeds::SavedFeed;
+    use std::sync::{Arc, Mutex};
+
+    /// Serves each path from a table that tests can update.
+    struct Table(Arc<Mutex<Vec<(String, String)>>>);
+
+    impl Upstream for Table {
+        fn fetch(&self, request: &UpstreamRequest) -> Result<UpstreamReply, String> {
+            let table = self.0.lock().unwrap();
+            let body = table
+                .iter()
+                .find(|(path, _)| request.url.ends_with(path.as_str()))
+                .map(|(_, body)| body.clone())
+                .ok_or_else(|| "not found".to_string())?;
+            Ok(UpstreamReply {
+                status: 200,
+                body: body.into_bytes(),
+                etag: None,
+                last_modified: None,
+                content_type: None,
+            })
+        }
+    }
+
+    fn calendar(events: &[(&str, &str, &str)]) -> String {
+        let mut ics = String::from("BEGIN:VCALENDAR\r\n");
+        for (uid, summary, description) in events {
+            ics.push_str(&format!(
+                "BEGIN:VEVENT\r\nUID:{uid}\r\nSUMMARY:{summary}\r\nDESCRIPTION:{description}\r\n\
+DTSTART:20250106T080000\r\nDTEND:20250106T100000\r\nEND:VEVENT\r\n"
+            ));
+        }
+        ics.push_str("END:VCALENDAR\r\n");
+        ics
+    }
+
+    #[test]
+    fn serves_saved_queries_with_validators() {
+        let table = Arc::new(Mutex::new(vec![
+            (
+                "m1.ics".to_string(),
+                calendar(&[("a", "CM Algo", "M1 INFO\\nDUPONT Jean")]),
+            ),
+            (
+                "m2.ics".to_string(),
+                calendar(&[
+                    ("b", "CM Algo", "M2 INFO\\nDUPONT Jean"),
+                    ("c", "TD Web", "M2 INFO\\nMARTIN Paul"),
+                ]),
+            ),
+        ]));
+        let config = ServerConfig {
+            cache_seconds: 0,
+            sources: vec![SourceConfig {
+                origin: "http://upstream.test".to_string(),
+                allowed_paths: vec!["/".to_string()],
+                ..SourceConfig::default()
+            }],
+            calendars: ["m1", "m2"]
+                .iter()
+                .map(|id| CalendarConfig {
+                    id: id.to_string(),
+                    mount: "p".to_string(),
+                    path: format!("/{id}.ics"),
+                    dedup: None,
+                })
+                .collect(),
+            ..ServerConfig::default()
+        };
+        let feeds = FeedStore::new(vec![SavedFeed {
+            name: "dupont".to_string(),
+            query: "teacher:\"DUPONT Jean\"".to_string(),
+            ..SavedFeed::default()
+        }])
+        .unwrap();
+        let proxy = Proxy::new(config, Box::new(Table(table.clone())));
+        let request = Request::get("/feeds/dupont.ics");
+
+        let first = serve(&proxy, &feeds, "dupont.ics", &request, 1_000);
+        assert_eq!(first.status, 200);
+        let body = String::from_utf8(first.body.clone()).unwrap();
+        assert_eq!(body.matches("BEGIN:VEVENT").count(), 1);
+        assert!(body.contains("UID:a\r\n"));
+        assert_eq!(
+            first.header("Last-Modified"),
+            Some("Thu, 01 Jan 1970 00:00:01 GMT")
+        );
+
+        let etag = first.header("ETag").unwrap().to_string();
+        let unchanged = serve(
+            &proxy,
+            &feeds,
+            "dupont",
+            &Request::get("/").with_header("If-None-Match", &etag),
+            5_000,
+        );
+        assert_eq!(unchanged.status, 304);
+        let since = serve(
+            &proxy,
+            &feeds,
+            "dupont",
+            &Request::get("/").with_header("If-Modified-Since", "Thu, 01 Jan 1970 00:00:02 GMT"),
+            6_000,
+        );
+        assert_eq!(since.status, 304);
+
+        table.lock().unwrap()[0].1 = calendar(&[("a", "CM Algo", "M1 INFO\\nMARTIN Paul")]);
+        let changed = serve(
+            &proxy,
+            &feeds,
+            "dupont",
+            &Request::get("/").with_header("If-None-Match", &etag),
+            9_000,
+        );
+        assert_eq!(changed.status, 200);
+        assert!(String::from_utf8(changed.body.clone())
+            .unwrap()
+            .contains("UID:b\r\n"));
+        assert_eq!(
+            changed.header("Last-Modified"),
+            Some("Thu, 01 Jan 1970 00:00:09 GMT")
+        );
+        assert_eq!(serve(&proxy, &feeds, "nope", &request, 9_000).status, 404);
+    }
+}
@@ -0,0 +1,191 @@
+//! `agendum-server`: self-hosted proxy for ICS sources that do not send CORS
+//! headers, such as the Rennes planning server. See [`routes`] for the URLs
+//! and [`config::ServerConfig`] for the settings.
+
+mod config;
+mod dav;
+mod feeds;
+mod http;
+mod proxy;
+mod routes;
+mod upstream;
+
+use clap::Parser;
+use config::ServerConfig;
+use http::Request;
+use proxy::Proxy;
+use routes::App;
+use std::io::Read;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use upstream::HttpUpstream;
+
+#[derive(Parser)]
+#[command(
+    name = "agendum-server",
+    version,
+    about = "Caching ICS proxy for the Agendum web app"
+)]
+struct Cli {
+    /// JSON configuration file (sources, allowlist, cache, rate limits).
+    #[arg(short, long)]
+    config: Option<PathBuf>,
+    /// Address to listen on, e.g. 0.0.0.0:8787 (overrides the config).
+    #[arg(short, long)]
+    listen: Option<String>,
+    /// Serve the configured calendars and feeds over CalDAV under `/dav/`.
+    #[arg(long)]
+    caldav: bool,
+}
+
+/// Largest request body read, enough for any PROPFIND or REPORT.
+const MAX_REQUEST_BYTES: u64 = 1024 * 1024;
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis() as u64)
+}
+
+fn respond(app: &App, mut incoming: tiny_http::Request) {
+    let mut body = Vec::new();
+    if let Err(e) = incoming
+        .as_reader()
+        .take(MAX_REQUEST_BYTES)
+        .read_to_end(&mut body)
+    {
+        eprintln!("agendum-server: cannot read request body: {e}");
+        return;
+    }
+    let request = Request {
+        method: incoming.method().as_str().to_string(),
+        url: incoming.url().to_string(),
+        headers: incoming
+            .headers()
+            .iter()
+            .map(|header| (header.field.to_string(), header.value.to_string()))
+            .collect(),
+        body,
+    };
+    let response = app.handle(&request, now_ms());
+    let mut outgoing =
+        tiny_http::Response::from_data(response.body).with_status_code(response.status);
+    for (name, value) in &response.headers {
+        if let Ok(header) = tiny_http::Header::from_bytes(name.as_bytes(), value.as_bytes()) {
+            outgoing.add_header(header);
+        }
+    }
+    let _ = incoming.respond(outgoing);
+}
+
+/// Answers requests, one thread each, until the server is dropped.
+fn serve(server: tiny_http::Server, app: Arc<App>) {
+    for incoming in server.incoming_requests() {
+        let app = app.clone();
+        std::thread::spawn(move || respond(&app, incoming));
+    }
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let mut config = match ServerConfig::load(cli.config.as_deref()) {
+        Ok(config) => config,
+        Err(message) => {
+            eprintln!("agendum-server: {message}");
+            return ExitCode::FAILURE;
+        }
+    };
+    if let Some(listen) = cli.listen {
+        config.listen = listen;
+    }
+    config.caldav |= cli.caldav;
+    let server = match tiny_http::Server::http(&config.listen) {
+        Ok(server) => server,
+        Err(e) => {
+            eprintln!("agendum-server: cannot listen on {}: {e}", config.listen);
+            return ExitCode::FAILURE;
+        }
+    };
+    let upstream = HttpUpstream::new(Duration::from_secs(config.timeout_seconds));
+    let listen = config.listen.clone();
+    let app = match App::new(Proxy::new(config, Box::new(upstream))) {
+        Ok(app) => app,
+        Err(message) => {
+            eprintln!("agendum-server: {message}");
+            return ExitCode::FAILURE;
+        }
+    };
+    eprintln!("agendum-server: listening on http://{listen}");
+    serve(server, Arc::new(app));
+    ExitCode::SUCCESS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::SourceConfig;
+
+    const ICS: &str = "BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nUID:1\r\nSUMMARY:TD Réseaux\r\n\
+DESCRIPTION:L3 INFO\\nMARTIN Paul\r\nDTSTART:20250107T080000\r\nDTEND:20250107T100000\r\n\
+END:VEVENT\r\nEND:VCALENDAR\r\n";
+
+    /// Stand-in for the planning server: one calendar with an ETag.
+    fn stand_in() -> (String, Arc<std::sync::atomic::AtomicUsize>) {
+        let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let origin = format!("http://{}", server.server_addr().to_ip().unwrap());
+        let full_responses = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counter = full_responses.clone();
+        std::thread::spawn(move || {
+            for request in server.incoming_requests() {
+                let revalidating = request.headers().iter().any(|header| {
+                    header.field.equiv("If-None-Match") && header.value == "\"cal-1\""
+                });
+                let response = if revalidating {
+                    tiny_http::Response::from_data(Vec::new()).with_status_code(304)
+                } else {
+                    counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                    tiny_http::Response::from_data(ICS.as_bytes().to_vec())
+                };
+                let etag = tiny_http::Header::from_bytes("ETag", "\"cal-1\"").unwrap();
+                let _ = request.respond(response.with_header(etag));
+            }
+        });
+        (origin, full_responses)
+    }
+
+    #[test]
+    fn proxies_a_local_ics_server_over_http() {
+        let (origin, full_responses) = stand_in();
+        let config = ServerConfig {
+            cache_seconds: 0,
+            sources: vec![SourceConfig {
+                origin,
+                ..SourceConfig::default()
+            }],
+            ..ServerConfig::default()
+        };
+        let upstream = HttpUpstream::new(Duration::from_secs(5));
+        let app = App::new(Proxy::new(config, Box::new(upstream))).unwrap();
+        let path = "/p/jsp/custom/modules/plannings/anonymous_cal.jsp?resources=42";
+
+        let first = app.handle(&Request::get(path), now_ms());
+        assert_eq!(first.status, 200);
+        assert_eq!(first.body, ICS.as_bytes());
+        assert_eq!(first.header("Access-Control-Allow-Origin"), Some("*"));
+
+        let second = app.handle(&Request::get(&format!("/json{path}")), now_ms());
+        assert_eq!(second.status, 200);
+        assert_eq!(second.header("X-Cache"), Some("revalidated"));
+        let payload: serde_json::Value = serde_json::from_slice(&second.body).unwrap();
+        assert_eq!(payload["events"][0]["teachers"][0], "MARTIN Paul");
+        assert_eq!(full_responses.load(std::sync::atomic::Ordering::SeqCst), 1);
+
+        assert_eq!(
+            app.handle(&Request::get("/p/etc/passwd"), now_ms()).status,
+            403
+        );
+        assert_eq!(app.handle(&Request::get("/health"), now_ms()).status, 200);
+    }
+}
@@ -0,0 +1,525 @@
+//! Allowlisted, cached and rate-limited access to upstream ICS feeds.
+
+use crate::config::{ServerConfig, SourceConfig};
+use crate::dav::decode;
+use crate::http::{etag, etag_matches, Request, Response};
+use agendum_core::normalizer::{normalize, NormalizedEvent};
+use agendum_core::parser::{parse_ics_content_with_diagnostics, ParseDiagnostics};
+use serde_json::json;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex, OnceLock};
+
+const RATE_WINDOW_MS: u64 = 60_000;
+
+/// Conditional GET sent upstream.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UpstreamRequest {
+    pub url: String,
+    pub if_none_match: Option<String>,
+    pub if_modified_since: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UpstreamReply {
+    pub status: u16,
+    pub body: Vec<u8>,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    pub content_type: Option<String>,
+}
+
+/// How upstream calendars are fetched: HTTP in production, canned replies in
+/// tests. Errors are transport failures; HTTP errors are replies.
+pub trait Upstream: Send + Sync {
+    fn fetch(&self, request: &UpstreamRequest) -> Result<UpstreamReply, String>;
+}
+
+/// An upstream calendar as last received.
+pub struct CachedCalendar {
+    body: Vec<u8>,
+    etag: String,
+    upstream_etag: Option<String>,
+    last_modified: Option<String>,
+    content_type: String,
+    validated_at_ms: u64,
+    /// When the body last differed from the previous copy.
+    changed_at_ms: u64,
+    /// Parsed and normalized on first use.
+    parsed: OnceLock<(Vec<NormalizedEvent>, ParseDiagnostics)>,
+}
+
+impl CachedCalendar {
+    fn parsed(&self) -> &(Vec<NormalizedEvent>, ParseDiagnostics) {
+        self.parsed.get_or_init(|| {
+            let parsed = parse_ics_content_with_diagnostics(&String::from_utf8_lossy(&self.body));
+            (normalize(parsed.events), parsed.diagnostics)
+        })
+    }
+
+    pub fn events(&self) -> &[NormalizedEvent] {
+        &self.parsed().0
+    }
+
+    pub fn changed_at_ms(&self) -> u64 {
+        self.changed_at_ms
+    }
+
+    fn normalized_json(&self) -> Vec<u8> {
+        let (events, diagnostics) = self.parsed();
+        serde_json::to_vec(&json!({ "events": events, "diagnostics": diagnostics }))
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CacheStatus {
+    Hit,
+    Miss,
+    Revalidated,
+    /// Upstream failed or the rate limit was reached.
+    Stale,
+}
+
+impl CacheStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            CacheStatus::Hit => "hit",
+            CacheStatus::Miss => "miss",
+            CacheStatus::Revalidated => "revalidated",
+            CacheStatus::Stale => "stale",
+        }
+    }
+}
+
+/// What a proxied request asks for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Representation {
+    Ics,
+    NormalizedJson,
+}
+
+pub struct Proxy {
+    config: ServerConfig,
+    upstream: Box<dyn Upstream>,
+    /// Cached calendars per source mount, keyed by upstream URL.
+    cache: Mutex<HashMap<String, HashMap<String, Arc<CachedCalendar>>>>,
+    /// Upstream request times per source mount, within the last minute.
+    requests: Mutex<HashMap<String, VecDeque<u64>>>,
+}
+
+/// Upstream URL for the part of a proxied path after the mount, or `None`
+/// when the path is outside the source's allowlist.
+fn upstream_url(source: &SourceConfig, rest: &str, query: Option<&str>) -> Option<String> {
+    let path = format!("/{}", rest.trim_start_matches('/'));
+    // Upstream servers decode the path, so check what they will see: no dot
+    // segments and no separators smuggled in as %2F or %5C.
+    let segments: Vec<String> = path.split('/').map(decode).collect();
+    if segments
+        .iter()
+        .any(|segment| segment == ".." || segment == "." || segment.contains(['/', '\\']))
+    {
+        return None;
+    }
+    let decoded = segments.join("/");
+    if !source
+        .allowed_paths
+        .iter()
+        .any(|prefix| decoded.starts_with(prefix.as_str()))
+    {
+        return None;
+    }
+    let origin = source.origin.trim_end_matches('/');
+    Some(match query {
+        Some(query) => format!("{origin}{path}?{query}"),
+        None => format!("{origin}{path}"),
+    })
+}
+
+impl Proxy {
+    pub fn new(config: ServerConfig, upstream: Box<dyn Upstream>) -> Proxy {
+        Proxy {
+            config,
+            upstream,
+            cache: Mutex::new(HashMap::new()),
+            requests: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn config(&self) -> &ServerConfig {
+        &self.config
+    }
+
+    /// Takes one upstream request from the source's budget, if any is left.
+    fn try_spend(&self, source: &SourceConfig, now_ms: u64) -> Result<(), u64> {
+        let mut requests = self.requests.lock().unwrap();
+        let times = requests.entry(source.mount.clone()).or_default();
+        while times
+            .front()
+            .is_some_and(|&time| time + RATE_WINDOW_MS <= now_ms)
+        {
+            times.pop_front();
+        }
+        if times.len() >= source.max_requests_per_minute as usize {
+            let retry_ms = times
+                .front()
+                .map_or(0, |&time| time + RATE_WINDOW_MS - now_ms);
+            return Err(retry_ms.div_ceil(1000));
+        }
+        times.push_back(now_ms);
+        Ok(())
+    }
+
+    fn load(
+        &self,
+        source: &SourceConfig,
+        url: &str,
+        now_ms: u64,
+    ) -> Result<(Arc<CachedCalendar>, CacheStatus), Response> {
+        let cached = self
+            .cache
+            .lock()
+            .unwrap()
+            .get(&source.mount)
+            .and_then(|entries| entries.get(url))
+            .cloned();
+        if let Some(entry) = &cached {
+            if now_ms.saturating_sub(entry.validated_at_ms) < self.config.cache_seconds * 1000 {
+                return Ok((entry.clone(), CacheStatus::Hit));
+            }
+        }
+
+        if let Err(retry_after) = self.try_spend(source, now_ms) {
+            return match cached {
+                Some(entry) => Ok((entry, CacheStatus::Stale)),
+                None => Err(Response::text(429, "Too many requests for this source")
+                    .with_header("Retry-After", &retry_after.to_string())),
+            };
+        }
+
+        let request = UpstreamRequest {
+            url: url.to_string(),
+            if_none_match: cached
+                .as_ref()
+                .and_then(|entry| entry.upstream_etag.clone()),
+            if_modified_since: cached
+                .as_ref()
+                .and_then(|entry| entry.last_modified.clone()),
+        };
+        let reply = self.upstream.fetch(&request);
+
+        let (entry, status) = match (reply, cached) {
+            (Ok(reply), Some(entry)) if reply.status == 304 => {
+                let refreshed = CachedCalendar {
+                    body: entry.body.clone(),
+                    etag: entry.etag.clone(),
+                    upstream_etag: reply.etag.or_else(|| entry.upstream_etag.clone()),
+                    last_modified: reply.last_modified.or_else(|| entry.last_modified.clone()),
+                    content_type: entry.content_type.clone(),
+                    validated_at_ms: now_ms,
+                    changed_at_ms: entry.changed_at_ms,
+                    parsed: entry.parsed.clone(),
+                };
+                (Arc::new(refreshed), CacheStatus::Revalidated)
+            }
+            (Ok(reply), cached) if (200..300).contains(&reply.status) => {
+                let unchanged = cached.filter(|entry| entry.body == reply.body);
+                let entry = CachedCalendar {
+                    etag: etag("", &reply.body),
+                    body: reply.body,
+                    upstream_etag: reply.etag,
+                    last_modified: reply.last_modified,
+                    content_type: reply
+                        .content_type
+                        .unwrap_or_else(|| "text/calendar; charset=utf-8".to_string()),
+                    validated_at_ms: now_ms,
+                    changed_at_ms: unchanged
+                        .as_ref()
+                        .map_or(now_ms, |entry| entry.changed_at_ms),
+                    parsed: unchanged.map_or_else(OnceLock::new, |entry| entry.parsed.clone()),
+                };
+                (Arc::new(entry), CacheStatus::Miss)
+            }
+            (_, Some(entry)) => return Ok((entry, CacheStatus::Stale)),
+            (Ok(reply), None) => {
+                return Err(Response::text(
+                    502,
+                    &format!("Upstream answered HTTP {}", reply.status),
+                ))
+            }
+            (Err(message), None) => {
+                return Err(Response::text(
+                    502,
+                    &format!("Upstream unreachable: {message}"),
+                ))
+            }
+        };
+        let mut cache = self.cache.lock().unwrap();
+        let entries = cache.entry(source.mount.clone()).or_default();
+        entries.insert(url.to_string(), entry.clone());
+        while entries.len() > source.max_cached_calendars.max(1) {
+            let Some(oldest) = entries
+                .iter()
+                .filter(|(key, _)| key.as_str() != url)
+                .min_by_key(|(_, entry)| entry.validated_at_ms)
+                .map(|(key, _)| key.clone())
+            else {
+                break;
+            };
+            entries.remove(&oldest);
+        }
+        Ok((entry, status))
+    }
+
+    /// The calendar at `target` (path and query) of a source, for routes
+    /// that publish derived content.
+    pub fn calendar(
+        &self,
+        mount: &str,
+        target: &str,
+        now_ms: u64,
+    ) -> Result<Arc<CachedCalendar>, Response> {
+        let Some(source) = self.config.source(mount) else {
+            return Err(Response::text(404, "Unknown source"));
+        };
+        let (path, query) = match target.split_once('?') {
+            Some((path, query)) => (path, Some(query)),
+            None => (target, None),
+        };
+        let Some(url) = upstream_url(source, path, query) else {
+            return Err(Response::text(403, "Path not allowed for this source"));
+        };
+        self.load(source, &url, now_ms).map(|(entry, _)| entry)
+    }
+
+    /// Serves `rest` (the path after the mount) of the given source.
+    pub fn handle(
+        &self,
+        mount: &str,
+        rest: &str,
+        representation: Representation,
+        request: &Request,
+        now_ms: u64,
+    ) -> Response {
+        let Some(source) = self.config.source(mount) else {
+            return Response::text(404, "Unknown source");
+        };
+        let Some(url) = upstream_url(source, rest, request.query()) else {
+            return Response::text(403, "Path not allowed for this source");
+        };
+        let (entry, status) = match self.load(source, &url, now_ms) {
+            Ok(loaded) => loaded,
+            Err(response) => return response,
+        };
+
+        let (etag, content_type, body) = match representation {
+            Representation::Ics => (
+                entry.etag.clone(),
+                entry.content_type.clone(),
+                entry.body.clone(),
+            ),
+            Representation::NormalizedJson => (
+                format!("\"n-{}", entry.etag.trim_start_matches('"')),
+                "application/json".to_string(),
+                entry.normalized_json(),
+            ),
+        };
+        let response = if etag_matches(request.header("If-None-Match"), &etag) {
+            Response::empty(304)
+        } else {
+            Response::new(200, &content_type, body)
+        };
+        let mut response = response
+            .with_header("ETag", &etag)
+            .with_header(
+                "Cache-Control",
+                &format!("max-age={}", self.config.cache_seconds),
+            )
+            .with_header("X-Cache", status.as_str());
+        if let Some(last_modified) = &entry.last_modified {
+            response = response.with_header("Last-Modified", last_modified);
+        }
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ICS: &str = "BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nUID:1\r\nSUMMARY:CM Algo\r\n\
+DESCRIPTION:M1 INFO\\nDUPONT Jean\r\nDTSTART:20250106T080000\r\nDTEND:20250106T100000\r\n\
+END:VEVENT\r\nEND:VCALENDAR\r\n";
+
+    /// Answers from a script and records what was asked.
+    struct Scripted {
+        replies: Mutex<VecDeque<Result<UpstreamReply, String>>>,
+        seen: Arc<Mutex<Vec<UpstreamRequest>>>,
+    }
+
+    impl Upstream for Scripted {
+        fn fetch(&self, request: &UpstreamRequest) -> Result<UpstreamReply, String> {
+            self.seen.lock().unwrap().push(request.clone());
+            self.replies
+                .lock()
+                .unwrap()
+                .pop_front()
+                .expect("unexpected upstream request")
+        }
+    }
+
+    fn reply(status: u16, body: &str) -> Result<UpstreamReply, String> {
+        Ok(UpstreamReply {
+            status,
+            body: body.as_bytes().to_vec(),
+            etag: Some("\"v1\"".to_string()),
+            last_modified: Some("Mon, 06 Jan 2025 08:00:00 GMT".to_string()),
+            content_type: None,
+        })
+    }
+
+    fn proxy(
+        replies: Vec<Result<UpstreamReply, String>>,
+        max_requests_per_minute: u32,
+    ) -> (Proxy, Arc<Mutex<Vec<UpstreamRequest>>>) {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let config = ServerConfig {
+            cache_seconds: 60,
+            sources: vec![SourceConfig {
+                origin: "http://upstream.test".to_string(),
+                max_requests_per_minute,
+                ..SourceConfig::default()
+            }],
+            ..ServerConfig::default()
+        };
+        let upstream = Scripted {
+            replies: Mutex::new(replies.into()),
+            seen: seen.clone(),
+        };
+        (Proxy::new(config, Box::new(upstream)), seen)
+    }
+
+    const PATH: &str = "jsp/custom/modules/plannings/abc.shu";
+
+    fn get(proxy: &Proxy, request: &Request, now_ms: u64) -> Response {
+        proxy.handle("p", PATH, Representation::Ics, request, now_ms)
+    }
+
+    #[test]
+    fn caches_and_revalidates_with_upstream_validators() {
+        let (proxy, seen) = proxy(vec![reply(200, ICS), reply(304, "")], 10);
+        let request = Request::get(&format!("/p/{PATH}?projectId=1"));
+
+        let first = get(&proxy, &request, 0);
+        assert_eq!(first.status, 200);
+        assert_eq!(first.body, ICS.as_bytes());
+        assert_eq!(first.header("X-Cache"), Some("miss"));
+        assert_eq!(
+            seen.lock().unwrap()[0].url,
+            format!("http://upstream.test/{PATH}?projectId=1")
+        );
+
+        let etag = first.header("ETag").unwrap().to_string();
+        let cached = get(
+            &proxy,
+            &request.clone().with_header("If-None-Match", &etag),
+            30_000,
+        );
+        assert_eq!(cached.status, 304);
+        assert_eq!(cached.header("X-Cache"), Some("hit"));
+
+        let revalidated = get(&proxy, &request, 61_000);
+        assert_eq!(revalidated.status, 200);
+        assert_eq!(revalidated.header("X-Cache"), Some("revalidated"));
+        assert_eq!(revalidated.body, ICS.as_bytes());
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen.len(), 2);
+        assert_eq!(seen[1].if_none_match.as_deref(), Some("\"v1\""));
+        assert!(seen[1].if_modified_since.is_some());
+    }
+
+    #[test]
+    fn enforces_allowlist_and_rate_limit() {
+        let (proxy, _) = proxy(
+            vec![reply(200, ICS), Err("connection refused".to_string())],
+            1,
+        );
+
+        let outside = proxy.handle(
+            "p",
+            "admin/../jsp",
+            Representation::Ics,
+            &Request::get("/"),
+            0,
+        );
+        assert_eq!(outside.status, 403);
+        let other = proxy.handle(
+            "p",
+            "etc/passwd",
+            Representation::Ics,
+            &Request::get("/"),
+            0,
+        );
+        assert_eq!(other.status, 403);
+        // Inside the allowlist as written, outside once decoded upstream.
+        for escape in ["%2e%2E/admin", "..%2Fadmin", "..%5cadmin"] {
+            let path = format!("jsp/custom/modules/plannings/{escape}");
+            let response = proxy.handle("p", &path, Representation::Ics, &Request::get("/"), 0);
+            assert_eq!(response.status, 403, "{path}");
+        }
+        assert_eq!(
+            proxy
+                .handle("x", PATH, Representation::Ics, &Request::get("/"), 0)
+                .status,
+            404
+        );
+
+        assert_eq!(get(&proxy, &Request::get("/p/a?1"), 0).status, 200);
+        let limited = get(&proxy, &Request::get("/p/a?2"), 1_000);
+        assert_eq!(limited.status, 429);
+        assert_eq!(limited.header("Retry-After"), Some("59"));
+
+        // The budget is back after a minute; a failing upstream then serves
+        // the stale copy.
+        let stale = get(&proxy, &Request::get("/p/a?1"), 120_000);
+        assert_eq!(stale.status, 200);
+        assert_eq!(stale.header("X-Cache"), Some("stale"));
+    }
+
+    #[test]
+    fn keeps_a_bounded_number_of_calendars_per_source() {
+        let (mut proxy, seen) = proxy(vec![reply(200, ICS); 4], 10);
+        proxy.config.sources[0].max_cached_calendars = 2;
+        let query = |n: u32| Request::get(&format!("/p/{PATH}?x={n}"));
+
+        for n in 1..=3 {
+            assert_eq!(get(&proxy, &query(n), u64::from(n) * 1_000).status, 200);
+        }
+        assert_eq!(proxy.cache.lock().unwrap()["p"].len(), 2);
+        // The least recently validated copy went first.
+        let kept = get(&proxy, &query(2), 5_000);
+        assert_eq!(kept.header("X-Cache"), Some("hit"));
+        let dropped = get(&proxy, &query(1), 6_000);
+        assert_eq!(dropped.header("X-Cache"), Some("miss"));
+        assert_eq!(seen.lock().unwrap().len(), 4);
+    }
+
+    #[test]
+    fn serves_normalized_json() {
+        let (proxy, _) = proxy(vec![reply(200, ICS)], 10);
+
+        let response = proxy.handle(
+            "p",
+            PATH,
+            Representation::NormalizedJson,
+            &Request::get("/json/p/x"),
+            0,
+        );
+
+        assert_eq!(response.status, 200);
+        assert_eq!(response.header("Content-Type"), Some("application/json"));
+        assert!(response.header("ETag").unwrap().starts_with("\"n-"));
+        let payload: serde_json::Value = serde_json::from_slice(&response.body).unwrap();
+        assert_eq!(payload["events"][0]["subject"], "Algo");
+        assert_eq!(payload["diagnostics"]["calendars_parsed"], 1);
+    }
+}
@@ -0,0 +1,87 @@
+//! URL layout of the server.
+
+use crate::dav::{self, Dav};
+use crate::feeds;
+use crate::http::{Request, Response};
+use crate::proxy::{Proxy, Representation};
+use agendum_core::feeds::FeedStore;
+
+pub struct App {
+    proxy: Proxy,
+    feeds: FeedStore,
+}
+
+impl App {
+    pub fn new(proxy: Proxy) -> Result<App, String> {
+        let feeds = FeedStore::new(proxy.config().feeds.clone())?;
+        Ok(App { proxy, feeds })
+    }
+
+    /// `/health`, `/{mount}/{path}` (upstream ICS), `/json/{mount}/{path}`
+    /// (normalized), `/feeds`, `/feeds/{name}.ics` and, when enabled, `/dav/…`.
+    fn route(&self, request: &Request, now_ms: u64) -> Response {
+        let path = request.path().trim_start_matches('/');
+        if self.proxy.config().caldav {
+            if path == ".well-known/caldav" {
+                return Response::empty(301).with_header("Location", dav::ROOT);
+            }
+            if path == "dav" || path.starts_with("dav/") {
+                let dav = Dav {
+                    proxy: &self.proxy,
+                    feeds: &self.feeds,
+                    now_ms,
+                };
+                return dav.handle(request);
+            }
+        }
+        if request.method == "OPTIONS" {
+            return Response::empty(204)
+                .with_header("Access-Control-Allow-Methods", "GET, HEAD, OPTIONS")
+                .with_header(
+                    "Access-Control-Allow-Headers",
+                    "If-None-Match, If-Modified-Since",
+                )
+                .with_header("Access-Control-Max-Age", "86400");
+        }
+        if request.method != "GET" && request.method != "HEAD" {
+            return Response::text(405, "Method not allowed")
+                .with_header("Allow", "GET, HEAD, OPTIONS");
+        }
+        if path == "health" {
+            return Response::text(200, "ok");
+        }
+        if path == "feeds" {
+            return feeds::list(&self.feeds);
+        }
+        if let Some(name) = path.strip_prefix("feeds/") {
+            return feeds::serve(&self.proxy, &self.feeds, name, request, now_ms);
+        }
+
+        let (representation, path) = match path.strip_prefix("json/") {
+            Some(rest) => (Representation::NormalizedJson, rest),
+            None => (Representation::Ics, path),
+        };
+        match path.split_once('/') {
+            Some((mount, rest)) => self
+                .proxy
+                .handle(mount, rest, representation, request, now_ms),
+            None => Response::text(404, "Not found"),
+        }
+    }
+
+    pub fn handle(&self, request: &Request, now_ms: u64) -> Response {
+        let mut response = self.route(request, now_ms);
+        if let Some(origin) = &self.proxy.config().allow_origin {
+            response = response
+                .with_header("Access-Control-Allow-Origin", origin)
+                .with_header(
+                    "Access-Control-Expose-Headers",
+                    "ETag, Last-Modified, X-Cache",
+                );
+        }
+        if request.method == "HEAD" {
+            response.body.clear();
+        }
+        response
+    }
+}
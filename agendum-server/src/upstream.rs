@@ -0,0 +1,65 @@
+use crate::proxy::{Upstream, UpstreamReply, UpstreamRequest};
+use std::io::Read;
+use std::time::Duration;
+
+/// Largest calendar accepted from upstream.
+const MAX_BODY_BYTES: u64 = 32 * 1024 * 1024;
+
+pub struct HttpUpstream {
+    agent: ureq::Agent,
+}
+
+impl HttpUpstream {
+    pub fn new(timeout: Duration) -> HttpUpstream {
+        HttpUpstream {
+            agent: ureq::AgentBuilder::new()
+                .timeout(timeout)
+                .user_agent(concat!("agendum-server/", env!("CARGO_PKG_VERSION")))
+                .build(),
+        }
+    }
+}
+
+fn reply(response: ureq::Response) -> Result<UpstreamReply, String> {
+    let status = response.status();
+    let header = |name: &str| response.header(name).map(str::to_string);
+    let etag = header("ETag");
+    let last_modified = header("Last-Modified");
+    let content_type = header("Content-Type");
+    let mut body = Vec::new();
+    response
+        .into_reader()
+        .take(MAX_BODY_BYTES + 1)
+        .read_to_end(&mut body)
+        .map_err(|e| e.to_string())?;
+    if body.len() as u64 > MAX_BODY_BYTES {
+        return Err(format!(
+            "Upstream calendar is larger than {} MiB",
+            MAX_BODY_BYTES / (1024 * 1024)
+        ));
+    }
+    Ok(UpstreamReply {
+        status,
+        body,
+        etag,
+        last_modified,
+        content_type,
+    })
+}
+
+impl Upstream for HttpUpstream {
+    fn fetch(&self, request: &UpstreamRequest) -> Result<UpstreamReply, String> {
+        let mut call = self.agent.get(&request.url);
+        if let Some(etag) = &request.if_none_match {
+            call = call.set("If-None-Match", etag);
+        }
+        if let Some(date) = &request.if_modified_since {
+            call = call.set("If-Modified-Since", date);
+        }
+        match call.call() {
+            Ok(response) => reply(response),
+            Err(ureq::Error::Status(_, response)) => reply(response),
+            Err(error) => Err(error.to_string()),
+        }
+    }
+}
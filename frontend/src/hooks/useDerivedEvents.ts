@@ -26,6 +26,34 @@ const parseIcsDateTime = (raw?: string): Date | null => {
   return new Date(+y, +mo - 1, +d, +hh, +mm, +ss);
 };
 
+const eventInstant = (utcMs?: number | null, fallbackIso?: string): Date | null => {
+  if (typeof utcMs === 'number' && Number.isFinite(utcMs)) return new Date(utcMs);
+  return parseIcsDateTime(fallbackIso);
+};
+
+type WallClock = { dateKey: string; minutes: number; weekday: number };
+
+// Date, time and weekday as shown in the calendar's timezone. `start_iso` and
+// `end_iso` already carry that local time, so filters read them rather than
+// the Date getters, which follow the browser's timezone.
+const wallClock = (iso: string | undefined, instant: Date | undefined): WallClock | null => {
+  const m = /^(\d{4})-(\d{2})-(\d{2})T(\d{2}):(\d{2})/.exec(iso || '');
+  if (m) {
+    const [, y, mo, d, hh, mm] = m;
+    return {
+      dateKey: `${y}-${mo}-${d}`,
+      minutes: +hh * 60 + +mm,
+      weekday: new Date(Date.UTC(+y, +mo - 1, +d)).getUTCDay(),
+    };
+  }
+  if (!instant) return null;
+  return {
+    dateKey: toLocalDateKey(instant),
+    minutes: instant.getHours() * 60 + instant.getMinutes(),
+    weekday: instant.getDay(),
+  };
+};
+
 const parseTimeToMinutes = (t: string) => {
   const [h, m] = t.split(':').map(Number);
   if (Number.isNaN(h) || Number.isNaN(m)) return null;
@@ -71,8 +99,8 @@ export function useDerivedEvents({
 
     const rawEvents: Omit<EnrichedEvent, 'is_duplicate'>[] = calendars
       .flatMap((cal) => cal.events.map((ev) => {
-        const startDate = parseIcsDateTime(ev.start_iso);
-        const endDate = parseIcsDateTime(ev.end_iso);
+        const startDate = eventInstant(ev.start_utc_ms, ev.start_iso);
+        const endDate = eventInstant(ev.end_utc_ms, ev.end_iso);
         const startMs = startDate?.getTime() ?? NaN;
         const endMs = endDate?.getTime() ?? NaN;
         const computedDuration = Number.isFinite(startMs) && Number.isFinite(endMs) && endMs > startMs
@@ -172,16 +200,16 @@ export function useDerivedEvents({
 
     if (filters.dateStart) {
       result = result.filter((ev) => {
-        const d = ev.start_date;
-        if (!d) return false;
-        return toLocalDateKey(d) >= filters.dateStart;
+        const start = wallClock(ev.start_iso, ev.start_date);
+        if (!start) return false;
+        return start.dateKey >= filters.dateStart;
       });
     }
     if (filters.dateEnd) {
       result = result.filter((ev) => {
-        const d = ev.start_date;
-        if (!d) return false;
-        return toLocalDateKey(d) <= filters.dateEnd;
+        const start = wallClock(ev.start_iso, ev.start_date);
+        if (!start) return false;
+        return start.dateKey <= filters.dateEnd;
       });
     }
 
@@ -189,11 +217,11 @@ export function useDerivedEvents({
     const endMin = filters.endTime ? parseTimeToMinutes(filters.endTime) : null;
     if (startMin !== null || endMin !== null) {
       result = result.filter((ev) => {
-        const startDate = ev.start_date;
-        const endDate = ev.end_date;
-        if (!startDate || !endDate) return false;
-        const evStart = startDate.getHours() * 60 + startDate.getMinutes();
-        const evEnd = endDate.getHours() * 60 + endDate.getMinutes();
+        const start = wallClock(ev.start_iso, ev.start_date);
+        const end = wallClock(ev.end_iso, ev.end_date);
+        if (!start || !end) return false;
+        const evStart = start.minutes;
+        const evEnd = end.minutes;
         if (startMin !== null && endMin !== null) {
           return evStart <= endMin && evEnd >= startMin;
         }
@@ -205,9 +233,9 @@ export function useDerivedEvents({
 
     if (filters.days.length > 0) {
       result = result.filter((ev) => {
-        const date = ev.start_date;
-        if (!date) return false;
-        let day = date.getDay(); // 0=Sun, 1=Mon
+        const start = wallClock(ev.start_iso, ev.start_date);
+        if (!start) return false;
+        let day = start.weekday; // 0=Sun, 1=Mon
         if (day === 0) day = 7; // Normalize to 1=Mon, 7=Sun
         return filters.days.includes(day);
       });
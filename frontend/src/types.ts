@@ -14,9 +14,15 @@ export interface NormalizedEvent {
     start_iso: string;
     end_iso: string;
     duration_hours: number;
+    duration_minutes?: number;
     teachers: string[];
     promos: string[];
     cleaned_description: string;
+    start_utc_ms?: number | null;
+    end_utc_ms?: number | null;
+    start_rfc3339?: string | null;
+    end_rfc3339?: string | null;
+    timezone?: string;
 }
 
 export interface EnrichedEvent extends NormalizedEvent {
@@ -39,6 +45,7 @@ export interface ParseDiagnostics {
     parser_errors: number;
     skipped_events_without_uid: number;
     parser_error_messages: string[];
+    recurring_events_not_expanded?: number;
 }
 
 export interface ParseAndNormalizeDetailedResult {
@@ -67,3 +74,32 @@ export interface Calendar {
 export interface AppState {
     calendars: Calendar[];
 }
+
+/** Minutes per session type, as computed by the core service report. */
+export interface ServiceBreakdown {
+    cm: number;
+    td: number;
+    tp: number;
+    project: number;
+    reunion: number;
+    exam: number;
+    other: number;
+    core: number;
+    teaching: number;
+    total: number;
+    sessions: number;
+}
+
+export interface ServiceReport {
+    scope: 'total' | 'done' | 'upcoming';
+    as_of_ms?: number | null;
+    teacher?: string | null;
+    summary: ServiceBreakdown;
+    undated: ServiceBreakdown;
+    teachers: {
+        teacher: string;
+        is_unknown: boolean;
+        subjects: { subject: string; breakdown: ServiceBreakdown }[];
+        totals: ServiceBreakdown;
+    }[];
+}
@@ -1,5 +1,6 @@
-import { useEffect, useMemo, useState, useCallback } from 'react';
-import type { EnrichedEvent } from '../types';
+import { useEffect, useMemo, useState } from 'react';
+import initCore, { compute_service_report } from '../pkg/agendum_core';
+import type { EnrichedEvent, ServiceReport } from '../types';
 import { useLang, useT } from '../i18n';
 
 interface Props {
@@ -9,23 +10,7 @@ interface Props {
     onSelectSubject?: (subject: string) => void;
 }
 
-interface Stats {
-    cm: number;
-    td: number;
-    tp: number;
-    project: number;
-    reunion: number;
-    exam: number;
-    other: number;
-    total: number;
-    count: number;
-}
-
-interface TeacherData {
-    name: string;
-    subjects: Map<string, Stats>;
-    grandTotal: number;
-}
+const HOURS = (minutes: number) => minutes / 60;
 
 export function ServiceDashboard({ events, selectedTeacher, isMobile = false, onSelectSubject }: Props) {
     const t = useT();
@@ -33,26 +18,7 @@ export function ServiceDashboard({ events, selectedTeacher, isMobile = false, on
     const showEmpty = false;
     const [serviceScope, setServiceScope] = useState<'total' | 'done' | 'todo'>('total');
     const [nowTs, setNowTs] = useState(() => Date.now());
-
-    const splitTeacherNames = useCallback((value?: string) => {
-        if (!value) return [];
-        return value
-            .split(',')
-            .map(v => v.trim())
-            .filter(Boolean);
-    }, []);
-
-    const isUnknownTeacherToken = useCallback((value: string) => {
-        const trimmed = value.trim();
-        if (!trimmed) return true;
-        if (trimmed === '—') return true;
-        const lower = trimmed.toLowerCase();
-        return lower === 'unknown teacher' || lower === t.unknown_teacher.toLowerCase();
-    }, [t.unknown_teacher]);
-
-    const unknownTeacherLabel = useMemo(() => (
-        selectedTeacher ? '—' : t.unknown_teacher
-    ), [selectedTeacher, t.unknown_teacher]);
+    const [coreReady, setCoreReady] = useState(false);
 
     // Column Viz Toggles
     const [cols, setCols] = useState({
@@ -81,152 +47,86 @@ export function ServiceDashboard({ events, selectedTeacher, isMobile = false, on
         return () => window.clearInterval(id);
     }, []);
 
-    const baseEvents = useMemo(() => {
-        const teacherFiltered = !selectedTeacher
-            ? events
-            : (() => {
-                const target = selectedTeacher.toLowerCase();
-                return events.filter(ev => {
-                    const teacherStr = (ev.extractedTeacher || '').toLowerCase();
-                    const teacherTokens = splitTeacherNames(teacherStr);
-                    const matchesSelected = teacherTokens.some(name => name.toLowerCase() === target);
-                    const isUnknown = teacherTokens.length === 0 || teacherTokens.every(isUnknownTeacherToken);
-                    return matchesSelected || isUnknown;
-                });
-            })();
-
-        if (serviceScope === 'total') return teacherFiltered;
-
-        return teacherFiltered.filter(ev => {
-            const start = ev.start_date;
-            const end = ev.end_date;
-            const startMs = start?.getTime();
-            const endMs = end?.getTime();
+    useEffect(() => {
+        let cancelled = false;
+        void initCore().then(() => {
+            if (!cancelled) setCoreReady(true);
+        });
+        return () => {
+            cancelled = true;
+        };
+    }, []);
 
-            if (typeof endMs === 'number' && Number.isFinite(endMs)) {
-                return serviceScope === 'done' ? endMs <= nowTs : endMs > nowTs;
-            }
-            if (typeof startMs === 'number' && Number.isFinite(startMs)) {
-                return serviceScope === 'done' ? startMs <= nowTs : startMs > nowTs;
+    // Totals come from the core service report; this view only converts them
+    // to hours and applies the column toggles.
+    const report = useMemo<ServiceReport | null>(() => {
+        if (!coreReady) return null;
+        return compute_service_report(
+            events.filter(ev => !ev.is_duplicate),
+            {
+                teacher: selectedTeacher || null,
+                as_of_ms: nowTs,
+                scope: serviceScope === 'todo' ? 'upcoming' : serviceScope
             }
-            return false;
-        });
-    }, [events, selectedTeacher, serviceScope, nowTs, splitTeacherNames, isUnknownTeacherToken]);
+        ) as ServiceReport;
+    }, [coreReady, events, selectedTeacher, serviceScope, nowTs]);
 
     const summary = useMemo(() => {
-        const totals = { cm: 0, td: 0, tp: 0, project: 0, reunion: 0, exam: 0, other: 0 };
-        baseEvents.forEach(ev => {
-            if (ev.is_duplicate) return;
-            const duration = ev.duration_hours || 0;
-            const type = (ev.type_ || '').toUpperCase();
-            if (type.includes('CM')) totals.cm += duration;
-            else if (type.includes('TD')) totals.td += duration;
-            else if (type.includes('TP')) totals.tp += duration;
-            else if (type.includes('PROJET') || type.includes('PROJECT')) totals.project += duration;
-            else if (type.includes('RÉUNION') || type.includes('REUNION')) totals.reunion += duration;
-            else if (type.includes('EXAM') || type.includes('DS') || type.includes('CT') || type.includes('CC')) totals.exam += duration;
-            else totals.other += duration;
-        });
-        const totalCore = totals.cm + totals.td + totals.tp;
-        const totalTeaching = totalCore + totals.project;
-        return { ...totals, totalCore, totalTeaching };
-    }, [baseEvents]);
+        const totals = report?.summary;
+        return {
+            cm: HOURS(totals?.cm ?? 0),
+            td: HOURS(totals?.td ?? 0),
+            tp: HOURS(totals?.tp ?? 0),
+            project: HOURS(totals?.project ?? 0),
+            reunion: HOURS(totals?.reunion ?? 0),
+            exam: HOURS(totals?.exam ?? 0),
+            other: HOURS(totals?.other ?? 0),
+            totalCore: HOURS(totals?.core ?? 0),
+            totalTeaching: HOURS(totals?.teaching ?? 0)
+        };
+    }, [report]);
 
     const teacherStats = useMemo(() => {
-        const teachers = new Map<string, TeacherData>();
-
-        baseEvents.forEach(ev => {
-            // Logic: Skip if marked as duplicate (Smart Deduplication)
-            // But only for the same teacher! 
-            // The is_duplicate flag from App.tsx assumes if same time/subject/type 
-            // it's likely a duplicate across promo calendars.
-            if (ev.is_duplicate) return;
-
-            const teacherStr = (ev.extractedTeacher || '').trim();
-            const teacherTokens = splitTeacherNames(teacherStr);
-            const matchesSelected = selectedTeacher
-                ? teacherTokens.some(name => name.toLowerCase() === selectedTeacher.toLowerCase())
-                : false;
-            const isUnknown = teacherTokens.length === 0 || teacherTokens.every(isUnknownTeacherToken);
-
-            let teacherNames: string[] = [];
-            if (selectedTeacher) {
-                if (matchesSelected) teacherNames = [selectedTeacher];
-                else if (isUnknown) teacherNames = [unknownTeacherLabel];
-                else return;
-            } else {
-                teacherNames = (!isUnknown && teacherTokens.length > 0) ? teacherTokens : [unknownTeacherLabel];
-            }
-
-            teacherNames.forEach((teacherName: string) => {
-                if (!teachers.has(teacherName)) {
-                    teachers.set(teacherName, { name: teacherName, subjects: new Map(), grandTotal: 0 });
-                }
-
-                const tData = teachers.get(teacherName)!;
-                let subject = ev.subject || t.unknown_subject;
-                subject = subject.trim();
-
-                if (!tData.subjects.has(subject)) {
-                    tData.subjects.set(subject, { cm: 0, td: 0, tp: 0, project: 0, reunion: 0, exam: 0, other: 0, total: 0, count: 0 });
-                }
-
-                const entry = tData.subjects.get(subject)!;
-                const duration = ev.duration_hours || 0;
-                const type = (ev.type_ || "").toUpperCase();
-
-                if (type.includes("CM")) entry.cm += duration;
-                else if (type.includes("TD")) entry.td += duration;
-                else if (type.includes("TP")) entry.tp += duration;
-                else if (type.includes("PROJET") || type.includes("PROJECT")) entry.project += duration;
-                else if (type.includes("RÉUNION") || type.includes("REUNION")) entry.reunion += duration;
-                else if (type.includes("EXAM") || type.includes("DS") || type.includes("CT") || type.includes("CC")) entry.exam += duration;
-                else entry.other += duration;
-
-                entry.count++;
-            });
-        });
-
-        // Convert to array and calculate filtered totals
-        return Array.from(teachers.values())
-            .map(t => {
-                let tGrandTotal = 0;
-                const totalsAll = { cm: 0, td: 0, tp: 0, project: 0, reunion: 0, exam: 0, other: 0 };
-                const subjectList = Array.from(t.subjects.entries()).map(([name, s]) => {
-                    totalsAll.cm += s.cm;
-                    totalsAll.td += s.td;
-                    totalsAll.tp += s.tp;
-                    totalsAll.project += s.project;
-                    totalsAll.reunion += s.reunion;
-                    totalsAll.exam += s.exam;
-                    totalsAll.other += s.other;
-                    const filteredTotal = s.cm + s.td + s.tp + s.project;
-                    tGrandTotal += filteredTotal;
-                    return { name, ...s, filteredTotal };
-                }).filter(row => {
+        if (!report) return [];
+        return report.teachers
+            .map(teacher => {
+                const subjectList = teacher.subjects.map(({ subject, breakdown }) => ({
+                    name: subject || t.unknown_subject,
+                    cm: HOURS(breakdown.cm),
+                    td: HOURS(breakdown.td),
+                    tp: HOURS(breakdown.tp),
+                    project: HOURS(breakdown.project),
+                    reunion: HOURS(breakdown.reunion),
+                    exam: HOURS(breakdown.exam),
+                    other: HOURS(breakdown.other),
+                    count: breakdown.sessions,
+                    filteredTotal: HOURS(breakdown.teaching)
+                })).filter(row => {
                     if (showEmpty) return true;
                     const hasCore = row.cm > 0 || row.td > 0 || row.tp > 0 || row.project > 0;
                     const hasExtras = (cols.exam && row.exam > 0) || (cols.reunion && row.reunion > 0) || (cols.other && row.other > 0);
                     return hasCore || hasExtras;
                 }).sort((a, b) => b.filteredTotal - a.filteredTotal);
 
-                return { ...t, subjectList, grandTotal: tGrandTotal, totalsAll };
+                const totals = teacher.totals;
+                return {
+                    name: teacher.is_unknown ? t.unknown_teacher : teacher.teacher,
+                    subjectList,
+                    grandTotal: HOURS(totals.teaching),
+                    totalsAll: {
+                        cm: HOURS(totals.cm),
+                        td: HOURS(totals.td),
+                        tp: HOURS(totals.tp),
+                        project: HOURS(totals.project),
+                        reunion: HOURS(totals.reunion),
+                        exam: HOURS(totals.exam),
+                        other: HOURS(totals.other)
+                    }
+                };
             })
             // Hide teacher sections when no visible rows after column filtering
-            .filter(t => t.subjectList.length > 0)
-            .sort((a, b) => {
-                if (selectedTeacher) {
-                    const aKey = a.name.toLowerCase();
-                    const bKey = b.name.toLowerCase();
-                    const selectedKey = selectedTeacher.toLowerCase();
-                    if (aKey === selectedKey && bKey !== selectedKey) return -1;
-                    if (bKey === selectedKey && aKey !== selectedKey) return 1;
-                    if (a.name === unknownTeacherLabel && b.name !== unknownTeacherLabel) return -1;
-                    if (b.name === unknownTeacherLabel && a.name !== unknownTeacherLabel) return 1;
-                }
-                return b.grandTotal - a.grandTotal;
-            });
-    }, [baseEvents, cols, showEmpty, t.unknown_subject, selectedTeacher, splitTeacherNames, isUnknownTeacherToken, unknownTeacherLabel]);
+            .filter(teacher => teacher.subjectList.length > 0);
+    }, [report, cols, showEmpty, t.unknown_subject, t.unknown_teacher]);
 
     return (
         <div className={`service-dashboard fade-in page-scroll ${isMobile ? 'service-dashboard--mobile' : ''}`}>